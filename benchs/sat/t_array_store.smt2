@@ -0,0 +1,18 @@
+; storing at distinct indices does not force the elements to be equal
+; :status sat
+
+(declare-sort I 0)
+(declare-sort E 0)
+(declare-fun a () (Array I E))
+(declare-fun b () (Array I E))
+(declare-fun i () I)
+(declare-fun j () I)
+(declare-fun v () E)
+
+(assert (= b (store a i v)))
+(assert (not (= (select b j) v)))
+(assert (not (= a b)))
+
+(check-sat) ; sat
+
+(exit)
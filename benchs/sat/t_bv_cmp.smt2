@@ -0,0 +1,14 @@
+; signed and unsigned comparisons differ
+; :status sat
+
+(declare-fun x () (_ BitVec 4))
+(declare-fun y () (_ BitVec 4))
+
+(assert (bvult x y))
+(assert (bvsgt x y))
+(assert (= (bvmul x #x3) (bvneg y)))
+
+(check-sat) ; sat
+(get-value (x y))
+
+(exit)
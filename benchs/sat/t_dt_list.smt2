@@ -0,0 +1,20 @@
+
+; test for datatypes
+; :status sat
+
+(declare-sort elt 0)
+(declare-datatypes ((list 0))
+  (((nil) (cons (head elt) (tail list)))))
+
+(declare-fun a () elt)
+(declare-fun l1 () list)
+(declare-fun l2 () list)
+
+(assert (is-cons l1))
+(assert (= (tail l1) l2))
+(assert (not (= l1 l2)))
+(assert (= (head l2) a))
+
+(check-sat) ; sat
+
+(exit)
@@ -0,0 +1,26 @@
+; the model gives values to booleans, reals, uninterpreted constants and
+; functions, and `get-value` evaluates compound terms in it
+; :status sat
+
+(set-option :produce-models true)
+(declare-sort U 0)
+(declare-fun p () Bool)
+(declare-fun x () Real)
+(declare-fun y () Real)
+(declare-fun a () U)
+(declare-fun b () U)
+(declare-fun f (U) U)
+(declare-fun g (U) Real)
+
+(assert (or p (< x 0)))
+(assert (not p))
+(assert (= y (+ x 1)))
+(assert (= (f a) b))
+(assert (not (= a b)))
+(assert (= (g b) y))
+
+(check-sat) ; sat
+(get-model)
+(get-value (p x y (f a) (g (f a)) (+ x y) (= (g (f a)) y)))
+
+(exit)
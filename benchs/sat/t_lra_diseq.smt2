@@ -0,0 +1,16 @@
+; the model must pick δ small enough to keep the disequalities true
+; :status sat
+
+(declare-fun x () Real)
+(declare-fun y () Real)
+
+(assert (< x y))
+(assert (< y 1))
+(assert (> x 0))
+(assert (not (= (+ x (/ 1 2)) y)))
+(assert (not (= (* 2 x) y)))
+(assert (not (= x (/ 1 2))))
+
+(check-sat) ; sat
+
+(exit)
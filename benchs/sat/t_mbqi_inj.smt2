@@ -0,0 +1,13 @@
+; an axiom that is satisfied by the candidate model, checked by MBQI
+; :status sat
+
+(declare-sort U 0)
+(declare-fun f (U) U)
+(declare-fun a () U)
+(declare-fun b () U)
+
+(assert (forall ((x U)) (=> (= (f x) a) (= x b))))
+(assert (= (f b) a))
+(assert (not (= a b)))
+
+(check-sat) ; sat
@@ -0,0 +1,18 @@
+; declarations made inside a scope disappear on `pop`, and are
+; not part of later models
+; :status sat
+
+(declare-fun x () Real)
+
+(push 1)
+(declare-fun y () Real)
+(assert (< x y))
+(check-sat) ; sat
+(pop 1)
+
+(declare-fun y () Bool)
+(assert (and y (> x 0)))
+(check-sat) ; sat
+(get-model) ; `y` is a boolean
+
+(exit)
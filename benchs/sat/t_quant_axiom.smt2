@@ -0,0 +1,13 @@
+; an axiom that does not contradict the ground part
+; :status sat
+
+(declare-sort U 0)
+(declare-fun f (U) U)
+(declare-fun a () U)
+(declare-fun b () U)
+
+(assert (forall ((x U)) (= (f (f x)) (f x))))
+(assert (= (f a) b))
+(assert (not (= a b)))
+
+(check-sat) ; sat
@@ -0,0 +1,16 @@
+; the pattern `(f x)` does not bind `y`, so it is ignored and a trigger
+; is inferred instead
+; :status sat
+
+(declare-sort U 0)
+(declare-fun f (U) U)
+(declare-fun p (U U) Bool)
+(declare-fun a () U)
+
+(assert (forall ((x U) (y U)) (! (p x y) :pattern ((f x)))))
+(assert (= (f a) a))
+(assert (p a a))
+
+(check-sat) ; sat
+
+(exit)
@@ -0,0 +1,12 @@
+; theory combination must not force `y = (+ x 1)`
+; :status sat
+
+(declare-fun f (Real) Real)
+(declare-fun x () Real)
+(declare-fun y () Real)
+
+(assert (<= x y))
+(assert (= (f (+ x 1)) 2))
+(assert (not (= (f y) 2)))
+
+(check-sat) ; sat
@@ -0,0 +1,16 @@
+; reals that only occur under uninterpreted symbols need distinct
+; values in the model, or `f` cannot tell `a` from `b`
+; :status sat
+
+(declare-fun f (Real) Real)
+(declare-fun p (Real) Bool)
+(declare-const a Real)
+(declare-const b Real)
+
+(assert (p (f a)))
+(assert (not (p (f b))))
+
+(check-sat) ; sat
+(get-model)
+
+(exit)
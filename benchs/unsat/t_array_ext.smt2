@@ -0,0 +1,17 @@
+; extensionality: arrays that differ are distinct at some index
+; :status unsat
+
+(declare-sort I 0)
+(declare-sort E 0)
+(declare-fun a () (Array I E))
+(declare-fun b () (Array I E))
+(declare-fun i () I)
+(declare-fun v () E)
+
+(assert (= b (store a i v)))
+(assert (= (select a i) v))
+(assert (not (= a b)))
+
+(check-sat) ; unsat
+
+(exit)
@@ -0,0 +1,18 @@
+; read-over-write, for equal and distinct indices
+; :status unsat
+
+(declare-sort I 0)
+(declare-sort E 0)
+(declare-fun a () (Array I E))
+(declare-fun i () I)
+(declare-fun j () I)
+(declare-fun v () E)
+(declare-fun w () E)
+
+(assert (not (= i j)))
+(assert (= (select (store (store a i v) j w) i) (select a j)))
+(assert (not (= v (select a j))))
+
+(check-sat) ; unsat
+
+(exit)
@@ -0,0 +1,13 @@
+; basic bit-vector arithmetic
+; :status unsat
+
+(declare-fun x () (_ BitVec 8))
+(declare-fun y () (_ BitVec 8))
+
+(assert (= (bvadd x y) #x0a))
+(assert (= (bvsub x y) #b00000010))
+(assert (not (= x #x06)))
+
+(check-sat) ; unsat
+
+(exit)
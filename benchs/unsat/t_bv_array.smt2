@@ -0,0 +1,14 @@
+; the read-over-write lemma introduces `(= i #b01)` during search,
+; which must be bit-blasted to be refuted
+; :status unsat
+
+(declare-fun a () (Array (_ BitVec 2) (_ BitVec 2)))
+(declare-fun i () (_ BitVec 2))
+(declare-fun j () (_ BitVec 2))
+(declare-fun v () (_ BitVec 2))
+
+(assert (= j #b00))
+(assert (= i (bvadd j #b01)))
+(assert (not (= (select (store a i v) #b01) v)))
+
+(check-sat) ; unsat
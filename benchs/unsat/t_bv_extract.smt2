@@ -0,0 +1,10 @@
+; extract and concat are inverse
+; :status unsat
+
+(declare-fun x () (_ BitVec 8))
+
+(assert (not (= x (concat ((_ extract 7 4) x) ((_ extract 3 0) x)))))
+
+(check-sat) ; unsat
+
+(exit)
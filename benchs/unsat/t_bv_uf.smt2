@@ -0,0 +1,13 @@
+; uninterpreted functions over bit-vectors
+; :status unsat
+
+(declare-fun f ((_ BitVec 4)) (_ BitVec 4))
+(declare-fun x () (_ BitVec 4))
+(declare-fun y () (_ BitVec 4))
+
+(assert (= x (bvand y #xf)))
+(assert (not (= (f x) (f y))))
+
+(check-sat) ; unsat
+
+(exit)
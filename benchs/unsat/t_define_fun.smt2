@@ -0,0 +1,19 @@
+; macros, sort definitions and parametric sorts
+; :status unsat
+
+(declare-sort U 0)
+(declare-sort Pair 2)
+(define-sort PU () (Pair U U))
+(declare-fun mk (U U) PU)
+(declare-fun fst ((Pair U U)) U)
+(declare-const a U)
+(declare-const b U)
+
+(define-fun proj ((p PU)) U (fst p))
+(define-fun same ((x U) (y U)) Bool (= x y))
+(define-fun ok () Bool (same (proj (mk a b)) a))
+
+(assert (forall ((x U) (y U)) (= (fst (mk x y)) x)))
+(assert (not ok))
+
+(check-sat) ; unsat
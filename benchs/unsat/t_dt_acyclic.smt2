@@ -0,0 +1,18 @@
+
+; test for acyclicity of datatypes
+; :status unsat
+
+(declare-sort elt 0)
+(declare-datatypes ((list 0))
+  (((nil) (cons (head elt) (tail list)))))
+
+(declare-fun a () elt)
+(declare-fun l1 () list)
+(declare-fun l2 () list)
+
+(assert (= l1 (cons a l2)))
+(assert (= l2 (tail (tail (cons a (cons a l1))))))
+
+(check-sat) ; unsat
+
+(exit)
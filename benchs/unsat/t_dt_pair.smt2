@@ -0,0 +1,17 @@
+
+; test for selectors on a single constructor
+; :status unsat
+
+(declare-sort elt 0)
+(declare-datatype pair ((mk-pair (fst elt) (snd Bool))))
+
+(declare-fun p () pair)
+(declare-fun q () pair)
+
+(assert (not (= p q)))
+(assert (= (fst p) (fst q)))
+(assert (= (snd p) (snd q)))
+
+(check-sat) ; unsat
+
+(exit)
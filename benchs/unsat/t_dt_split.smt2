@@ -0,0 +1,18 @@
+
+; test for case splitting on constructors
+; :status unsat
+
+(declare-datatype color ((red) (green) (blue)))
+
+(declare-fun c1 () color)
+(declare-fun c2 () color)
+(declare-fun c3 () color)
+
+(assert (distinct c1 c2 c3))
+(assert (not ((_ is red) c1)))
+(assert (not (is-red c2)))
+(assert (not (= c3 red)))
+
+(check-sat) ; unsat
+
+(exit)
@@ -0,0 +1,11 @@
+; the axiom has no trigger, only MBQI can instantiate it
+; :status unsat
+
+(declare-sort U 0)
+(declare-fun a () U)
+(declare-fun b () U)
+
+(assert (forall ((x U) (y U)) (= x y)))
+(assert (not (= a b)))
+
+(check-sat) ; unsat
@@ -0,0 +1,11 @@
+
+; the classes of the model do not cover the reals, so MBQI
+; must not answer sat when they all satisfy the axiom
+; :status unsat
+
+(declare-fun a () Real)
+
+(assert (forall ((x Real)) (<= x 5)))
+(assert (<= a 5))
+
+(check-sat) ; unsat
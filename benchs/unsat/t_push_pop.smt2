@@ -0,0 +1,26 @@
+; assertions and declarations are scoped by push/pop: after `pop`, `np` must
+; not be in the unsat core, and `r` can be declared again with another sort
+; :status unsat
+
+(set-option :produce-unsat-cores true)
+(declare-fun p () Bool)
+(declare-fun q () Bool)
+(assert (! (or p q) :named pq))
+
+(push 1)
+(declare-fun r () Bool)
+(assert (! (not p) :named np))
+(assert (! (=> r q) :named rq))
+(assert (! (not q) :named nq))
+(check-sat) ; unsat
+(get-unsat-core) ; (pq np nq)
+(pop 1)
+
+(declare-fun r () Real)
+(assert (! (not q) :named nq))
+(assert (! (=> p (< r 0)) :named pr))
+(assert (! (> r 1) :named r1))
+(check-sat) ; unsat
+(get-unsat-core) ; (pq nq pr r1)
+
+(exit)
@@ -0,0 +1,17 @@
+; the pattern `(f x)` does not bind `y`, so it is ignored and a trigger
+; is inferred instead
+; :status unsat
+
+(declare-sort U 0)
+(declare-fun f (U) U)
+(declare-fun p (U U) Bool)
+(declare-fun a () U)
+(declare-fun b () U)
+
+(assert (forall ((x U) (y U)) (! (p x y) :pattern ((f x)))))
+(assert (= (f a) b))
+(assert (not (p a b)))
+
+(check-sat) ; unsat
+
+(exit)
@@ -0,0 +1,10 @@
+; existential, by skolemization
+; :status unsat
+
+(declare-sort U 0)
+(declare-fun p (U) Bool)
+
+(assert (exists ((x U)) (p x)))
+(assert (forall ((y U)) (not (p y))))
+
+(check-sat) ; unsat
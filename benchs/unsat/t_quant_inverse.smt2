@@ -0,0 +1,12 @@
+; a UF axiom, instantiated by E-matching
+; :status unsat
+
+(declare-sort U 0)
+(declare-fun f (U) U)
+(declare-fun g (U) U)
+(declare-fun a () U)
+
+(assert (forall ((x U)) (= (f (g x)) x)))
+(assert (not (= (f (g a)) a)))
+
+(check-sat) ; unsat
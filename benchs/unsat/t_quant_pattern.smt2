@@ -0,0 +1,18 @@
+; transitivity of an uninterpreted order, with an explicit multi-trigger
+; :status unsat
+
+(declare-sort U 0)
+(declare-fun le (U U) Bool)
+(declare-fun a () U)
+(declare-fun b () U)
+(declare-fun c () U)
+
+(assert
+  (forall ((x U) (y U) (z U))
+    (! (=> (and (le x y) (le y z)) (le x z))
+       :pattern ((le x y) (le y z)))))
+(assert (le a b))
+(assert (le b c))
+(assert (not (le a c)))
+
+(check-sat) ; unsat
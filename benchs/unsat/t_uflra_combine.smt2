@@ -0,0 +1,12 @@
+; `x = y` is only implied by arithmetic
+; :status unsat
+
+(declare-fun f (Real) Real)
+(declare-fun x () Real)
+(declare-fun y () Real)
+
+(assert (<= x y))
+(assert (<= y x))
+(assert (< (f x) (f y)))
+
+(check-sat) ; unsat
@@ -0,0 +1,14 @@
+; `(+ x 1)` only occurs as an argument of `f`, so it must be purified
+; into a term shared between arithmetic and congruence closure
+; :status unsat
+
+(declare-sort U 0)
+(declare-fun f (Real) U)
+(declare-fun x () Real)
+(declare-fun y () Real)
+
+(assert (<= y (+ x 1)))
+(assert (>= y (+ x 1)))
+(assert (not (= (f (+ x 1)) (f y))))
+
+(check-sat) ; unsat
@@ -0,0 +1,20 @@
+; only the named assertions involved in the conflict are in the unsat core
+; :status unsat
+
+(set-option :produce-unsat-cores true)
+(declare-fun p () Bool)
+(declare-fun q () Bool)
+(declare-fun r () Bool)
+(declare-fun x () Real)
+
+(assert (! (=> p q) :named pq))
+(assert (! r :named hr))
+(assert (! p :named hp))
+(assert (! (=> q (< x 0)) :named qx))
+(assert (! (or (not r) (< x 1)) :named rx))
+(assert (! (> x 2) :named x2))
+
+(check-sat) ; unsat
+(get-unsat-core) ; (pq hp qx x2) or (hr rx x2)
+
+(exit)
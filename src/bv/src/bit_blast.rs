@@ -0,0 +1,401 @@
+
+//! Bit-blasting of bit-vector terms into clauses.
+
+use {
+    std::marker::PhantomData,
+    batsat as sat,
+    batsmt_core::{ast_u32::AST, AstView, Manager, },
+    batsmt_theory::{self as theory, Theory, },
+    batsmt_solver::{Solver, BLit, },
+    fxhash::{FxHashMap, FxHashSet, },
+    crate::intf::{Ctx, BVView, BVPred, BVOp, },
+};
+
+type Lit = sat::Lit;
+
+/// Turns bit-vector atoms into clauses.
+///
+/// Every bit-vector term is mapped to a vector of literals, least
+/// significant bit first. Terms that are not bit-vector operations
+/// (variables, applications of uninterpreted functions, `ite`, etc.)
+/// get fresh literals.
+///
+/// Consistency between these bits and the congruence closure is ensured
+/// by adding equality atoms, which are both bit-blasted and seen by the
+/// congruence closure (as theory literals):
+///
+/// - for `ite(c,a,b)`, the atoms `ite(c,a,b) = a` and `ite(c,a,b) = b`;
+/// - for two applications `f(t1…tn)` and `f(u1…un)` of the same
+///     function, `ti = ui` for the bit-vector arguments, and
+///     `f(t1…tn) = f(u1…un)` if `f` returns a bit-vector.
+///
+/// Atoms created during search (e.g. by array lemmas, instantiation,
+/// or theory combination) are only bit-blasted after `solve` returns,
+/// by `add_new_atoms`.
+pub struct BitBlaster<C: Ctx> {
+    bits: FxHashMap<AST, Vec<Lit>>, // bit-vector term -> its bits
+    atoms: FxHashSet<AST>, // atoms already processed
+    seen: FxHashSet<AST>, // subterms already traversed
+    apps: FxHashMap<AST, Vec<AST>>, // function -> its applications involving bit-vectors
+    todo: Vec<AST>, // atoms to process
+    true_: Option<Lit>,
+    n_clauses: usize, // clauses added so far
+    clause: Vec<Lit>, // temporary
+    _m: PhantomData<C>,
+}
+
+impl<C: Ctx> BitBlaster<C> {
+    /// New bit-blaster.
+    pub fn new() -> Self {
+        BitBlaster {
+            bits: FxHashMap::default(),
+            atoms: FxHashSet::default(),
+            seen: FxHashSet::default(),
+            apps: FxHashMap::default(),
+            todo: vec!(),
+            true_: None,
+            n_clauses: 0,
+            clause: vec!(),
+            _m: PhantomData,
+        }
+    }
+
+    /// Bit-blast the atom `t`, if it is a bit-vector atom.
+    ///
+    /// Subterms of `t` are traversed to add the equalities
+    /// required for consistency with the congruence closure.
+    /// This does nothing if `t` was already processed.
+    pub fn add_atom<Th>(&mut self, c: &mut C, s: &mut Solver<C,Th>, t: &AST)
+        where Th: Theory<C>
+    {
+        self.todo.push(*t);
+        while let Some(t) = self.todo.pop() {
+            if ! self.atoms.insert(t) { continue }
+            self.visit(c, &t);
+
+            let lit = match c.view_as_bv(&t) {
+                BVView::Pred(p, a, b) => {
+                    let (a, b) = (*a, *b);
+                    self.blast_pred(c, s, p, &a, &b)
+                },
+                _ => continue,
+            };
+            // `p <=> lit`, where `p` is the literal of `t` in the solver
+            let p = s.new_term_lit(c, t).0;
+            trace!("bv: atom {} := {:?} (bits {:?})", theory::pp_ast(c, &t), p, lit);
+            self.add_clause(s, &[!p, lit]);
+            self.add_clause(s, &[p, !lit]);
+        }
+    }
+
+    /// Bit-blast the atoms that were added to `s` during search.
+    ///
+    /// Returns `true` if this added clauses. In this case the bits of
+    /// these atoms were unconstrained during the last call to `solve`,
+    /// so a `SAT` answer must be confirmed by calling `solve` again.
+    pub fn add_new_atoms<Th>(&mut self, c: &mut C, s: &mut Solver<C,Th>) -> bool
+        where Th: Theory<C>
+    {
+        let n = self.n_clauses;
+        let new: Vec<AST> =
+            s.lit_map().iter_terms().map(|(t,_)| t)
+            .filter(|t| ! self.atoms.contains(t))
+            .collect();
+        for t in new.iter() {
+            self.add_atom(c, s, t);
+        }
+        debug!("bv: {} clauses for atoms created during search", self.n_clauses - n);
+        self.n_clauses > n
+    }
+
+    /// Iterate over the terms that have been bit-blasted.
+    pub fn iter_terms<'a>(&'a self) -> impl Iterator<Item=&'a AST> + 'a {
+        self.bits.keys()
+    }
+
+    /// Value of `t` in the model of the last call to `solve`,
+    /// least significant bit first.
+    pub fn value<Th>(&self, s: &Solver<C,Th>, t: &AST) -> Option<Vec<bool>>
+        where Th: Theory<C>
+    {
+        let bits = self.bits.get(t)?;
+        Some(bits.iter().map(|l| s.model_value(BLit(*l)).unwrap_or(false)).collect())
+    }
+
+    // find the subterms of `t` that need consistency equations
+    fn visit(&mut self, c: &mut C, t: &AST) {
+        if ! self.seen.insert(*t) { return }
+        let is_bv = c.bv_width(t).is_some();
+        match c.view_as_bv(t) {
+            BVView::Ite(_, a, b) => {
+                let (a, b) = (*a, *b);
+                let eq_a = c.mk_eq(t, &a);
+                let eq_b = c.mk_eq(t, &b);
+                self.todo.push(eq_a);
+                self.todo.push(eq_b);
+            },
+            BVView::Other(_) => {
+                if let AstView::App{f, args} = c.view(t) {
+                    let (f, args): (AST, Vec<AST>) = (*f, args.to_vec());
+                    if is_bv || args.iter().any(|u| c.bv_width(u).is_some()) {
+                        self.ackermann(c, f, t, &args);
+                    }
+                }
+            },
+            _ => (),
+        }
+        let args: Vec<AST> = match c.view(t) {
+            AstView::App{args, ..} => args.to_vec(),
+            _ => return,
+        };
+        for u in args.iter() {
+            self.visit(c, u)
+        }
+    }
+
+    // add equations between `t = f(args)` and the other applications of `f`
+    fn ackermann(&mut self, c: &mut C, f: AST, t: &AST, args: &[AST]) {
+        let is_bv = c.bv_width(t).is_some();
+        let others = self.apps.entry(f).or_insert(vec!());
+        for u in others.iter() {
+            let args_u: Vec<AST> = match c.view(u) {
+                AstView::App{args, ..} => args.to_vec(),
+                _ => unreachable!(),
+            };
+            if args_u.len() != args.len() { continue }
+            for (a, b) in args.iter().zip(args_u.iter()) {
+                if a != b && c.bv_width(a).is_some() {
+                    self.todo.push(c.mk_eq(a, b));
+                }
+            }
+            if is_bv {
+                self.todo.push(c.mk_eq(t, u));
+            }
+        }
+        others.push(*t);
+    }
+
+    // literal for the atom `a pred b`
+    fn blast_pred<Th>(&mut self, c: &mut C, s: &mut Solver<C,Th>, p: BVPred, a: &AST, b: &AST) -> Lit
+        where Th: Theory<C>
+    {
+        let a = self.get_bits(c, s, a);
+        let b = self.get_bits(c, s, b);
+        assert_eq!(a.len(), b.len(), "bv: comparison of bit-vectors of distinct widths");
+        match p {
+            BVPred::Eq => self.eq(s, &a, &b),
+            BVPred::Ult => self.ult(s, &a, &b),
+            BVPred::Ule => ! self.ult(s, &b, &a),
+            BVPred::Ugt => self.ult(s, &b, &a),
+            BVPred::Uge => ! self.ult(s, &a, &b),
+            BVPred::Slt => self.slt(s, &a, &b),
+            BVPred::Sle => ! self.slt(s, &b, &a),
+            BVPred::Sgt => self.slt(s, &b, &a),
+            BVPred::Sge => ! self.slt(s, &a, &b),
+        }
+    }
+
+    // bits of `t`, computed if needed
+    fn get_bits<Th>(&mut self, c: &mut C, s: &mut Solver<C,Th>, t: &AST) -> Vec<Lit>
+        where Th: Theory<C>
+    {
+        if let Some(bits) = self.bits.get(t) {
+            return bits.clone()
+        }
+        let bits = match c.view_as_bv(t) {
+            BVView::Const(v) => {
+                let v = v.to_vec();
+                let tr = self.mk_true(s);
+                v.iter().map(|b| if *b { tr } else { !tr }).collect()
+            },
+            BVView::Op(op, args) => {
+                let args = args.to_vec();
+                let mut acc = self.get_bits(c, s, &args[0]);
+                for u in args[1..].iter() {
+                    let b = self.get_bits(c, s, u);
+                    acc = self.op(s, op, &acc, &b);
+                }
+                acc
+            },
+            BVView::Neg(a) => {
+                let a = *a;
+                let a = self.get_bits(c, s, &a);
+                let not_a: Vec<Lit> = a.iter().map(|l| !*l).collect();
+                let zero = self.constant(s, a.len(), false);
+                let tr = self.mk_true(s);
+                self.add(s, &not_a, &zero, tr)
+            },
+            BVView::Not(a) => {
+                let a = *a;
+                self.get_bits(c, s, &a).iter().map(|l| !*l).collect()
+            },
+            BVView::Concat(args) => {
+                let args = args.to_vec();
+                let mut res = vec!();
+                for u in args.iter().rev() {
+                    res.extend(self.get_bits(c, s, u));
+                }
+                res
+            },
+            BVView::Extract(hi, lo, a) => {
+                let a = *a;
+                let a = self.get_bits(c, s, &a);
+                a[lo as usize ..= hi as usize].to_vec()
+            },
+            BVView::Pred(..) => panic!("bv: {} is not a bit-vector", theory::pp_ast(c, t)),
+            BVView::Ite(..) | BVView::Other(..) => {
+                // fresh bits
+                let w = match c.bv_width(t) {
+                    Some(w) => w,
+                    None => panic!("bv: {} is not a bit-vector", theory::pp_ast(c, t)),
+                };
+                (0 .. w).map(|_| s.new_bool_lit()).collect()
+            },
+        };
+        self.bits.insert(*t, bits.clone());
+        bits
+    }
+
+    fn add_clause<Th>(&mut self, s: &mut Solver<C,Th>, c: &[Lit]) where Th: Theory<C> {
+        self.n_clauses += 1;
+        self.clause.clear();
+        self.clause.extend_from_slice(c);
+        s.add_bool_clause_reuse(&mut self.clause);
+    }
+
+    // the literal that is always true
+    fn mk_true<Th>(&mut self, s: &mut Solver<C,Th>) -> Lit where Th: Theory<C> {
+        match self.true_ {
+            Some(l) => l,
+            None => {
+                let l = s.new_bool_lit();
+                self.add_clause(s, &[l]);
+                self.true_ = Some(l);
+                l
+            }
+        }
+    }
+
+    fn constant<Th>(&mut self, s: &mut Solver<C,Th>, n: usize, b: bool) -> Vec<Lit>
+        where Th: Theory<C>
+    {
+        let tr = self.mk_true(s);
+        vec![if b { tr } else { !tr }; n]
+    }
+
+    fn and2<Th>(&mut self, s: &mut Solver<C,Th>, a: Lit, b: Lit) -> Lit where Th: Theory<C> {
+        let tr = self.mk_true(s);
+        if a == !tr || b == !tr || a == !b { return !tr }
+        if a == tr { return b }
+        if b == tr || a == b { return a }
+        let o = s.new_bool_lit();
+        self.add_clause(s, &[!o, a]);
+        self.add_clause(s, &[!o, b]);
+        self.add_clause(s, &[o, !a, !b]);
+        o
+    }
+
+    fn or2<Th>(&mut self, s: &mut Solver<C,Th>, a: Lit, b: Lit) -> Lit where Th: Theory<C> {
+        ! self.and2(s, !a, !b)
+    }
+
+    fn xor2<Th>(&mut self, s: &mut Solver<C,Th>, a: Lit, b: Lit) -> Lit where Th: Theory<C> {
+        let tr = self.mk_true(s);
+        if a == b { return !tr }
+        if a == !b { return tr }
+        if a == tr { return !b }
+        if a == !tr { return b }
+        if b == tr { return !a }
+        if b == !tr { return a }
+        let o = s.new_bool_lit();
+        self.add_clause(s, &[!o, a, b]);
+        self.add_clause(s, &[!o, !a, !b]);
+        self.add_clause(s, &[o, !a, b]);
+        self.add_clause(s, &[o, a, !b]);
+        o
+    }
+
+    // bitwise or arithmetic operation
+    fn op<Th>(&mut self, s: &mut Solver<C,Th>, op: BVOp, a: &[Lit], b: &[Lit]) -> Vec<Lit>
+        where Th: Theory<C>
+    {
+        assert_eq!(a.len(), b.len(), "bv: {:?} on bit-vectors of distinct widths", op);
+        match op {
+            BVOp::And => (0 .. a.len()).map(|i| self.and2(s, a[i], b[i])).collect(),
+            BVOp::Or => (0 .. a.len()).map(|i| self.or2(s, a[i], b[i])).collect(),
+            BVOp::Xor => (0 .. a.len()).map(|i| self.xor2(s, a[i], b[i])).collect(),
+            BVOp::Add => {
+                let f = ! self.mk_true(s);
+                self.add(s, a, b, f)
+            },
+            BVOp::Sub => {
+                // `a - b = a + ¬b + 1`
+                let not_b: Vec<Lit> = b.iter().map(|l| !*l).collect();
+                let tr = self.mk_true(s);
+                self.add(s, a, &not_b, tr)
+            },
+            BVOp::Mul => {
+                // shift-and-add
+                let n = a.len();
+                let f = ! self.mk_true(s);
+                let mut acc = self.constant(s, n, false);
+                for i in 0 .. n {
+                    let mut pp = vec![f; n];
+                    for j in i .. n {
+                        pp[j] = self.and2(s, a[j-i], b[i]);
+                    }
+                    acc = self.add(s, &acc, &pp, f);
+                }
+                acc
+            },
+        }
+    }
+
+    // ripple-carry adder
+    fn add<Th>(&mut self, s: &mut Solver<C,Th>, a: &[Lit], b: &[Lit], carry: Lit) -> Vec<Lit>
+        where Th: Theory<C>
+    {
+        let mut carry = carry;
+        let mut res = Vec::with_capacity(a.len());
+        for i in 0 .. a.len() {
+            let x = self.xor2(s, a[i], b[i]);
+            res.push(self.xor2(s, x, carry));
+            let c1 = self.and2(s, a[i], b[i]);
+            let c2 = self.and2(s, x, carry);
+            carry = self.or2(s, c1, c2);
+        }
+        res
+    }
+
+    fn eq<Th>(&mut self, s: &mut Solver<C,Th>, a: &[Lit], b: &[Lit]) -> Lit where Th: Theory<C> {
+        let mut res = self.mk_true(s);
+        for i in 0 .. a.len() {
+            let x = self.xor2(s, a[i], b[i]);
+            res = self.and2(s, res, !x);
+        }
+        res
+    }
+
+    // unsigned `a < b`, from the least significant bit up
+    fn ult<Th>(&mut self, s: &mut Solver<C,Th>, a: &[Lit], b: &[Lit]) -> Lit where Th: Theory<C> {
+        let mut lt = ! self.mk_true(s);
+        for i in 0 .. a.len() {
+            // `a[..=i] < b[..=i]` iff `a_i < b_i`, or `a_i = b_i` and `a[..i] < b[..i]`
+            let lt_i = self.and2(s, !a[i], b[i]);
+            let x = self.xor2(s, a[i], b[i]);
+            let eq_lt = self.and2(s, !x, lt);
+            lt = self.or2(s, lt_i, eq_lt);
+        }
+        lt
+    }
+
+    // signed `a < b`: flip the sign bits, then compare as unsigned
+    fn slt<Th>(&mut self, s: &mut Solver<C,Th>, a: &[Lit], b: &[Lit]) -> Lit where Th: Theory<C> {
+        let n = a.len();
+        let mut a = a.to_vec();
+        let mut b = b.to_vec();
+        a[n-1] = ! a[n-1];
+        b[n-1] = ! b[n-1];
+        self.ult(s, &a, &b)
+    }
+}
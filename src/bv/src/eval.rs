@@ -0,0 +1,80 @@
+
+//! Evaluation of bit-vector operations on constants.
+//!
+//! Constants are represented as slices of booleans,
+//! least significant bit first.
+
+use crate::intf::{BVPred, BVOp, };
+
+/// `a + b + carry`, modulo `2^width`.
+fn add(a: &[bool], b: &[bool], carry: bool) -> Vec<bool> {
+    let mut carry = carry;
+    let mut res = Vec::with_capacity(a.len());
+    for (x, y) in a.iter().zip(b.iter()) {
+        res.push(x ^ y ^ carry);
+        carry = (x & y) | ((x ^ y) & carry);
+    }
+    res
+}
+
+/// Two's complement negation.
+pub fn neg(a: &[bool]) -> Vec<bool> {
+    let not_a: Vec<bool> = a.iter().map(|x| !x).collect();
+    add(&not_a, &vec![false; a.len()], true)
+}
+
+/// Evaluate `a op b`.
+pub fn op(op: BVOp, a: &[bool], b: &[bool]) -> Vec<bool> {
+    assert_eq!(a.len(), b.len());
+    match op {
+        BVOp::And => a.iter().zip(b.iter()).map(|(x,y)| x & y).collect(),
+        BVOp::Or => a.iter().zip(b.iter()).map(|(x,y)| x | y).collect(),
+        BVOp::Xor => a.iter().zip(b.iter()).map(|(x,y)| x ^ y).collect(),
+        BVOp::Add => add(a, b, false),
+        BVOp::Sub => {
+            let not_b: Vec<bool> = b.iter().map(|x| !x).collect();
+            add(a, &not_b, true)
+        },
+        BVOp::Mul => {
+            let n = a.len();
+            let mut acc = vec![false; n];
+            for i in 0 .. n {
+                if ! b[i] { continue }
+                let mut shifted = vec![false; n];
+                shifted[i..].copy_from_slice(&a[..n-i]);
+                acc = add(&acc, &shifted, false);
+            }
+            acc
+        },
+    }
+}
+
+// unsigned `a < b`
+fn ult(a: &[bool], b: &[bool]) -> bool {
+    for (x, y) in a.iter().zip(b.iter()).rev() {
+        if x != y { return *y }
+    }
+    false
+}
+
+// signed `a < b`
+fn slt(a: &[bool], b: &[bool]) -> bool {
+    let n = a.len();
+    if a[n-1] != b[n-1] { a[n-1] } else { ult(a, b) }
+}
+
+/// Evaluate `a pred b`.
+pub fn pred(p: BVPred, a: &[bool], b: &[bool]) -> bool {
+    assert_eq!(a.len(), b.len());
+    match p {
+        BVPred::Eq => a == b,
+        BVPred::Ult => ult(a, b),
+        BVPred::Ule => ! ult(b, a),
+        BVPred::Ugt => ult(b, a),
+        BVPred::Uge => ! ult(a, b),
+        BVPred::Slt => slt(a, b),
+        BVPred::Sle => ! slt(b, a),
+        BVPred::Sgt => slt(b, a),
+        BVPred::Sge => ! slt(a, b),
+    }
+}
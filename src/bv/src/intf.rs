@@ -0,0 +1,64 @@
+//! Interface between the bit-vector theory and terms.
+
+use {
+    batsmt_theory::{self as theory, HasEq, },
+    batsmt_solver::BLit,
+};
+
+/// Comparison between two bit-vectors of the same width.
+#[derive(Copy,Clone,Debug,Eq,PartialEq,Hash)]
+pub enum BVPred {
+    Eq,
+    Ult,
+    Ule,
+    Ugt,
+    Uge,
+    Slt,
+    Sle,
+    Sgt,
+    Sge,
+}
+
+/// Binary operation on bit-vectors of the same width.
+///
+/// Arithmetic operations are modulo `2^width`.
+#[derive(Copy,Clone,Debug,Eq,PartialEq,Hash)]
+pub enum BVOp {
+    Add,
+    Sub,
+    Mul,
+    And,
+    Or,
+    Xor,
+}
+
+/// View of a term as a bit-vector term.
+pub enum BVView<'a, AST> {
+    /// A constant, least significant bit first.
+    Const(&'a [bool]),
+    /// Comparison `a pred b`.
+    Pred(BVPred, &'a AST, &'a AST),
+    /// Binary operation (left associative if there are more arguments).
+    Op(BVOp, &'a [AST]),
+    /// Two's complement negation.
+    Neg(&'a AST),
+    /// Bitwise negation.
+    Not(&'a AST),
+    /// Concatenation. The first argument is the most significant part.
+    Concat(&'a [AST]),
+    /// `(_ extract hi lo)`, bits `lo` to `hi` (included).
+    Extract(u32, u32, &'a AST),
+    /// If-then-else on bit-vectors.
+    Ite(&'a AST, &'a AST, &'a AST),
+    /// Any other term, considered as a variable.
+    Other(&'a AST),
+}
+
+/// The context for the bit-vector theory.
+pub trait Ctx : theory::Ctx<B=BLit> + HasEq {
+    /// View a term as a bit-vector expression.
+    fn view_as_bv<'a>(&'a self, t: &'a Self::AST) -> BVView<'a, Self::AST>;
+
+    /// Width of `t`, if it is a bit-vector.
+    fn bv_width(&self, t: &Self::AST) -> Option<u32>;
+}
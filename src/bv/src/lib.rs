@@ -0,0 +1,25 @@
+
+//! Bit-vectors (QF_BV), by bit-blasting.
+//!
+//! Bit-vector terms are turned into vectors of SAT literals, and
+//! bit-vector atoms into boolean circuits over these literals, all
+//! encoded as clauses directly in the SAT solver. Atoms are mapped
+//! to literals through the solver's `SatLitMap`, so that other theories
+//! see them; in particular, equalities between bit-vector terms are
+//! propagated to the congruence closure, and vice versa.
+//!
+//! The expected usage is to call `BitBlaster::add_atom` on every atom
+//! before the clauses that contain it are added to the solver.
+
+#[macro_use] extern crate log;
+
+pub mod intf;
+pub mod bit_blast;
+pub mod eval;
+
+pub use {
+    crate::{
+        intf::{Ctx, BVView, BVPred, BVOp, },
+        bit_blast::BitBlaster,
+    },
+};
@@ -0,0 +1,38 @@
+extern crate batsmt_bv;
+
+use {
+    batsmt_bv::{eval, BVOp, BVPred, },
+};
+
+// `n` on `w` bits, least significant bit first
+fn bits(w: usize, n: u32) -> Vec<bool> {
+    (0 .. w).map(|i| (n >> i) & 1 == 1).collect()
+}
+
+#[test]
+fn test_arith() {
+    assert_eq!(eval::op(BVOp::Add, &bits(4, 7), &bits(4, 11)), bits(4, 2));
+    assert_eq!(eval::op(BVOp::Sub, &bits(4, 3), &bits(4, 5)), bits(4, 14));
+    assert_eq!(eval::op(BVOp::Mul, &bits(8, 13), &bits(8, 21)), bits(8, 13 * 21 % 256));
+    assert_eq!(eval::neg(&bits(4, 1)), bits(4, 15));
+    assert_eq!(eval::neg(&bits(4, 0)), bits(4, 0));
+}
+
+#[test]
+fn test_bitwise() {
+    assert_eq!(eval::op(BVOp::And, &bits(4, 0b1100), &bits(4, 0b1010)), bits(4, 0b1000));
+    assert_eq!(eval::op(BVOp::Or, &bits(4, 0b1100), &bits(4, 0b1010)), bits(4, 0b1110));
+    assert_eq!(eval::op(BVOp::Xor, &bits(4, 0b1100), &bits(4, 0b1010)), bits(4, 0b0110));
+}
+
+#[test]
+fn test_cmp() {
+    // 2 < 14 unsigned, but 14 is -2 signed
+    let (a, b) = (bits(4, 2), bits(4, 14));
+    assert!(eval::pred(BVPred::Ult, &a, &b));
+    assert!(eval::pred(BVPred::Sgt, &a, &b));
+    assert!(eval::pred(BVPred::Ule, &a, &a));
+    assert!(! eval::pred(BVPred::Slt, &a, &a));
+    assert!(eval::pred(BVPred::Sge, &bits(4, 7), &bits(4, 8)));
+    assert!(! eval::pred(BVPred::Eq, &a, &b));
+}
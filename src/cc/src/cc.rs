@@ -8,7 +8,8 @@
 
 use {
     std::{ u32, ptr, hash::Hash, fmt::Debug, marker::PhantomData, },
-    batsmt_core::{backtrack, },
+    batsmt_core::{backtrack, stats, },
+    batsmt_theory::{ExplStep, EqReason, },
     fxhash::FxHashMap,
     batsmt_pretty as pp,
     crate::{ Ctx, Actions, CCInterface, CCView, SVec, pp_t, },
@@ -61,6 +62,18 @@ pub trait MicroTheory<C:Ctx> : backtrack::Backtrackable<C> {
 
     /// Called with all terms whose signature is to be updated
     fn on_sig_update(&mut self, c: &mut C, acts: &mut MicroTheoryArg<C>, t: &C::AST, n: NodeID) {}
+
+    /// Called at the end of a partial check that did not find any conflict.
+    ///
+    /// Unlike the other callbacks, this one can add lemmas, and therefore
+    /// introduce new terms, using `acts`.
+    fn partial_check(&mut self, c: &mut C, cc1: &mut CC1<C>, acts: &mut dyn Actions<C>) {}
+
+    /// Called at the end of a final check that did not find any conflict.
+    ///
+    /// Same as `partial_check`, but the congruence closure contains
+    /// the whole trail.
+    fn final_check(&mut self, c: &mut C, cc1: &mut CC1<C>, acts: &mut dyn Actions<C>) {}
 }
 
 /// Implement `MicroTheory` for a tuple of types themselves micro-theories.
@@ -102,6 +115,18 @@ macro_rules! impl_micro_theory_tuple {
                 let ($( $t ,)*) = self;
                 $( $t.on_sig_update(c, acts, t, n); )*
             }
+
+            fn partial_check(&mut self, c: &mut C, cc1: &mut CC1<C>, acts: &mut dyn Actions<C>)
+            {
+                let ($( $t ,)*) = self;
+                $( $t.partial_check(c, cc1, acts); )*
+            }
+
+            fn final_check(&mut self, c: &mut C, cc1: &mut CC1<C>, acts: &mut dyn Actions<C>)
+            {
+                let ($( $t ,)*) = self;
+                $( $t.final_check(c, cc1, acts); )*
+            }
         }
 
         impl_micro_theory_tuple_peel!{ $($t,)* }
@@ -125,7 +150,29 @@ pub struct CC1<C:Ctx> {
     alloc_lit_list: ListAlloc<(NodeID,C::B)>,
     nodes: Nodes<C>,
     confl: Vec<C::B>, // local for conflict
+    confl_expl: Vec<ExplStep<C::AST, C::B>>, // explanation of `confl`, for proofs
+    proofs: bool, // record `confl_expl`?
     tmp_expl: Vec<NodeID>,
+    stats: Stats,
+}
+
+// statistics, registered in the default registry
+struct Stats {
+    merges: stats::Counter,
+    conflicts: stats::Counter,
+    conflict_lits: stats::Counter, // sum of the size of conflicts
+    th_firings: stats::Counter, // calls to micro theories that merged something
+}
+
+impl Stats {
+    fn new() -> Self {
+        Stats {
+            merges: stats::counter("cc.merges"),
+            conflicts: stats::counter("cc.conflicts"),
+            conflict_lits: stats::counter("cc.conflict-lits"),
+            th_firings: stats::counter("cc.micro-theory-firings"),
+        }
+    }
 }
 
 /// Unique Node ID.
@@ -232,14 +279,20 @@ impl<C:Ctx, Th: MicroTheory<C>> CCInterface<C> for CC<C, Th> {
     fn final_check<A>(&mut self, m: &mut C, acts: &mut A)
         where A: Actions<C>
     {
-        self.check_internal(m, acts)
+        self.check_internal(m, acts);
+        if self.cc1.ok {
+            self.th.final_check(m, &mut self.cc1, acts);
+        }
     }
 
     #[inline]
     fn partial_check<A>(&mut self, m: &mut C, acts: &mut A)
         where A: Actions<C>
     {
-        self.check_internal(m, acts)
+        self.check_internal(m, acts);
+        if self.cc1.ok {
+            self.th.partial_check(m, &mut self.cc1, acts);
+        }
     }
 
     fn explain_prop(&mut self, _m: &C, _p: C::B) -> &[C::B] {
@@ -250,6 +303,8 @@ impl<C:Ctx, Th: MicroTheory<C>> CCInterface<C> for CC<C, Th> {
 
     fn enable_propagation(&mut self, _b: bool) {}
 
+    fn enable_proofs(&mut self, b: bool) { self.cc1.proofs = b }
+
     fn impl_descr() -> &'static str { "fast congruence closure"}
 }
 
@@ -263,6 +318,9 @@ impl<C:Ctx, Th: MicroTheory<C>> CC<C, Th> {
         self.fixpoint(m, Some(acts));
         if ! self.cc1.ok {
             debug_assert!(self.cc1.confl.len() >= 1); // must have some conflict
+            if self.cc1.proofs {
+                acts.justify(&self.cc1.confl_expl);
+            }
             let costly = true;
             acts.raise_conflict(&self.cc1.confl, costly)
         }
@@ -404,6 +462,12 @@ impl<C:Ctx, Th:MicroTheory<C>> CC<C, Th> {
 
     // we do not care
     fn map_to_lit(&mut self, _m: &C, _t: NodeID, _lit: C::B) {}
+
+    /// Call `f` on every term of the congruence closure, along with
+    /// the representative of its class.
+    pub fn iter_repr<F>(&mut self, f: F) where F: FnMut(&C::AST, &C::AST) {
+        self.cc1.iter_repr(f)
+    }
 }
 
 /// Internal structure used during merging of newly equivalent classes.
@@ -471,6 +535,7 @@ impl<'a, 'b:'a, C:Ctx> MergePhase<'a,'b,C> {
                 self.undo.push_if_nonzero(UndoOp::SetOk);
                 {
                     let mut er = ExplResolve::new(&mut self.cc1, &mut self.expl_st);
+                    er.record_step(a, b, &expl);
                     er.add_expl(expl);
                     er.explain_eq(m, a, ra);
                     er.explain_eq(m, b, rb);
@@ -478,6 +543,8 @@ impl<'a, 'b:'a, C:Ctx> MergePhase<'a,'b,C> {
                 }
                 // negation
                 for lit in self.cc1.confl.iter_mut() { *lit = ! *lit }
+                self.cc1.stats.conflicts.incr();
+                self.cc1.stats.conflict_lits.add(self.cc1.confl.len() as u64);
                 trace!("inconsistent set of explanations: {:?}", &self.cc1.confl);
                 return;
             } else {
@@ -498,12 +565,17 @@ impl<'a, 'b:'a, C:Ctx> MergePhase<'a,'b,C> {
 
         // call micro theories
         {
+            let n_combine = self.combine2.len();
             let mut acts = MicroTheoryArg{
                 cc1: &mut self.cc1, n_true: self.n_true, n_false: self.n_false,
                 combine: &mut self.combine2};
             th.before_merge(m, &mut acts, ra, rb);
+            if self.combine2.len() > n_combine || !self.cc1.ok {
+                self.cc1.stats.th_firings.incr();
+            }
         }
         if !self.cc1.ok { return; }
+        self.cc1.stats.merges.incr();
 
         // update forest tree so that `b --[expl]--> a`.
         // Note that here we link `a` and `b`, not their representatives.
@@ -557,10 +629,14 @@ impl<'a, 'b:'a, C:Ctx> MergePhase<'a,'b,C> {
 
         // call micro theories
         {
+            let n_combine = combine2.len();
             let mut acts = MicroTheoryArg{
                 cc1, n_true: *n_true, n_false: *n_false,
                 combine: combine2};
             th.after_merge(m, &mut acts, ra, rb);
+            if combine2.len() > n_combine || !cc1.ok {
+                cc1.stats.th_firings.incr();
+            }
         }
     }
 }
@@ -626,10 +702,14 @@ impl<'a, C:Ctx> UpdateSigPhase<'a,C> {
         }
         // call micro theories
         {
+            let n_combine = combine.len();
             let mut arg = MicroTheoryArg{
                 cc1, n_true: *n_true, n_false: *n_false, combine,
             };
             th.on_sig_update(m, &mut arg, &t, n);
+            if combine.len() > n_combine || !cc1.ok {
+                cc1.stats.th_firings.incr();
+            }
         }
     }
 }
@@ -644,14 +724,22 @@ impl<C:Ctx> CC1<C> {
             alloc_lit_list: backtrack::Alloc::new(),
             tmp_expl: vec!(),
             confl: vec!(),
+            confl_expl: vec!(),
+            proofs: false,
+            stats: Stats::new(),
         }
     }
 
     #[inline(always)]
     pub(crate) fn find(&mut self, t: NodeID) -> NodeID { self.nodes.find(t) }
 
+    /// Representative of the class of `t`, which must be in the E-graph.
+    #[inline(always)]
+    pub fn find_t(&mut self, t: &C::AST) -> NodeID { self.nodes.find_t(t) }
+
+    /// Is `t` in the E-graph?
     #[inline(always)]
-    pub(crate) fn find_t(&mut self, t: &C::AST) -> NodeID { self.nodes.find_t(t) }
+    pub fn contains(&self, t: &C::AST) -> bool { self.nodes.contains(t) }
 
     #[inline(always)]
     pub(crate) fn is_root(&mut self, id: NodeID) -> bool { self.find(id) == id }
@@ -667,6 +755,20 @@ impl<C:Ctx> CC1<C> {
         self.nodes.get_term_id(t)
     }
 
+    /// Call `f` on every term of the class of `r`.
+    pub fn iter_class<F>(&mut self, r: NodeID, mut f: F) where F: FnMut(&C::AST) {
+        self.nodes.iter_class_mut(r, |n| f(&n.ast))
+    }
+
+    /// Call `f` on every term of the E-graph, along with its representative.
+    pub fn iter_repr<F>(&mut self, mut f: F) where F: FnMut(&C::AST, &C::AST) {
+        for i in 0 .. self.nodes.nodes.len() {
+            let n = NodeID(i as u32);
+            let r = self.find(n);
+            f(&self.nodes[n].ast, &self.nodes[r].ast)
+        }
+    }
+
     /// Undo one change.
     fn perform_undo(&mut self, m: &C, op: UndoOp) {
         trace!("perform-undo {}", pp::pp2(&self.nodes,m,&op));
@@ -674,6 +776,7 @@ impl<C:Ctx> CC1<C> {
             UndoOp::SetOk => {
                 self.ok = true;
                 self.confl.clear();
+                self.confl_expl.clear();
             },
             UndoOp::Unmerge {root: a, old_root: b} => {
                 assert_ne!(a,b); // crucial invariant
@@ -858,9 +961,41 @@ impl<'a,C:Ctx> ExplResolve<'a,C> {
     fn new(cc1: &'a mut CC1<C>, expl_st: &'a mut Vec<Expl<C::B>>) -> Self {
         expl_st.clear();
         cc1.confl.clear();
+        cc1.confl_expl.clear();
         ExplResolve { cc1, expl_st }
     }
 
+    /// Record that `a = b` because of `e`, if proofs are enabled.
+    fn record_step(&mut self, a: NodeID, b: NodeID, e: &Expl<C::B>) {
+        if ! self.cc1.proofs { return }
+        let reason = match e {
+            Expl::Axiom => EqReason::Axiom,
+            Expl::Lit(lit) => EqReason::Lit(*lit),
+            Expl::Congruence(..) => EqReason::Congruence,
+            Expl::AreEq(..) | Expl::Conj(..) => {
+                let mut eqns = vec!();
+                self.collect_eqns(e, &mut eqns);
+                EqReason::Eqns(eqns)
+            },
+        };
+        let lhs = self.cc1[a].ast;
+        let rhs = self.cc1[b].ast;
+        self.cc1.confl_expl.push(ExplStep{lhs, rhs, reason});
+    }
+
+    // the equations `e` relies upon
+    fn collect_eqns(&self, e: &Expl<C::B>, eqns: &mut Vec<(C::AST,C::AST)>) {
+        match e {
+            Expl::Axiom | Expl::Lit(_) => (),
+            Expl::AreEq(a,b) | Expl::Congruence(a,b) => {
+                eqns.push((self.cc1[*a].ast, self.cc1[*b].ast))
+            },
+            Expl::Conj(v) => {
+                for e in v.iter() { self.collect_eqns(e, eqns) }
+            },
+        }
+    }
+
     #[inline]
     fn add_expl(&mut self, e: Expl<C::B>) {
         self.expl_st.push(e)
@@ -934,12 +1069,13 @@ impl<'a,C:Ctx> ExplResolve<'a,C> {
     /// Explain why `cur =_E ancestor`, where `ancestor` is reachable from `cur`
     fn explain_along_path(&mut self, mut cur: NodeID, ancestor: NodeID) {
         while cur != ancestor {
-            if let Some((next, expl)) = &self.cc1[cur].expl {
-                self.expl_st.push(expl.clone()); // need to explain this link
-                cur = *next;
-            } else {
-                panic!()
-            }
+            let (next, expl) = match &self.cc1[cur].expl {
+                Some((next, expl)) => (*next, expl.clone()),
+                None => panic!(),
+            };
+            self.record_step(cur, next, &expl);
+            self.expl_st.push(expl); // need to explain this link
+            cur = next;
         }
     }
 }
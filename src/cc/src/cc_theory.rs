@@ -14,14 +14,23 @@ use crate::{naive_cc::NaiveCC,cc::{CC, MicroTheory}};
 
 type SVec8<T> = smallvec::SmallVec<[T;8]>;
 
-//#[cfg(feature="naive")]
-//type CCI<M> = NaiveCC<M>;
+// the implementation of congruence closure
+enum CCI<C:Ctx, Th: MicroTheory<C>> {
+    Fast(CC<C, Th>),
+    Naive(NaiveCC<C>),
+}
 
-//#[cfg(not(feature="naive"))]
-type CCI<M, Th> = CC<M, Th>;
+// call the same method on either implementation
+macro_rules! with_cc {
+    ($cc: expr, $x: ident => $e: expr) => {
+        match $cc {
+            CCI::Fast($x) => $e,
+            CCI::Naive($x) => $e,
+        }
+    }
+}
 
 /// A theory built on top of a congruence closure.
-#[repr(transparent)]
 pub struct CCTheory<C:Ctx, Th: MicroTheory<C> = (theories::Ite, )>{
     cc: CCI<C, Th>,
 }
@@ -29,9 +38,27 @@ pub struct CCTheory<C:Ctx, Th: MicroTheory<C> = (theories::Ite, )>{
 impl<C:Ctx, Th: MicroTheory<C>> CCTheory<C, Th> {
     /// Build a new theory for equality, based on congruence closure.
     pub fn new(m: &mut C) -> Self {
-        let cc = CCI::new(m);
-        debug!("use {}", CCI::<C,Th>::impl_descr());
-        Self { cc }
+        debug!("use {}", CC::<C,Th>::impl_descr());
+        Self { cc: CCI::Fast(CC::new(m)) }
+    }
+
+    /// Build a new theory for equality, based on the naive congruence closure.
+    ///
+    /// This is much slower, and ignores the micro theories (only
+    /// equality and uninterpreted functions are handled), but it is a
+    /// useful reference when debugging. Callers must not give it terms
+    /// that need one of the micro theories of `Th`.
+    pub fn new_naive(m: &mut C) -> Self {
+        debug!("use {}", NaiveCC::<C>::impl_descr());
+        Self { cc: CCI::Naive(NaiveCC::new(m)) }
+    }
+
+    // iterate over terms and their representative
+    fn iter_repr<F>(&mut self, m: &C, f: F) where F: FnMut(&C::AST, &C::AST) {
+        match &mut self.cc {
+            CCI::Fast(cc) => cc.iter_repr(f),
+            CCI::Naive(cc) => cc.iter_repr(m, f),
+        }
     }
 
     /// Add trail to the congruence closure, returns `true` if anything was added
@@ -45,10 +72,11 @@ impl<C:Ctx, Th: MicroTheory<C>> CCTheory<C, Th> {
                 CCView::Eq(a,b) => {
                     if sign {
                         // `a=b`
-                        self.cc.merge(m, *a, *b, lit);
+                        with_cc!(&mut self.cc, cc => cc.merge(m, *a, *b, lit));
                     } else {
                         // `(a=b)=false`
-                        self.cc.merge(m, ast, m.get_bool_term(false), lit);
+                        let f = m.get_bool_term(false);
+                        with_cc!(&mut self.cc, cc => cc.merge(m, ast, f, lit));
                     }
                 },
                 CCView::Distinct(args) => {
@@ -57,11 +85,11 @@ impl<C:Ctx, Th: MicroTheory<C>> CCTheory<C, Th> {
                     };
                     // copy `args` locally
                     let args = SVec8::from_slice(args);
-                    self.cc.distinct(m, &args, lit)
+                    with_cc!(&mut self.cc, cc => cc.distinct(m, &args, lit))
                 },
                 _ => {
                     let u = m.get_bool_term(sign);
-                    self.cc.merge(m, ast, u, lit)
+                    with_cc!(&mut self.cc, cc => cc.merge(m, ast, u, lit))
                 },
             }
 
@@ -73,9 +101,11 @@ impl<C:Ctx, Th: MicroTheory<C>> CCTheory<C, Th> {
 
 impl<C:Ctx, Th:MicroTheory<C>> backtrack::Backtrackable<C> for CCTheory<C,Th> {
     #[inline]
-    fn push_level(&mut self, c: &mut C) { self.cc.push_level(c) }
+    fn push_level(&mut self, c: &mut C) { with_cc!(&mut self.cc, cc => cc.push_level(c)) }
     #[inline]
-    fn pop_levels(&mut self, c: &mut C, n:usize) { self.cc.pop_levels(c, n) }
+    fn pop_levels(&mut self, c: &mut C, n:usize) {
+        with_cc!(&mut self.cc, cc => cc.pop_levels(c, n))
+    }
 }
 
 impl<C:Ctx, Th:MicroTheory<C>> theory::Theory<C> for CCTheory<C, Th> {
@@ -86,7 +116,7 @@ impl<C:Ctx, Th:MicroTheory<C>> theory::Theory<C> for CCTheory<C, Th> {
     {
         debug!("cc.final-check");
         self.add_trail_to_cc(ctx, trail);
-        self.cc.final_check(ctx, acts);
+        with_cc!(&mut self.cc, cc => cc.final_check(ctx, acts));
     }
 
     fn partial_check<A>(
@@ -94,9 +124,6 @@ impl<C:Ctx, Th:MicroTheory<C>> theory::Theory<C> for CCTheory<C, Th> {
         acts: &mut A, trail: &theory::Trail<C>
     ) where A: theory::Actions<C>
     {
-        if ! CCI::<C,Th>::has_partial_check() {
-            return; // doesn't handle partial checks
-        }
         debug!("cc.partial-check");
         trace!("trail: {:?}", trail.as_slice());
 
@@ -105,25 +132,48 @@ impl<C:Ctx, Th:MicroTheory<C>> theory::Theory<C> for CCTheory<C, Th> {
         if !do_sth {
             return; // nothing new
         }
-        self.cc.partial_check(ctx, acts);
+        // the naive implementation only checks full models
+        with_cc!(&mut self.cc, cc => cc.partial_check(ctx, acts));
     }
 
+    // both implementations get the trail in `partial_check`; the naive
+    // one only records it, and checks it in `final_check`
     #[inline(always)]
-    fn has_partial_check() -> bool {
-        CCI::<C,Th>::has_partial_check()
+    fn has_partial_check() -> bool { true }
+
+    fn enable_propagation(&mut self, b: bool) {
+        with_cc!(&mut self.cc, cc => cc.enable_propagation(b))
     }
 
-    fn enable_propagation(&mut self, b: bool) { self.cc.enable_propagation(b) }
+    fn enable_proofs(&mut self, b: bool) { with_cc!(&mut self.cc, cc => cc.enable_proofs(b)) }
 
     #[inline]
     fn add_literal(&mut self, ctx: &mut C, t: C::AST, lit: C::B) {
-        self.cc.add_literal(ctx, t,lit);
+        with_cc!(&mut self.cc, cc => cc.add_literal(ctx, t,lit));
     }
 
     #[inline]
     fn explain_propagation(&mut self, m: &mut C, _t: C::AST, _sign: bool, p: C::B) -> &[C::B] {
         // what does `t=sign` correspond to?
         trace!("explain-prop {} sign={} (lit {:?})", pp_t(m,&_t), _sign, p);
-        self.cc.explain_prop(m, p)
+        with_cc!(&mut self.cc, cc => cc.explain_prop(m, p))
+    }
+
+    fn build_model(&mut self, ctx: &mut C, model: &mut theory::Model<C>) {
+        self.iter_repr(ctx, |t, r| model.add_repr(*t, *r));
+    }
+
+    // applications and their arguments: those are the terms
+    // congruence closure can tell something about.
+    fn iter_interface_terms<F>(&mut self, ctx: &C, mut f: F) where F: FnMut(&C::AST) {
+        self.iter_repr(ctx, |t, _| {
+            match ctx.view_as_cc_term(t) {
+                CCView::Apply(_, args) | CCView::ApplyHO(_, args) => {
+                    f(t);
+                    for u in args.iter() { f(u) }
+                },
+                _ => (),
+            }
+        });
     }
 }
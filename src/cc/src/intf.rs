@@ -107,6 +107,9 @@ pub trait CC<C: Ctx> : backtrack::Backtrackable<C> {
     /// Enable/disable boolean propagation.
     fn enable_propagation(&mut self, _on: bool) {}
 
+    /// Enable/disable recording of explanations, for proofs.
+    fn enable_proofs(&mut self, _on: bool) {}
+
     /// Explain why `p` was propagated
     fn explain_prop(&mut self, m: &C, p: C::B) -> &[C::B];
 }
@@ -203,3 +206,52 @@ pub trait HasConstructorSelect<AST> {
     fn view_as_constructor_select<'a>(&'a self, t: &'a AST)
         -> ConstructorSelectView<'a, Self::F, AST>;
 }
+
+/// Algebraic datatypes, with constructors, selectors and testers.
+///
+/// On top of `HasConstructorSelect`, this provides enough information
+/// to check acyclicity and to split on constructors.
+pub trait HasDatatype<AST> : HasConstructorSelect<AST> {
+    /// The constructors of the sort of `t`, if it is a datatype.
+    fn datatype_cstors(&self, t: &AST) -> Option<&[Self::F]>;
+
+    /// Does the sort of `t` have finitely many values?
+    fn is_finite_datatype(&self, t: &AST) -> bool;
+
+    /// Build `f(select-f-1(t), …, select-f-n(t))`.
+    ///
+    /// `t = f(select-f-1(t), …)` is the meaning of the tester `is-f(t)`.
+    fn mk_cstor_app(&mut self, f: &Self::F, t: &AST) -> AST;
+}
+
+/// A view of terms as operations on functional arrays.
+pub enum ArrayView<'a, AST> {
+    /// `select(arr, idx)` is the value of `arr` at `idx`.
+    Select {
+        arr: &'a AST,
+        idx: &'a AST,
+    },
+    /// `store(arr, idx, val)` is `arr`, except that `idx` now maps to `val`.
+    Store {
+        arr: &'a AST,
+        idx: &'a AST,
+        val: &'a AST,
+    },
+    Other(&'a AST),
+}
+
+/// Functional arrays, with extensionality.
+pub trait HasArray<AST> {
+    /// View the term as an array operation, if it is.
+    fn view_as_array<'a>(&'a self, t: &'a AST) -> ArrayView<'a, AST>;
+
+    /// Is `t` of an array sort?
+    fn is_array(&self, t: &AST) -> bool;
+
+    /// Build `select(a, i)`.
+    fn mk_select(&mut self, a: &AST, i: &AST) -> AST;
+
+    /// Build a fresh index `k` such that `a != b` implies
+    /// `select(a,k) != select(b,k)`.
+    fn mk_diff(&mut self, a: &AST, b: &AST) -> AST;
+}
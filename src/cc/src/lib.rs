@@ -17,7 +17,8 @@ pub use {
             CC as CCInterface, CCView, Ctx, IteView, HasIte,
             InjectiveView, HasInjectivity, HasDisjointness,
             SelectorView, HasSelector, ConstructorView, HasConstructor,
-            ConstructorSelectView, HasConstructorSelect,
+            ConstructorSelectView, HasConstructorSelect, HasDatatype,
+            ArrayView, HasArray,
         },
         cc::{CC, MicroTheory},
         naive_cc::NaiveCC,
@@ -89,6 +89,18 @@ impl<C:Ctx> NaiveCC<C> {
             ops: backtrack::Stack::new(),
         }
     }
+
+    /// Iterate over the terms and their representative, by solving the
+    /// current set of operations again.
+    pub fn iter_repr<F>(&mut self, m: &C, mut f: F) where F: FnMut(&C::AST, &C::AST) {
+        self.confl.clear();
+        let mut solve = Solve::new(m, &mut self.confl);
+        if solve.check_internal(self.ops.as_slice()) {
+            for t in solve.root.keys() {
+                f(t, &solve.find(*t).0)
+            }
+        }
+    }
 }
 
 // just backtrack the set of operations we'll have to perform
@@ -7,6 +7,8 @@ mod th_disjointness;
 mod th_selector;
 mod th_constructor;
 mod th_constructor_select;
+mod th_array;
+mod th_acyclic;
 
 pub use {
     th_ite::Ite,
@@ -15,6 +17,8 @@ pub use {
     th_selector::Selector,
     th_constructor::Constructor,
     th_constructor_select::ConstructorSelect,
+    th_array::Array,
+    th_acyclic::Acyclic,
 };
 
 /// A local small-vec
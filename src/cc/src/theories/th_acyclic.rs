@@ -0,0 +1,166 @@
+
+//! Acyclicity of datatypes, and case splitting on constructors
+
+use {
+    batsmt_core::{ast_u32::AST, backtrack::{Backtrackable, HashMap as BHMap}},
+    batsmt_theory::{TheoryLit, HasEq, },
+    fxhash::FxHashSet,
+    crate::{
+        cc::{self, MicroTheory, MicroTheoryArg, NodeID, CC1, },
+        theories::SVec,
+        Ctx, Actions, pp_t, ConstructorSelectView as CView, HasDatatype,
+    },
+};
+
+/// Theory of acyclicity for datatypes.
+///
+/// Values of a datatype are finite trees, so a term can never be equal
+/// to one of its strict subterms: `x = cons(a, x)` is unsatisfiable.
+/// Classes form a graph, where the class of `f(t1…tn)` points to the
+/// classes of `t1…tn`; a conflict is raised as soon as a merge
+/// creates a cycle in this graph.
+///
+/// In `final_check`, classes that contain no constructor are split upon,
+/// using `t = f1(…) ∨ … ∨ t = fn(…)` for the constructors `f1…fn`
+/// of the sort of `t`. This is only done if some selector is applied
+/// to the class, or if the datatype is finite; other classes can be
+/// given distinct fresh values.
+///
+/// This is intended to be used alongside `ConstructorSelect`.
+pub struct Acyclic {
+    cstor: BHMap<NodeID, AST>, // class -> some constructor term in it
+    has_sel: BHMap<NodeID, ()>, // classes some selector is applied to
+    split: FxHashSet<AST>, // terms already split upon
+    seen: FxHashSet<NodeID>, // for the DFS
+    path: Vec<(AST, AST)>, // `(u, t)` where `u = t` is on the cycle
+    classes: Vec<AST>, // for `final_check`
+}
+
+impl<C> Backtrackable<C> for Acyclic {
+    fn push_level(&mut self, _: &mut C) {
+        self.cstor.push_level();
+        self.has_sel.push_level();
+    }
+    fn pop_levels(&mut self, _: &mut C, n: usize) {
+        self.cstor.pop_levels(n);
+        self.has_sel.pop_levels(n);
+    }
+}
+
+impl Acyclic {
+    /// Look for a path from the class `n` back to the class `target`,
+    /// following arguments of constructors.
+    ///
+    /// On success, the path is stored (in reverse) in `self.path`.
+    fn find_cycle<C>(&mut self, c: &C, cc1: &mut CC1<C>, n: NodeID, target: NodeID) -> bool
+        where C: Ctx + HasDatatype<AST>
+    {
+        if ! self.seen.insert(n) { return false }
+        let t = match self.cstor.get(&n) {
+            Some(t) => *t,
+            None => return false,
+        };
+        let args = match c.view_as_constructor_select(&t) {
+            CView::AppConstructor(_, args) => args,
+            _ => unreachable!(),
+        };
+        for u in args.iter() {
+            let r = cc1.find_t(u);
+            let t_r = match self.cstor.get(&r) {
+                Some(t_r) => *t_r,
+                None => continue, // no constructor, no edge
+            };
+            if r == target || self.find_cycle(c, cc1, r, target) {
+                self.path.push((*u, t_r));
+                return true
+            }
+        }
+        false
+    }
+}
+
+impl<C> MicroTheory<C> for Acyclic where C: Ctx + HasDatatype<AST> + HasEq {
+    fn init(_m: &mut C) -> Self {
+        Acyclic {
+            cstor: BHMap::new(),
+            has_sel: BHMap::new(),
+            split: FxHashSet::default(),
+            seen: FxHashSet::default(),
+            path: vec!(),
+            classes: vec!(),
+        }
+    }
+
+    fn on_new_term(&mut self, c: &mut C, cc1: &mut CC1<C>, t: &AST, n: NodeID) {
+        match c.view_as_constructor_select(t) {
+            CView::AppConstructor(..) => {
+                debug_assert_eq!(n, cc1.find(n));
+                self.cstor.insert(n, *t);
+            },
+            CView::Select{sub, ..} => {
+                let n_sub = cc1.find_t(sub);
+                self.has_sel.insert(n_sub, ());
+            },
+            CView::Other(..) => (),
+        }
+    }
+
+    fn after_merge(&mut self, c: &mut C, acts: &mut MicroTheoryArg<C>, n1: NodeID, n2: NodeID) {
+        if self.has_sel.get(&n2).is_some() {
+            self.has_sel.insert(n1, ());
+        }
+        if self.cstor.get(&n1).is_none() {
+            match self.cstor.get(&n2) {
+                Some(t2) => { let t2 = *t2; self.cstor.insert(n1, t2); },
+                None => return, // no constructor, `n1` cannot be on a cycle
+            }
+        }
+
+        // any new cycle must go through `n1`
+        self.seen.clear();
+        self.path.clear();
+        if ! self.find_cycle(c, acts.cc1, n1, n1) { return }
+
+        trace!("acyclicity: cycle through {}", pp_t(c, self.cstor.get(&n1).unwrap()));
+        // conflict by `false <== u0=t1 ∧ u1=t2 ∧ … ∧ uk=t0`,
+        // where `ui` is an argument of `ti`
+        let MicroTheoryArg{cc1, n_true, n_false, combine, ..} = acts;
+        let mut v = Vec::with_capacity(self.path.len());
+        for (u, t) in self.path.iter() {
+            let n_u = cc1.get_term_id(u);
+            let n_t = cc1.get_term_id(t);
+            if n_u != n_t { v.push(cc::Expl::AreEq(n_u, n_t)) }
+        }
+        combine.push((*n_true, *n_false, cc::Expl::Conj(v)));
+    }
+
+    fn final_check(&mut self, c: &mut C, cc1: &mut CC1<C>, acts: &mut dyn Actions<C>) {
+        self.classes.clear();
+        {
+            let classes = &mut self.classes;
+            cc1.iter_repr(|t, r| if t == r { classes.push(*t) });
+        }
+
+        for i in 0 .. self.classes.len() {
+            let t = self.classes[i];
+            let n = cc1.get_term_id(&t);
+            if self.cstor.get(&n).is_some() { continue } // complete
+            let cstors: SVec<_> = match c.datatype_cstors(&t) {
+                Some(fs) => fs.iter().cloned().collect(),
+                None => continue, // not a datatype
+            };
+            if self.has_sel.get(&n).is_none() && ! c.is_finite_datatype(&t) { continue }
+            if ! self.split.insert(t) { continue }
+
+            trace!("acyclicity: split on {}", pp_t(c, &t));
+            // `t = f1(…) ∨ … ∨ t = fn(…)`
+            let mut lemma = SVec::new();
+            for f in cstors.iter() {
+                let u = c.mk_cstor_app(f, &t);
+                let eq = c.mk_eq(&t, &u);
+                lemma.push(acts.map_lit(c, TheoryLit::new_t(eq, true)));
+            }
+            acts.add_lemma(&lemma[..]);
+        }
+    }
+}
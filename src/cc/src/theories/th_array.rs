@@ -0,0 +1,234 @@
+
+use {
+    batsmt_core::{ast_u32::AST, backtrack::{Backtrackable, HashMap as BHMap}},
+    batsmt_theory::{TheoryLit, HasEq, },
+    fxhash::FxHashSet,
+    crate::{
+        cc::{MicroTheory, MicroTheoryArg, NodeID, CC1, },
+        theories::SVec,
+        Ctx, Actions, CCView, pp_t, ArrayView, HasArray, },
+};
+
+/// Theory of functional arrays, with extensionality.
+///
+/// Axioms are instantiated lazily, as lemmas:
+///
+/// - `select(store(a,i,v),i) = v` for every `store` term;
+/// - `i = j ∨ select(store(a,i,v),j) = select(a,j)`
+///     whenever some `select(b,j)` is such that `b` is equal to
+///     either `store(a,i,v)` or `a`;
+/// - `a = b ∨ select(a,k) != select(b,k)` (with `k` fresh) for every
+///     equation `a = b` between arrays, in final check.
+///
+/// Triggers are detected in `on_new_term` and `after_merge`,
+/// and the lemmas are added at the end of the next check.
+pub struct Array {
+    stores: BHMap<NodeID, SVec<AST>>, // class -> `store` terms in it
+    store_parents: BHMap<NodeID, SVec<AST>>, // class of `a` -> `store(a,_,_)` terms
+    selects: BHMap<NodeID, SVec<AST>>, // class of `a` -> `select(a,_)` terms
+    pending: Vec<Lemma>, // to be added
+    done: FxHashSet<Lemma>, // already added
+}
+
+/// An instance of one of the axioms.
+#[derive(Copy,Clone,Debug,Eq,PartialEq,Hash)]
+enum Lemma {
+    Idx(AST), // `store(a,i,v)`
+    Row(AST, AST), // `store(a,i,v)`, `j`
+    Ext(AST), // `a = b`
+}
+
+// add `t` to the list of `n`
+fn push_to(m: &mut BHMap<NodeID, SVec<AST>>, n: NodeID, t: AST) {
+    m.update(&n, |_, v_opt| {
+        let mut v = v_opt.cloned().unwrap_or_else(SVec::new);
+        v.push(t);
+        v
+    })
+}
+
+// add the list of `n2` to the list of `n1`
+fn append_to(m: &mut BHMap<NodeID, SVec<AST>>, n1: NodeID, n2: NodeID) {
+    if let Some(v2) = m.get(&n2) {
+        let mut v2 = v2.clone();
+        m.update(&n1, move |_, v1_opt| {
+            if let Some(v1) = v1_opt {
+                for x in v1.iter().cloned() { v2.push(x) };
+            }
+            v2
+        });
+    }
+}
+
+impl Array {
+    /// Instances of read-over-write for `select(_, j)` terms
+    /// and `store` terms of `stores`.
+    fn row<C>(pending: &mut Vec<Lemma>, c: &C, sel: &SVec<AST>, stores: &SVec<AST>)
+        where C: Ctx + HasArray<AST>
+    {
+        for t_sel in sel.iter() {
+            let j = match c.view_as_array(t_sel) {
+                ArrayView::Select{idx, ..} => *idx,
+                _ => unreachable!(),
+            };
+            for s in stores.iter() {
+                pending.push(Lemma::Row(*s, j));
+            }
+        }
+    }
+
+    /// Find instances between the classes `n1` and `n2`.
+    fn cross<C>(&mut self, c: &C, n1: NodeID, n2: NodeID)
+        where C: Ctx + HasArray<AST>
+    {
+        if let Some(sel) = self.selects.get(&n1) {
+            if let Some(st) = self.stores.get(&n2) {
+                Array::row(&mut self.pending, c, sel, st);
+            }
+            if let Some(st) = self.store_parents.get(&n2) {
+                Array::row(&mut self.pending, c, sel, st);
+            }
+        }
+    }
+
+    /// Add pending lemmas. Extensionality lemmas are only added if `ext` is true.
+    fn add_lemmas<C>(&mut self, c: &mut C, acts: &mut dyn Actions<C>, ext: bool)
+        where C: Ctx + HasArray<AST> + HasEq
+    {
+        if self.pending.len() == 0 { return }
+        let mut pending = std::mem::replace(&mut self.pending, vec!());
+        for l in pending.drain(..) {
+            if let Lemma::Ext(..) = l {
+                if !ext { self.pending.push(l); continue }
+            }
+            if ! self.done.insert(l) { continue }
+
+            macro_rules! lit {
+                ($t: expr, $sign: expr) => {
+                    acts.map_lit(c, TheoryLit::new_t($t, $sign))
+                }
+            }
+            trace!("array.add-lemma {:?}", l);
+            match l {
+                Lemma::Idx(s) => {
+                    let (i, v) = match c.view_as_array(&s) {
+                        ArrayView::Store{idx, val, ..} => (*idx, *val),
+                        _ => unreachable!(),
+                    };
+                    let sel = c.mk_select(&s, &i);
+                    let eq = c.mk_eq(&sel, &v);
+                    let l_eq = lit!(eq, true);
+                    acts.add_lemma(&[l_eq]);
+                },
+                Lemma::Row(s, j) => {
+                    let (a, i) = match c.view_as_array(&s) {
+                        ArrayView::Store{arr, idx, ..} => (*arr, *idx),
+                        _ => unreachable!(),
+                    };
+                    if i == j { continue } // see `Idx`
+                    let eq_ij = c.mk_eq(&i, &j);
+                    let sel_s = c.mk_select(&s, &j);
+                    let sel_a = c.mk_select(&a, &j);
+                    let eq_sel = c.mk_eq(&sel_s, &sel_a);
+                    let l_ij = lit!(eq_ij, true);
+                    let l_sel = lit!(eq_sel, true);
+                    acts.add_lemma(&[l_ij, l_sel]);
+                },
+                Lemma::Ext(eq) => {
+                    let (a, b) = match c.view_as_cc_term(&eq) {
+                        CCView::Eq(a, b) => (*a, *b),
+                        _ => unreachable!(),
+                    };
+                    let k = c.mk_diff(&a, &b);
+                    let sel_a = c.mk_select(&a, &k);
+                    let sel_b = c.mk_select(&b, &k);
+                    let eq_sel = c.mk_eq(&sel_a, &sel_b);
+                    let l_eq = lit!(eq, true);
+                    let l_sel = lit!(eq_sel, false);
+                    acts.add_lemma(&[l_eq, l_sel]);
+                },
+            }
+        }
+        self.pending.extend(pending.drain(..)); // reuse allocation
+    }
+}
+
+impl<C> Backtrackable<C> for Array {
+    fn push_level(&mut self, _: &mut C) {
+        self.stores.push_level();
+        self.store_parents.push_level();
+        self.selects.push_level();
+    }
+    fn pop_levels(&mut self, _: &mut C, n: usize) {
+        self.stores.pop_levels(n);
+        self.store_parents.pop_levels(n);
+        self.selects.pop_levels(n);
+    }
+}
+
+impl<C> MicroTheory<C> for Array where C: Ctx + HasArray<AST> + HasEq {
+    fn init(_m: &mut C) -> Self {
+        Array {
+            stores: BHMap::new(),
+            store_parents: BHMap::new(),
+            selects: BHMap::new(),
+            pending: vec!(),
+            done: FxHashSet::default(),
+        }
+    }
+
+    fn on_new_term(&mut self, c: &mut C, cc1: &mut CC1<C>, t: &AST, n: NodeID) {
+        match c.view_as_array(t) {
+            ArrayView::Store{arr, ..} => {
+                trace!("array: new store term {}", pp_t(c, t));
+                self.pending.push(Lemma::Idx(*t));
+                let n_arr = cc1.find_t(arr);
+                push_to(&mut self.stores, n, *t);
+                push_to(&mut self.store_parents, n_arr, *t);
+                let st = SVec::from_elem(*t, 1);
+                for n2 in [n, n_arr].iter() {
+                    if let Some(sel) = self.selects.get(n2) {
+                        Array::row(&mut self.pending, c, sel, &st);
+                    }
+                }
+            },
+            ArrayView::Select{arr, ..} => {
+                let n_arr = cc1.find_t(arr);
+                push_to(&mut self.selects, n_arr, *t);
+                let sel = SVec::from_elem(*t, 1);
+                if let Some(st) = self.stores.get(&n_arr) {
+                    Array::row(&mut self.pending, c, &sel, st);
+                }
+                if let Some(st) = self.store_parents.get(&n_arr) {
+                    Array::row(&mut self.pending, c, &sel, st);
+                }
+            },
+            ArrayView::Other(..) => {
+                if let CCView::Eq(a, _) = c.view_as_cc_term(t) {
+                    if c.is_array(a) {
+                        self.pending.push(Lemma::Ext(*t));
+                    }
+                }
+            },
+        }
+    }
+
+    fn before_merge(&mut self, c: &mut C, _acts: &mut MicroTheoryArg<C>, a: NodeID, b: NodeID) {
+        self.cross(c, a, b);
+        self.cross(c, b, a);
+    }
+
+    fn after_merge(&mut self, _c: &mut C, _acts: &mut MicroTheoryArg<C>, n1: NodeID, n2: NodeID) {
+        append_to(&mut self.stores, n1, n2);
+        append_to(&mut self.store_parents, n1, n2);
+        append_to(&mut self.selects, n1, n2);
+    }
+
+    fn partial_check(&mut self, c: &mut C, _cc1: &mut CC1<C>, acts: &mut dyn Actions<C>) {
+        self.add_lemmas(c, acts, false)
+    }
+
+    fn final_check(&mut self, c: &mut C, _cc1: &mut CC1<C>, acts: &mut dyn Actions<C>) {
+        self.add_lemmas(c, acts, true)
+    }
+}
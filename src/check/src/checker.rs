@@ -0,0 +1,585 @@
+
+//! The proof checker.
+//!
+//! It replays proofs in the format described in `batsmt_solver::proof`.
+//! Steps are checked in order, using a clause database of active clauses:
+//!
+//! - `assume` steps are trusted (they are checked against the input
+//!     problem elsewhere, if at all);
+//! - `tseitin` steps must be one of the definitional clauses of a
+//!     connective occurring in the clause;
+//! - `th-lemma` steps are checked by replaying their explanation, if any,
+//!     or by running a naive congruence closure on the negation of the clause;
+//!     `(axiom a b)` equations of explanations are trusted, and
+//!     counted (see `Checker::n_trusted`);
+//! - `trust` steps are trusted, and counted too;
+//! - `drup` steps must follow from active clauses by unit propagation.
+//!
+//! The proof is a refutation if some `drup` step derives the empty clause,
+//! or a clause of pure boolean literals (a refutation under assumptions).
+//! A proof written by an incremental solver may have several of them.
+//!
+//! This is meant to be simple rather than fast.
+
+use {
+    std::fmt,
+    batsmt_core::{Manager, AstView, ast_u32::AST, },
+    batsmt_hast::{HManager, StrSymbolManager, },
+    batsmt_pretty as pp,
+    fxhash::FxHashMap,
+    crate::sexp::Sexp,
+};
+
+type M = HManager<StrSymbolManager>;
+
+/// A literal, made of a term (that is not a negation) and a sign.
+type Lit = (AST, bool);
+
+/// A clause, as a sorted list of literals without duplicates.
+type Clause = Vec<Lit>;
+
+/// Builtin symbols.
+struct Builtins {
+    true_: AST,
+    false_: AST,
+    not_: AST,
+    eq: AST,
+    and_: AST,
+    or_: AST,
+    imply_: AST,
+    ite: AST,
+}
+
+/// A boolean-centric view of terms.
+enum View<'a> {
+    Not(AST),
+    Eq(AST, AST),
+    And(&'a [AST]),
+    Or(&'a [AST]),
+    Imply(&'a [AST]),
+    Ite(AST, AST, AST),
+    Other,
+}
+
+/// A step of the explanation of a theory lemma.
+#[derive(Clone,Debug)]
+enum Hint {
+    Lit(AST, AST),
+    Cong(AST, AST),
+    Eq(AST, AST, Vec<(AST, AST)>),
+    Axiom(AST, AST),
+}
+
+/// Error for the first step that fails to check.
+#[derive(Debug)]
+pub struct StepError {
+    /// Name of the step, or its index if it has no name.
+    pub step: String,
+    pub msg: String,
+}
+
+impl fmt::Display for StepError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        write!(out, "step {}: {}", self.step, self.msg)
+    }
+}
+
+impl std::error::Error for StepError {}
+
+/// Outcome of a successful check.
+#[derive(Debug,Clone,Copy,Eq,PartialEq)]
+pub enum Outcome {
+    /// The empty clause was derived.
+    Refutation,
+    /// A clause made only of pure boolean literals was derived, i.e.
+    /// the problem is refuted under some assumptions.
+    RefutationUnderAssumptions,
+}
+
+/// Union-find on terms.
+struct UF(FxHashMap<AST, AST>);
+
+impl UF {
+    fn new() -> Self { UF(FxHashMap::default()) }
+
+    fn find(&self, mut t: AST) -> AST {
+        while let Some(u) = self.0.get(&t) { t = *u }
+        t
+    }
+
+    fn eq(&self, a: AST, b: AST) -> bool { self.find(a) == self.find(b) }
+
+    /// Merge classes of `a` and `b`, return `true` if they were distinct.
+    fn union(&mut self, a: AST, b: AST) -> bool {
+        let ra = self.find(a);
+        let rb = self.find(b);
+        if ra == rb { false } else { self.0.insert(ra, rb); true }
+    }
+}
+
+/// The checker's state.
+pub struct Checker {
+    m: M,
+    b: Builtins,
+    syms: FxHashMap<String, AST>, // symbols are shared
+    clauses: FxHashMap<Clause, usize>, // active clauses, with multiplicity
+    n_steps: usize,
+    n_trusted: usize,
+    outcome: Option<Outcome>, // best refutation so far
+}
+
+impl Checker {
+    /// New checker.
+    pub fn new() -> Self {
+        let mut syms = FxHashMap::default();
+        let mut m = HManager::new();
+        let mut sym = |s: &str| -> AST {
+            let t = m.mk_string(s.to_string(), None);
+            syms.insert(s.to_string(), t);
+            t
+        };
+        let b = Builtins {
+            true_: sym("true"), false_: sym("false"), not_: sym("not"),
+            eq: sym("="), and_: sym("and"), or_: sym("or"), imply_: sym("=>"),
+            ite: sym("ite"),
+        };
+        Checker {
+            m, b, syms, clauses: FxHashMap::default(), n_steps: 0, n_trusted: 0, outcome: None,
+        }
+    }
+
+    /// Number of steps checked so far.
+    pub fn n_steps(&self) -> usize { self.n_steps }
+
+    /// Number of trusted theory axioms and `trust` steps used so far.
+    pub fn n_trusted(&self) -> usize { self.n_trusted }
+
+    /// Check the whole proof, stopping at the first step that fails.
+    pub fn check_all(&mut self, steps: &[Sexp]) -> Result<Outcome, StepError> {
+        for s in steps.iter() {
+            self.check_step(s)?;
+        }
+        self.outcome.ok_or_else(|| StepError{
+            step: "<end>".to_string(),
+            msg: "the proof contains no refutation".to_string(),
+        })
+    }
+
+    /// Check one step, and add its clause to the active clauses.
+    pub fn check_step(&mut self, s: &Sexp) -> Result<(), StepError> {
+        self.n_steps += 1;
+        let idx = self.n_steps;
+        let l = match s {
+            Sexp::List(l) if l.len() >= 2 => l,
+            _ => return Err(StepError{step: format!("#{}", idx), msg: "expected a step".into()}),
+        };
+        let name = match &l[1] {
+            Sexp::Atom(n) if l[0] != Sexp::Atom("delete".into()) => n.clone(),
+            _ => format!("#{}", idx),
+        };
+        debug!("check step {}", name);
+        let mk_err = |msg: String| StepError{step: name.clone(), msg};
+
+        match &l[0] {
+            Sexp::Atom(a) if a == "delete" => {
+                let c = self.clause(&l[1]).map_err(mk_err)?;
+                self.remove_clause(&c);
+            },
+            Sexp::Atom(a) if a == "assume" && l.len() >= 3 => {
+                let c = self.clause(&l[2]).map_err(mk_err)?;
+                self.add_clause(c);
+            },
+            Sexp::Atom(a) if a == "step" && l.len() >= 3 => {
+                let c = self.clause(&l[2]).map_err(mk_err)?;
+                let rule = match attr(&l[3..], ":rule") {
+                    Some(Sexp::Atom(r)) => r.as_str(),
+                    _ => return Err(mk_err("missing `:rule`".into())),
+                };
+                match rule {
+                    "tseitin" => self.check_tseitin(&c),
+                    "th-lemma" => {
+                        match attr(&l[3..], ":explanation") {
+                            Some(e) => self.check_th_lemma_expl(&c, e),
+                            None => self.check_th_lemma(&c),
+                        }
+                    },
+                    "trust" => {
+                        self.n_trusted += 1;
+                        Ok(())
+                    },
+                    "drup" => self.check_rup(&c),
+                    r => Err(format!("unknown rule `{}`", r)),
+                }.map_err(mk_err)?;
+                if rule == "drup" {
+                    if c.is_empty() {
+                        self.outcome = Some(Outcome::Refutation);
+                    } else if self.outcome.is_none() && c.iter().all(|(t,_)| self.is_pure_bool(*t)) {
+                        self.outcome = Some(Outcome::RefutationUnderAssumptions);
+                    }
+                }
+                self.add_clause(c);
+            },
+            _ => return Err(mk_err(format!("unknown step {}", s))),
+        }
+        Ok(())
+    }
+
+    fn add_clause(&mut self, c: Clause) {
+        *self.clauses.entry(c).or_insert(0) += 1;
+    }
+
+    fn remove_clause(&mut self, c: &Clause) {
+        let n = match self.clauses.get_mut(c) {
+            None => return, // unknown clause
+            Some(n) => { *n -= 1; *n },
+        };
+        if n == 0 { self.clauses.remove(c); }
+    }
+
+    // pure boolean literal, without a term (selector, activation literal, …),
+    // printed as `@bN` by the solver
+    fn is_pure_bool(&self, t: AST) -> bool {
+        match self.m.view(&t) {
+            AstView::Const(s) => {
+                s.len() > 2 && s.starts_with("@b") && s[2..].bytes().all(|c| c.is_ascii_digit())
+            },
+            _ => false,
+        }
+    }
+
+    fn pp_t(&self, t: AST) -> String { pp::pp1(&self.m, &t).to_string() }
+
+    fn pp_clause(&self, c: &Clause) -> String {
+        let lits: Vec<String> = c.iter().map(|(t,sign)| {
+            if *sign { self.pp_t(*t) } else { format!("(not {})", self.pp_t(*t)) }
+        }).collect();
+        format!("(cl {})", lits.join(" "))
+    }
+
+    /// Build a term from a S-expression.
+    fn term(&mut self, e: &Sexp) -> Result<AST, String> {
+        match e {
+            Sexp::Atom(s) => {
+                if let Some(t) = self.syms.get(s) { return Ok(*t) }
+                let t = self.m.mk_string(s.clone(), None);
+                self.syms.insert(s.clone(), t);
+                Ok(t)
+            },
+            Sexp::List(l) if l.len() >= 2 => {
+                let f = self.term(&l[0])?;
+                let mut args = Vec::with_capacity(l.len()-1);
+                for u in l[1..].iter() { args.push(self.term(u)?) }
+                Ok(self.m.mk_app(f, &args, None))
+            },
+            _ => Err(format!("expected a term, got {}", e)),
+        }
+    }
+
+    /// Literal for the term `t`, removing negations.
+    fn lit(&self, mut t: AST) -> Lit {
+        let mut sign = true;
+        while let View::Not(u) = self.view(t) {
+            t = u;
+            sign = ! sign;
+        }
+        (t, sign)
+    }
+
+    fn clause(&mut self, e: &Sexp) -> Result<Clause, String> {
+        match e {
+            Sexp::List(l) if l.len() >= 1 && l[0] == Sexp::Atom("cl".into()) => {
+                let mut c = Vec::with_capacity(l.len()-1);
+                for u in l[1..].iter() {
+                    let t = self.term(u)?;
+                    c.push(self.lit(t));
+                }
+                Ok(norm(c))
+            },
+            _ => Err(format!("expected a clause, got {}", e)),
+        }
+    }
+
+    fn view(&self, t: AST) -> View {
+        let b = &self.b;
+        match self.m.view(&t) {
+            AstView::App{f, args} if *f == b.not_ && args.len() == 1 => View::Not(args[0]),
+            AstView::App{f, args} if *f == b.eq && args.len() == 2 => View::Eq(args[0], args[1]),
+            AstView::App{f, args} if *f == b.and_ => View::And(args),
+            AstView::App{f, args} if *f == b.or_ => View::Or(args),
+            AstView::App{f, args} if *f == b.imply_ && args.len() >= 2 => View::Imply(args),
+            AstView::App{f, args} if *f == b.ite && args.len() == 3 => {
+                View::Ite(args[0], args[1], args[2])
+            },
+            _ => View::Other,
+        }
+    }
+
+    /// Check that `c` is a definitional clause of a connective.
+    fn check_tseitin(&self, c: &Clause) -> Result<(), String> {
+        let b = &self.b;
+        if *c == [(b.true_, true)] || *c == [(b.false_, false)] {
+            return Ok(())
+        }
+        for &(t, _) in c.iter() {
+            let top = (t, true);
+            let mut defs: Vec<Clause> = vec!();
+            match self.view(t) {
+                View::And(args) => {
+                    let subs: Vec<Lit> = args.iter().map(|u| self.lit(*u)).collect();
+                    for &s in subs.iter() { defs.push(vec!(neg(top), s)) }
+                    defs.push(subs.iter().map(|s| neg(*s)).chain(Some(top)).collect());
+                },
+                View::Or(args) => {
+                    let subs: Vec<Lit> = args.iter().map(|u| self.lit(*u)).collect();
+                    for &s in subs.iter() { defs.push(vec!(neg(s), top)) }
+                    defs.push(subs.iter().cloned().chain(Some(neg(top))).collect());
+                },
+                View::Imply(args) => {
+                    let n = args.len();
+                    let subs: Vec<Lit> =
+                        Some(self.lit(args[n-1])).into_iter()
+                        .chain(args[..n-1].iter().map(|u| neg(self.lit(*u))))
+                        .collect();
+                    for &s in subs.iter() { defs.push(vec!(neg(s), top)) }
+                    defs.push(subs.iter().cloned().chain(Some(neg(top))).collect());
+                },
+                _ => (),
+            }
+            if defs.into_iter().any(|d| norm(d) == *c) {
+                return Ok(())
+            }
+        }
+        Err(format!("{} is not a Tseitin clause", self.pp_clause(c)))
+    }
+
+    /// Check that `c` follows from the active clauses by unit propagation.
+    fn check_rup(&self, c: &Clause) -> Result<(), String> {
+        let mut assign: FxHashMap<AST, bool> = FxHashMap::default();
+        for &(t, sign) in c.iter() {
+            if assign.insert(t, !sign) == Some(sign) {
+                return Ok(()) // tautology
+            }
+        }
+        loop {
+            let mut changed = false;
+            for c2 in self.clauses.keys() {
+                let mut unassigned = None;
+                let mut n_unassigned = 0;
+                let mut sat = false;
+                for &(t, sign) in c2.iter() {
+                    match assign.get(&t) {
+                        Some(v) if *v == sign => { sat = true; break },
+                        Some(_) => (),
+                        None => { n_unassigned += 1; unassigned = Some((t,sign)) },
+                    }
+                }
+                if sat { continue }
+                match (n_unassigned, unassigned) {
+                    (0, _) => return Ok(()), // conflict
+                    (1, Some((t, sign))) => {
+                        assign.insert(t, sign);
+                        changed = true;
+                    },
+                    _ => (),
+                }
+            }
+            if ! changed {
+                return Err(format!("{} does not follow by unit propagation", self.pp_clause(c)))
+            }
+        }
+    }
+
+    /// Equations asserted by the negation of `c`.
+    fn asserted_eqns(&self, c: &Clause) -> Vec<(AST, AST)> {
+        let mut v = vec!();
+        for &(t, sign) in c.iter() {
+            if let (false, View::Eq(a, b)) = (sign, self.view(t)) {
+                v.push((a, b))
+            }
+            v.push((t, if sign { self.b.false_ } else { self.b.true_ }));
+        }
+        v
+    }
+
+    /// Does `a = b` follow from equalities in `uf` by one of the theory rules?
+    fn th_rule(&self, uf: &UF, a: AST, b: AST) -> bool {
+        let (t, f) = (self.b.true_, self.b.false_);
+        match self.view(a) {
+            View::Eq(x, y) => b == t && uf.eq(x, y),
+            View::Not(x) => (b == f && uf.eq(x, t)) || (b == t && uf.eq(x, f)),
+            View::Ite(c, x, y) => {
+                (b == x && uf.eq(c, t)) || (b == y && uf.eq(c, f))
+                || ((b == x || b == y) && uf.eq(x, y))
+            },
+            _ => false,
+        }
+    }
+
+    /// Are `a` and `b` congruent applications, modulo `uf`?
+    fn congruent(&self, uf: &UF, a: AST, b: AST) -> bool {
+        match (self.m.view(&a), self.m.view(&b)) {
+            (AstView::App{f: f1, args: args1}, AstView::App{f: f2, args: args2}) => {
+                args1.len() == args2.len() && uf.eq(*f1, *f2)
+                && args1.iter().zip(args2.iter()).all(|(x,y)| uf.eq(*x, *y))
+            },
+            _ => false,
+        }
+    }
+
+    fn hint(&mut self, e: &Sexp) -> Result<Hint, String> {
+        match e {
+            Sexp::List(l) if l.len() >= 3 => {
+                let a = self.term(&l[1])?;
+                let b = self.term(&l[2])?;
+                match &l[0] {
+                    Sexp::Atom(k) if k == "lit" => Ok(Hint::Lit(a, b)),
+                    Sexp::Atom(k) if k == "cong" => Ok(Hint::Cong(a, b)),
+                    Sexp::Atom(k) if k == "axiom" => Ok(Hint::Axiom(a, b)),
+                    Sexp::Atom(k) if k == "eq" => {
+                        let mut v = vec!();
+                        for p in l[3..].iter() {
+                            match p {
+                                Sexp::List(p) if p.len() == 2 => {
+                                    v.push((self.term(&p[0])?, self.term(&p[1])?))
+                                },
+                                _ => return Err(format!("expected a pair, got {}", p)),
+                            }
+                        }
+                        Ok(Hint::Eq(a, b, v))
+                    },
+                    _ => Err(format!("unknown explanation step {}", e)),
+                }
+            },
+            _ => Err(format!("expected an explanation step, got {}", e)),
+        }
+    }
+
+    /// Check a theory lemma, using its explanation.
+    ///
+    /// Every equation of the explanation must be justified by the negation
+    /// of the clause, by congruence, or by a theory rule, using only
+    /// previously justified equations. In the end, `true = false` must hold.
+    fn check_th_lemma_expl(&mut self, c: &Clause, e: &Sexp) -> Result<(), String> {
+        let mut hints = match e {
+            Sexp::List(l) => {
+                let mut v = vec!();
+                for h in l.iter() { v.push(self.hint(h)?) }
+                v
+            },
+            _ => return Err(format!("expected a list of equations, got {}", e)),
+        };
+        let asserted = self.asserted_eqns(c);
+        let mut uf = UF::new();
+
+        // justify equations until fixpoint
+        loop {
+            let n = hints.len();
+            let mut i = 0;
+            while i < hints.len() {
+                let ok = match &hints[i] {
+                    Hint::Lit(a, b) => {
+                        asserted.iter().any(|&(x,y)| (x,y) == (*a,*b) || (y,x) == (*a,*b))
+                    },
+                    Hint::Cong(a, b) => self.congruent(&uf, *a, *b),
+                    Hint::Eq(a, b, v) => {
+                        v.iter().all(|(x,y)| uf.eq(*x, *y))
+                        && (self.th_rule(&uf, *a, *b) || self.th_rule(&uf, *b, *a))
+                    },
+                    Hint::Axiom(..) => true, // trusted
+                };
+                if ok {
+                    let (a, b) = match hints.swap_remove(i) {
+                        Hint::Axiom(a,b) => { self.n_trusted += 1; (a,b) },
+                        Hint::Lit(a,b) | Hint::Cong(a,b) | Hint::Eq(a,b,_) => (a,b),
+                    };
+                    uf.union(a, b);
+                } else {
+                    i += 1;
+                }
+            }
+            if hints.len() == n { break }
+        }
+
+        if let Some(h) = hints.first() {
+            let (k, a, b) = match h {
+                Hint::Lit(a,b) => ("lit", a, b),
+                Hint::Cong(a,b) => ("cong", a, b),
+                Hint::Eq(a,b,_) => ("eq", a, b),
+                Hint::Axiom(a,b) => ("axiom", a, b),
+            };
+            Err(format!("cannot justify ({} {} {})", k, self.pp_t(*a), self.pp_t(*b)))
+        } else if ! uf.eq(self.b.true_, self.b.false_) {
+            Err(format!("explanation of {} does not entail `true = false`", self.pp_clause(c)))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check a theory lemma with a naive congruence closure on the
+    /// subterms of `c`.
+    fn check_th_lemma(&self, c: &Clause) -> Result<(), String> {
+        // gather subterms
+        let mut terms = vec!(self.b.true_, self.b.false_);
+        {
+            let mut seen: FxHashMap<AST, ()> = FxHashMap::default();
+            let mut st: Vec<AST> = c.iter().map(|(t,_)| *t).collect();
+            while let Some(t) = st.pop() {
+                if seen.insert(t, ()).is_some() { continue }
+                terms.push(t);
+                if let AstView::App{f, args} = self.m.view(&t) {
+                    st.push(*f);
+                    st.extend_from_slice(args);
+                }
+            }
+        }
+
+        let mut uf = UF::new();
+        for (a, b) in self.asserted_eqns(c) { uf.union(a, b); }
+
+        loop {
+            let mut changed = false;
+            let mut sigs: FxHashMap<Vec<AST>, AST> = FxHashMap::default();
+            for &t in terms.iter() {
+                if let AstView::App{f, args} = self.m.view(&t) {
+                    let sig: Vec<AST> =
+                        Some(*f).iter().chain(args.iter()).map(|u| uf.find(*u)).collect();
+                    match sigs.get(&sig) {
+                        Some(u) => { changed |= uf.union(t, *u); },
+                        None => { sigs.insert(sig, t); },
+                    }
+                }
+                let cands = match self.view(t) {
+                    View::Eq(..) | View::Not(_) => [self.b.true_, self.b.false_],
+                    View::Ite(_, x, y) => [x, y],
+                    _ => continue,
+                };
+                for &u in cands.iter() {
+                    if ! uf.eq(t, u) && self.th_rule(&uf, t, u) {
+                        uf.union(t, u);
+                        changed = true;
+                    }
+                }
+            }
+            if uf.eq(self.b.true_, self.b.false_) {
+                return Ok(())
+            } else if ! changed {
+                return Err(format!("{} is not a theory tautology", self.pp_clause(c)))
+            }
+        }
+    }
+}
+
+// find the value of attribute `k` in `l`
+fn attr<'a>(l: &'a [Sexp], k: &str) -> Option<&'a Sexp> {
+    l.iter().position(|e| *e == Sexp::Atom(k.to_string()))
+        .and_then(|i| l.get(i+1))
+}
+
+#[inline]
+fn neg((t, sign): Lit) -> Lit { (t, !sign) }
+
+fn norm(mut c: Clause) -> Clause {
+    c.sort_unstable();
+    c.dedup();
+    c
+}
@@ -0,0 +1,10 @@
+
+//! Checker for the proofs produced by `batsmt_solver` (see its
+//! `proof` module for the format).
+
+#[macro_use] extern crate log;
+
+pub mod sexp;
+pub mod checker;
+
+pub use crate::checker::{Checker, Outcome, StepError, };
@@ -0,0 +1,42 @@
+
+// Proof checker
+
+#[macro_use] extern crate log;
+
+use {
+    std::{env, fs, io::{self, Read}, error::Error, process, },
+    batsmt_check::{sexp, Checker, Outcome, },
+};
+
+fn main() -> Result<(), Box<Error>> {
+    batsmt_logger::init();
+
+    let mut s = String::new();
+    match env::args().skip(1).next() {
+        Some(file) if file != "-" => {
+            info!("read proof from {}", file);
+            fs::File::open(&file)?.read_to_string(&mut s)?;
+        },
+        _ => {
+            info!("read proof from stdin");
+            io::stdin().read_to_string(&mut s)?;
+        },
+    };
+
+    let steps = sexp::parse_all(&s)?;
+    let mut checker = Checker::new();
+    match checker.check_all(&steps) {
+        Ok(Outcome::Refutation) => {
+            println!("OK ({} steps, {} trusted axioms)", checker.n_steps(), checker.n_trusted());
+        },
+        Ok(Outcome::RefutationUnderAssumptions) => {
+            println!("OK under assumptions ({} steps, {} trusted axioms)",
+                checker.n_steps(), checker.n_trusted());
+        },
+        Err(e) => {
+            println!("FAIL at {}", e);
+            process::exit(1);
+        },
+    }
+    Ok(())
+}
@@ -0,0 +1,136 @@
+
+//! Minimal S-expression reader, for proofs.
+
+use {
+    std::{fmt, error::Error},
+};
+
+/// A S-expression.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub enum Sexp {
+    Atom(String),
+    List(Vec<Sexp>),
+}
+
+/// Parse error, with the line at which it occurred.
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        write!(out, "parse error at line {}: {}", self.line, self.msg)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Read a sequence of S-expressions from `s`.
+pub fn parse_all(s: &str) -> Result<Vec<Sexp>, ParseError> {
+    let mut p = Parser{s: s.as_bytes(), i: 0, line: 1};
+    let mut res = vec!();
+    loop {
+        p.skip_spaces();
+        if p.i >= p.s.len() { break }
+        res.push(p.sexp()?);
+    }
+    Ok(res)
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    i: usize,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn err<T>(&self, msg: &str) -> Result<T, ParseError> {
+        Err(ParseError{line: self.line, msg: msg.to_string()})
+    }
+
+    // skip whitespace and comments
+    fn skip_spaces(&mut self) {
+        while self.i < self.s.len() {
+            match self.s[self.i] {
+                b'\n' => { self.line += 1; self.i += 1 },
+                b' ' | b'\t' | b'\r' => self.i += 1,
+                b';' => {
+                    while self.i < self.s.len() && self.s[self.i] != b'\n' { self.i += 1 }
+                },
+                _ => break,
+            }
+        }
+    }
+
+    fn sexp(&mut self) -> Result<Sexp, ParseError> {
+        self.skip_spaces();
+        if self.i >= self.s.len() {
+            return self.err("unexpected end of input")
+        }
+        match self.s[self.i] {
+            b'(' => {
+                self.i += 1;
+                let mut l = vec!();
+                loop {
+                    self.skip_spaces();
+                    if self.i >= self.s.len() {
+                        return self.err("unclosed '('")
+                    } else if self.s[self.i] == b')' {
+                        self.i += 1;
+                        break
+                    }
+                    l.push(self.sexp()?);
+                }
+                Ok(Sexp::List(l))
+            },
+            b')' => self.err("unexpected ')'"),
+            b'|' => {
+                // quoted symbol, keep the bars so it's printed identically
+                let start = self.i;
+                self.i += 1;
+                while self.i < self.s.len() && self.s[self.i] != b'|' {
+                    if self.s[self.i] == b'\n' { self.line += 1 }
+                    self.i += 1
+                }
+                if self.i >= self.s.len() { return self.err("unclosed '|'") }
+                self.i += 1;
+                self.atom(start)
+            },
+            _ => {
+                let start = self.i;
+                while self.i < self.s.len() {
+                    match self.s[self.i] {
+                        b' ' | b'\t' | b'\r' | b'\n' | b'(' | b')' | b';' => break,
+                        _ => self.i += 1,
+                    }
+                }
+                self.atom(start)
+            },
+        }
+    }
+
+    fn atom(&self, start: usize) -> Result<Sexp, ParseError> {
+        match std::str::from_utf8(&self.s[start .. self.i]) {
+            Ok(s) => Ok(Sexp::Atom(s.to_string())),
+            Err(_) => self.err("invalid utf8"),
+        }
+    }
+}
+
+impl fmt::Display for Sexp {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sexp::Atom(s) => write!(out, "{}", s),
+            Sexp::List(l) => {
+                write!(out, "(")?;
+                for (i,e) in l.iter().enumerate() {
+                    if i > 0 { write!(out, " ")?; }
+                    write!(out, "{}", e)?;
+                }
+                write!(out, ")")
+            },
+        }
+    }
+}
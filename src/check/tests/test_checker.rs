@@ -0,0 +1,118 @@
+extern crate batsmt_check;
+
+use batsmt_check::{sexp, Checker, Outcome, StepError, };
+
+fn check(proof: &str) -> Result<(Outcome, usize), StepError> {
+    let steps = sexp::parse_all(proof).unwrap();
+    let mut checker = Checker::new();
+    let res = checker.check_all(&steps)?;
+    Ok((res, checker.n_trusted()))
+}
+
+// name of the step that fails
+fn failing_step(proof: &str) -> String {
+    match check(proof) {
+        Ok(r) => panic!("proof should be rejected, got {:?}", r),
+        Err(e) => e.step,
+    }
+}
+
+#[test]
+fn test_drup() {
+    let p = "(assume c0 (cl a b)) (assume c1 (cl (not a) b)) (assume c2 (cl (not b)))
+        (step c3 (cl b) :rule drup) (step c4 (cl) :rule drup)";
+    assert_eq!(check(p).unwrap(), (Outcome::Refutation, 0));
+
+    // `a` does not follow from the clauses
+    let p = "(assume c0 (cl a b)) (step c1 (cl a) :rule drup)";
+    assert_eq!(failing_step(p), "c1");
+
+    // deleted clauses cannot be used
+    let p = "(assume c0 (cl a)) (assume c1 (cl (not a))) (delete (cl a)) (step c2 (cl) :rule drup)";
+    assert_eq!(failing_step(p), "c2");
+}
+
+#[test]
+fn test_tseitin() {
+    let p = "(step c0 (cl (not (and a b)) a) :rule tseitin)
+        (step c1 (cl (not (or a b)) a b) :rule tseitin)
+        (step c2 (cl (=> a b) a) :rule tseitin)
+        (assume c3 (cl (and a b))) (assume c4 (cl (not a)))
+        (step c5 (cl) :rule drup)";
+    assert_eq!(check(p).unwrap(), (Outcome::Refutation, 0));
+
+    let p = "(step c0 (cl (not (or a b)) a) :rule tseitin)";
+    assert_eq!(failing_step(p), "c0");
+}
+
+#[test]
+fn test_th_lemma() {
+    // congruence, checked by the naive congruence closure
+    let p = "(step c0 (cl (not (= a b)) (= (f a) (f b))) :rule th-lemma)";
+    assert_eq!(failing_step(p), "<end>"); // not a refutation, but the step is fine
+
+    // ... or with an explanation
+    let p = "(step c0 (cl (not (= a b)) (= (f a) (f b))) :rule th-lemma
+        :explanation ((lit a b) (cong (f a) (f b))
+        (eq (= (f a) (f b)) true ((f a) (f b))) (lit (= (f a) (f b)) false)))
+        (assume c1 (cl (= a b))) (assume c2 (cl (not (= (f a) (f b)))))
+        (step c3 (cl) :rule drup)";
+    assert_eq!(check(p).unwrap(), (Outcome::Refutation, 0));
+
+    // not a tautology
+    let p = "(step c0 (cl (not (= a b)) (= (f a) (f c))) :rule th-lemma)";
+    assert_eq!(failing_step(p), "c0");
+
+    // the explanation uses an equation that is not justified
+    let p = "(step c0 (cl (not (= a b)) (= (f a) (f c))) :rule th-lemma
+        :explanation ((lit a c) (cong (f a) (f c))
+        (eq (= (f a) (f c)) true ((f a) (f c))) (lit (= (f a) (f c)) false)))";
+    assert_eq!(failing_step(p), "c0");
+}
+
+#[test]
+fn test_axiom() {
+    // axioms are trusted, and counted
+    let p = "(step c0 (cl (= (hd (cons x y)) x)) :rule th-lemma
+        :explanation ((axiom (hd (cons x y)) x) (lit (= (hd (cons x y)) x) false)
+        (eq (= (hd (cons x y)) x) true ((hd (cons x y)) x))))
+        (assume c1 (cl (not (= (hd (cons x y)) x))))
+        (step c2 (cl) :rule drup)";
+    assert_eq!(check(p).unwrap(), (Outcome::Refutation, 1));
+}
+
+#[test]
+fn test_trust() {
+    // trusted lemmas are counted
+    let p = "(step c0 (cl (<= x 1) (<= 2 x)) :rule trust)
+        (assume c1 (cl (not (<= x 1)))) (assume c2 (cl (not (<= 2 x))))
+        (step c3 (cl) :rule drup)";
+    assert_eq!(check(p).unwrap(), (Outcome::Refutation, 1));
+}
+
+#[test]
+fn test_incremental() {
+    // the refutation need not be the last step
+    let p = "(assume c0 (cl (not @b1) a)) (assume c1 (cl (not a)))
+        (step c2 (cl (not @b1)) :rule drup) (assume c3 (cl b))";
+    assert_eq!(check(p).unwrap(), (Outcome::RefutationUnderAssumptions, 0));
+
+    // only `drup` steps are refutations
+    let p = "(assume c0 (cl (not @b1)))";
+    assert_eq!(failing_step(p), "<end>");
+}
+
+#[test]
+fn test_assumptions() {
+    // `@b1` is a selector, the problem is unsat under `@b1`
+    let p = "(assume c0 (cl (not @b1) a)) (assume c1 (cl (not a)))
+        (step c2 (cl (not @b1)) :rule drup)";
+    assert_eq!(check(p).unwrap(), (Outcome::RefutationUnderAssumptions, 0));
+
+    // only `@bN` literals are pure boolean literals
+    for lit in ["@bx", "@b", "@b1x", "b1"].iter() {
+        let p = format!("(assume c0 (cl (not {0}) a)) (assume c1 (cl (not a)))
+            (step c2 (cl (not {0})) :rule drup)", lit);
+        assert_eq!(failing_step(&p), "<end>", "{}", lit);
+    }
+}
@@ -395,6 +395,9 @@ pub mod hash_map {
     impl<AST:Eq+Hash, V> HashMap<AST,V> {
         /// New hashmap
         pub fn new() -> Self { HashMap(FxHashMap::default()) }
+
+        /// Iterate over the bindings.
+        pub fn iter(&self) -> impl Iterator<Item=(&AST,&V)> { self.0.iter() }
     }
 
     impl<AST:Hash+Eq,V> gc::HasInternalMemory for HashMap<AST,V> {
@@ -8,6 +8,7 @@ pub mod backtrack;
 pub mod shared;
 pub mod ast_u32;
 pub mod chrono;
+pub mod stats;
 
 pub use crate::{
   backtrack::{Stack as BacktrackStack,Backtrackable},
@@ -15,5 +16,6 @@ pub use crate::{
   gc::GC,
   shared::{Shared,SharedRef,SharedRefMut},
   chrono::Chrono,
+  stats::Stats,
 };
 
@@ -0,0 +1,196 @@
+
+//! Statistics.
+//!
+//! A `Stats` registry maps names to counters and timers. Components
+//! register their statistics once, using `counter` or `timer`, and then
+//! update the returned handle, which is as cheap as updating a field.
+//! Registering the same name twice returns the same handle, so that
+//! several instances of a component share their statistics.
+//!
+//! Each thread has a default registry, used by the free functions of
+//! this module; it is the one frontends should print.
+
+use {
+    std::{
+        fmt, rc::Rc, cell::{Cell, RefCell},
+        time::Instant,
+    },
+};
+
+/// A counter, registered in some `Stats`.
+#[derive(Clone,Debug)]
+pub struct Counter(Rc<Cell<u64>>);
+
+/// A timer, registered in some `Stats`. It accumulates time, in seconds.
+#[derive(Clone,Debug)]
+pub struct Timer(Rc<Cell<f64>>);
+
+/// Value of a statistic.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Value {
+    Int(u64),
+    /// Time, in seconds.
+    Time(f64),
+}
+
+#[derive(Clone,Debug)]
+enum Entry {
+    Counter(Counter),
+    Timer(Timer),
+}
+
+/// A registry of statistics.
+///
+/// Clones share the same statistics.
+#[derive(Clone,Debug,Default)]
+pub struct Stats(Rc<RefCell<Vec<(&'static str, Entry)>>>);
+
+thread_local! {
+    static STATS: Stats = Stats::new();
+}
+
+/// The default registry of the current thread.
+pub fn global() -> Stats { STATS.with(|st| st.clone()) }
+
+/// Counter `name` in the default registry.
+pub fn counter(name: &'static str) -> Counter { STATS.with(|st| st.counter(name)) }
+
+/// Timer `name` in the default registry.
+pub fn timer(name: &'static str) -> Timer { STATS.with(|st| st.timer(name)) }
+
+impl Counter {
+    #[inline(always)]
+    pub fn incr(&self) { self.add(1) }
+
+    #[inline(always)]
+    pub fn add(&self, n: u64) { self.0.set(self.0.get() + n) }
+
+    #[inline(always)]
+    pub fn set(&self, n: u64) { self.0.set(n) }
+
+    #[inline(always)]
+    pub fn get(&self) -> u64 { self.0.get() }
+}
+
+impl Timer {
+    /// Add `secs` seconds.
+    pub fn add(&self, secs: f64) { self.0.set(self.0.get() + secs) }
+
+    pub fn set(&self, secs: f64) { self.0.set(secs) }
+
+    /// Total time, in seconds.
+    pub fn get(&self) -> f64 { self.0.get() }
+
+    /// Call `f`, adding the time it takes to this timer.
+    pub fn time<F, R>(&self, f: F) -> R where F: FnOnce() -> R {
+        let start = Instant::now();
+        let res = f();
+        let d = start.elapsed();
+        self.add(d.as_secs() as f64 + d.subsec_nanos() as f64 * 1e-9);
+        res
+    }
+}
+
+impl Stats {
+    /// New empty registry.
+    pub fn new() -> Self { Stats::default() }
+
+    /// Register counter `name`, or return it if it exists already.
+    ///
+    /// Panics if `name` is already registered as a timer.
+    pub fn counter(&self, name: &'static str) -> Counter {
+        let mut entries = self.0.borrow_mut();
+        match entries.iter().find(|(n,_)| *n == name) {
+            Some((_, Entry::Counter(c))) => return c.clone(),
+            Some((_, Entry::Timer(_))) => panic!("statistic {} is a timer", name),
+            None => (),
+        }
+        let c = Counter(Rc::new(Cell::new(0)));
+        entries.push((name, Entry::Counter(c.clone())));
+        c
+    }
+
+    /// Register timer `name`, or return it if it exists already.
+    ///
+    /// Panics if `name` is already registered as a counter.
+    pub fn timer(&self, name: &'static str) -> Timer {
+        let mut entries = self.0.borrow_mut();
+        match entries.iter().find(|(n,_)| *n == name) {
+            Some((_, Entry::Timer(t))) => return t.clone(),
+            Some((_, Entry::Counter(_))) => panic!("statistic {} is a counter", name),
+            None => (),
+        }
+        let t = Timer(Rc::new(Cell::new(0.)));
+        entries.push((name, Entry::Timer(t.clone())));
+        t
+    }
+
+    /// Reset all statistics to 0. Handles remain valid.
+    pub fn reset(&self) {
+        for (_, e) in self.0.borrow().iter() {
+            match e {
+                Entry::Counter(c) => c.set(0),
+                Entry::Timer(t) => t.set(0.),
+            }
+        }
+    }
+
+    /// Current values, sorted by name.
+    pub fn values(&self) -> Vec<(&'static str, Value)> {
+        let mut v: Vec<_> = self.0.borrow().iter().map(|(n, e)| {
+            let x = match e {
+                Entry::Counter(c) => Value::Int(c.get()),
+                Entry::Timer(t) => Value::Time(t.get()),
+            };
+            (*n, x)
+        }).collect();
+        v.sort_by_key(|(n,_)| *n);
+        v
+    }
+
+    /// Display as a SMT-LIB attribute list `(:name value …)`,
+    /// as expected by `(get-info :all-statistics)`.
+    pub fn pp_smt2(&self) -> impl fmt::Display {
+        PpSmt2(self.values())
+    }
+
+    /// Display as a JSON object `{"name": value, …}`.
+    pub fn pp_json(&self) -> impl fmt::Display {
+        PpJson(self.values())
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(out, "{}", n),
+            Value::Time(t) => write!(out, "{:.3}", t),
+        }
+    }
+}
+
+struct PpSmt2(Vec<(&'static str, Value)>);
+struct PpJson(Vec<(&'static str, Value)>);
+
+impl fmt::Display for PpSmt2 {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        out.write_str("(")?;
+        for (i, (n, x)) in self.0.iter().enumerate() {
+            if i > 0 { out.write_str("\n ")? }
+            write!(out, ":{} {}", n, x)?;
+        }
+        out.write_str(")")
+    }
+}
+
+impl fmt::Display for PpJson {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        out.write_str("{")?;
+        for (i, (n, x)) in self.0.iter().enumerate() {
+            if i > 0 { out.write_str(", ")? }
+            // names are plain identifiers, no need to escape them
+            write!(out, "{:?}: {}", n, x)?;
+        }
+        out.write_str("}")
+    }
+}
@@ -168,3 +168,24 @@ mod backtrack {
         }
     }
 }
+
+mod stats {
+    use batsmt_core::stats::{Stats, Value};
+
+    #[test]
+    fn test_registry() {
+        let st = Stats::new();
+        let c1 = st.counter("b.count");
+        let c2 = st.counter("b.count"); // same counter
+        let t = st.timer("a.time");
+        c1.incr();
+        c2.add(2);
+        t.set(1.5);
+        assert_eq!(c1.get(), 3);
+        assert_eq!(st.values(), vec!(("a.time", Value::Time(1.5)), ("b.count", Value::Int(3))));
+        assert_eq!(st.pp_smt2().to_string(), "(:a.time 1.500\n :b.count 3)");
+        assert_eq!(st.pp_json().to_string(), r#"{"a.time": 1.500, "b.count": 3}"#);
+        st.reset();
+        assert_eq!(c2.get(), 0);
+    }
+}
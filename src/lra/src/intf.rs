@@ -0,0 +1,117 @@
+
+//! Interface between the arithmetic theory and terms.
+
+use {
+    std::fmt,
+    batsmt_theory as theory,
+    batsmt_pretty as pp,
+    num_bigint::BigInt,
+    num_traits::{Zero, Signed, Num, },
+};
+
+/// Exact rational numbers.
+pub type Q = num_rational::BigRational;
+
+/// Arithmetic comparison between two terms.
+#[derive(Copy,Clone,Debug,Eq,PartialEq,Hash)]
+pub enum Pred {
+    Leq,
+    Lt,
+    Geq,
+    Gt,
+    Eq,
+}
+
+/// View of a term as an arithmetic term.
+pub enum LRAView<'a, AST> {
+    /// A rational constant.
+    Const(&'a Q),
+    /// Comparison `a pred b`, between two real terms.
+    Pred(Pred, &'a AST, &'a AST),
+    /// Sum.
+    Add(&'a [AST]),
+    /// Difference (left associative), or negation if there's only one argument.
+    Sub(&'a [AST]),
+    /// Product. At most one argument should be non-constant.
+    Mul(&'a [AST]),
+    /// Division (left associative). Only the first argument can be non-constant.
+    Div(&'a [AST]),
+    /// Any other term, considered as a variable.
+    Other(&'a AST),
+}
+
+/// The context for the arithmetic theory.
+pub trait Ctx : theory::Ctx {
+    /// View a term as an arithmetic expression.
+    fn view_as_lra<'a>(&'a self, t: &'a Self::AST) -> LRAView<'a, Self::AST>;
+
+    /// Does `t` have sort `Real`?
+    fn is_real(&self, t: &Self::AST) -> bool;
+
+    /// Build the atom `a pred b`.
+    fn mk_pred(&mut self, p: Pred, a: Self::AST, b: Self::AST) -> Self::AST;
+
+    /// Build a numeral with the given value.
+    ///
+    /// It must be seen as `LRAView::Const(q)` by `view_as_lra`.
+    fn mk_numeral(&mut self, q: &Q) -> Self::AST;
+}
+
+/// Parse a numeral, such as `42`, `3.14`, or `1/3`.
+pub fn parse_numeral(s: &str) -> Option<Q> {
+    if let Some(i) = s.find('.') {
+        let (n, d) = (&s[..i], &s[i+1..]);
+        if n.len() == 0 || d.len() == 0 { return None }
+        let n = BigInt::from_str_radix(&format!("{}{}", n, d), 10).ok()?;
+        let d = num_traits::pow(BigInt::from(10), d.len());
+        Some(Q::new(n, d))
+    } else if let Some(i) = s.find('/') {
+        let n = BigInt::from_str_radix(&s[..i], 10).ok()?;
+        let d = BigInt::from_str_radix(&s[i+1..], 10).ok()?;
+        if d.is_zero() { None } else { Some(Q::new(n, d)) }
+    } else {
+        BigInt::from_str_radix(s, 10).ok().map(Q::from_integer)
+    }
+}
+
+/// Print a rational in SMT-LIB syntax, e.g. `(- (/ 1 3))`.
+pub fn pp_q(q: &Q) -> impl fmt::Display + '_ { PPQ(q) }
+
+struct PPQ<'a>(&'a Q);
+
+impl<'a> fmt::Display for PPQ<'a> {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        let q = self.0;
+        let (n, d) = (q.numer().abs(), q.denom());
+        if q.is_negative() { write!(out, "(- ")?; }
+        if q.is_integer() {
+            write!(out, "{}", n)?;
+        } else {
+            write!(out, "(/ {} {})", n, d)?;
+        }
+        if q.is_negative() { write!(out, ")")?; }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Pred {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Pred::Leq => "<=",
+            Pred::Lt => "<",
+            Pred::Geq => ">=",
+            Pred::Gt => ">",
+            Pred::Eq => "=",
+        };
+        write!(out, "{}", s)
+    }
+}
+
+impl pp::Pretty for Pred {
+    fn pp_into(&self, ctx: &mut pp::Ctx) { ctx.display(self); }
+}
+
+/// Print a term.
+pub(crate) fn pp_t<'a, C:Ctx>(m: &'a C, t: &C::AST) -> impl fmt::Display + 'a {
+    theory::pp_ast(m, t)
+}
@@ -0,0 +1,23 @@
+
+//! Linear real arithmetic (QF_LRA).
+//!
+//! The theory is decided by a general simplex over exact rationals,
+//! with propagation of the atoms implied by the current bounds.
+//! The expected usage is `LRATheory::new(&mut ctx)`, possibly combined
+//! with the congruence closure using `batsmt_theory::Combine`.
+
+#[macro_use] extern crate log;
+
+pub mod intf;
+pub mod simplex;
+pub mod lra_theory;
+
+pub use {
+    crate::{
+        intf::{Ctx, LRAView, Pred, Q, parse_numeral, pp_q, },
+        simplex::Simplex,
+        lra_theory::LRATheory,
+    },
+};
+
+pub(crate) use crate::intf::pp_t;
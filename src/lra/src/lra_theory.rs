@@ -0,0 +1,493 @@
+
+//! Theory of linear real arithmetic, built on the simplex.
+
+use {
+    batsmt_core::{backtrack, AstView, ast_u32::AST, },
+    batsmt_theory::{self as theory, TheoryLit, BoolLit, },
+    fxhash::{FxHashMap, FxHashSet, },
+    num_traits::{Zero, One, Signed, },
+    crate::{
+        Ctx, LRAView, Pred, Q, pp_t,
+        simplex::{Simplex, Var, DQ, },
+    },
+};
+
+/// A linear expression `Σ aᵢ·tᵢ + c`, with `tᵢ` sorted.
+#[derive(Clone,Debug,PartialEq)]
+pub struct LinExpr<AST> {
+    pub terms: Vec<(AST, Q)>,
+    pub cst: Q,
+}
+
+/// An arithmetic atom `x pred c`, where `x` is a simplex variable.
+#[derive(Clone,Debug)]
+struct Atom<AST, B> {
+    t: AST, // the atom, `(pred a b)`
+    args: (AST, AST),
+    lit: B, // literal for `t`
+    var: Option<Var>, // `None` if the atom is constant
+    pred: Pred,
+    c: Q,
+}
+
+/// Theory of linear real arithmetic.
+///
+/// Atoms `(pred a b)` are normalized into `x pred c` where `x` is
+/// a simplex variable for the linear expression `a - b`, and `c` a constant.
+/// Non-arithmetic subterms are treated as variables.
+///
+/// Arithmetic terms that occur as arguments of non-arithmetic applications
+/// (e.g. `(+ x 1)` in `(f (+ x 1))`) are purified: they are not variables,
+/// but they are interface terms, with a value in the model, so that
+/// theory combination can compare them with other shared terms.
+/// Real terms that only occur under non-arithmetic applications
+/// (e.g. `(f a)` in `(p (f a))`) are variables, for the same reason.
+pub struct LRATheory<C:Ctx> {
+    simplex: Simplex<C::B>,
+    vars: FxHashMap<C::AST, Var>, // non-arithmetic terms
+    terms: Vec<(C::AST, Var)>, // inverse of `vars`, for models
+    shared: Vec<(C::AST, Vec<(Var, Q)>, Q)>, // purified terms, with their linear form
+    purified: FxHashSet<C::AST>, // terms already traversed by `purify`
+    slacks: FxHashMap<Vec<(Var, Q)>, Var>, // normalized linear expressions
+    atoms: Vec<Atom<C::AST, C::B>>,
+    atom_of: FxHashMap<C::AST, usize>, // term -> index in `atoms`
+    atoms_of_var: FxHashMap<Var, Vec<usize>>, // for bound propagation
+    assigned: backtrack::BVec<bool>, // is `atoms[i]` assigned?
+    diseqs: backtrack::Stack<usize>, // `=` atoms assigned to false
+    diseq_lemmas: FxHashMap<usize, ()>, // disequalities already split
+    props: FxHashMap<C::B, C::B>, // propagated literal -> reason
+    expl: Vec<C::B>, // temporary, for explanations
+    confl: Vec<C::B>, // temporary, for conflicts
+    propagate: bool,
+}
+
+impl LinExpr<AST> {
+    /// Linear form of `a - b`, where non-linear subterms are
+    /// considered as variables.
+    pub fn diff<C:Ctx>(m: &C, a: &AST, b: &AST) -> Self {
+        let mut le = LinExpr{terms: vec!(), cst: Q::zero()};
+        le.add_term(m, a, &Q::one());
+        le.add_term(m, b, &-Q::one());
+        le.terms.sort_by_key(|(t,_)| *t);
+        let mut terms: Vec<(AST, Q)> = vec!();
+        for (t, q) in le.terms.into_iter() {
+            match terms.last_mut() {
+                Some((t2, q2)) if *t2 == t => *q2 += q,
+                _ => terms.push((t, q)),
+            }
+        }
+        terms.retain(|(_,q)| ! q.is_zero());
+        le.terms = terms;
+        le
+    }
+
+    // linearize `t`, adding `k·t` to `self`
+    fn add_term<C:Ctx>(&mut self, m: &C, t: &AST, k: &Q) {
+        match m.view_as_lra(t) {
+            LRAView::Const(q) => self.cst += k * q,
+            LRAView::Add(args) => {
+                for u in args.iter() { self.add_term(m, u, k) }
+            },
+            LRAView::Sub(args) if args.len() == 1 => {
+                self.add_term(m, &args[0], &-k)
+            },
+            LRAView::Sub(args) => {
+                self.add_term(m, &args[0], k);
+                for u in args[1..].iter() { self.add_term(m, u, &-k) }
+            },
+            LRAView::Mul(args) => {
+                // product of constants, times (at most) one term
+                let mut q = Q::one();
+                let mut non_cst = None;
+                for u in args.iter() {
+                    match m.view_as_lra(u) {
+                        LRAView::Const(q2) => q *= q2,
+                        _ if non_cst.is_none() => non_cst = Some(u),
+                        _ => {
+                            // non linear, consider `t` as a variable
+                            self.terms.push((*t, k.clone()));
+                            return
+                        },
+                    }
+                }
+                match non_cst {
+                    None => self.cst += k * q,
+                    Some(u) => self.add_term(m, u, &(k * q)),
+                }
+            },
+            LRAView::Div(args) => {
+                let mut q = Q::one();
+                for u in args[1..].iter() {
+                    match m.view_as_lra(u) {
+                        LRAView::Const(q2) if ! q2.is_zero() => q /= q2,
+                        _ => {
+                            // non linear, consider `t` as a variable
+                            self.terms.push((*t, k.clone()));
+                            return
+                        },
+                    }
+                }
+                self.add_term(m, &args[0], &(k * q))
+            },
+            LRAView::Pred(..) | LRAView::Other(..) => self.terms.push((*t, k.clone())),
+        }
+    }
+}
+
+impl<C:Ctx> LRATheory<C> {
+    /// New arithmetic theory.
+    pub fn new(_m: &mut C) -> Self {
+        LRATheory {
+            simplex: Simplex::new(),
+            vars: FxHashMap::default(),
+            terms: vec!(),
+            shared: vec!(),
+            purified: FxHashSet::default(),
+            slacks: FxHashMap::default(),
+            atoms: vec!(),
+            atom_of: FxHashMap::default(),
+            atoms_of_var: FxHashMap::default(),
+            assigned: backtrack::BVec::new(),
+            diseqs: backtrack::Stack::new(),
+            diseq_lemmas: FxHashMap::default(),
+            props: FxHashMap::default(),
+            expl: vec!(),
+            confl: vec!(),
+            propagate: true,
+        }
+    }
+
+    /// Number of pivots performed by the simplex.
+    pub fn n_pivots(&self) -> u64 { self.simplex.n_pivots() }
+
+    // simplex variable for the non-arithmetic term `t`
+    fn var_of_term(&mut self, m: &C, t: C::AST) -> Var {
+        if let Some(x) = self.vars.get(&t) { return *x }
+        let x = self.simplex.new_var();
+        self.vars.insert(t, x);
+        self.terms.push((t, x));
+        self.purify(m, t);
+        x
+    }
+
+    // register arithmetic and real arguments of the non-arithmetic
+    // applications in `t`
+    fn purify(&mut self, m: &C, t: C::AST) {
+        let mut st = vec!(t);
+        while let Some(t) = st.pop() {
+            if ! self.purified.insert(t) { continue }
+            let args = match (m.view_as_lra(&t), m.view(&t)) {
+                (LRAView::Other(_), AstView::App{args, ..}) => args,
+                _ => continue,
+            };
+            for u in args.iter() {
+                match m.view_as_lra(u) {
+                    LRAView::Other(_) if m.is_real(u) => { self.var_of_term(m, *u); },
+                    LRAView::Other(_) => st.push(*u),
+                    LRAView::Pred(..) => (),
+                    _ => self.add_shared(m, *u),
+                }
+            }
+        }
+    }
+
+    // arithmetic term `t` is shared with other theories
+    fn add_shared(&mut self, m: &C, t: C::AST) {
+        if ! self.purified.insert(t) { return }
+        let mut le = LinExpr{terms: vec!(), cst: Q::zero()};
+        le.add_term(m, &t, &Q::one());
+        let vars: Vec<(Var, Q)> =
+            le.terms.iter().map(|(u,q)| (self.var_of_term(m, *u), q.clone())).collect();
+        trace!("lra.add-shared {}", pp_t(m, &t));
+        self.shared.push((t, vars, le.cst));
+    }
+
+    /// Register the atom `t`, whose positive literal is `lit`.
+    ///
+    /// Returns `false` if `t` is not an arithmetic atom.
+    fn add_atom(&mut self, m: &C, t: C::AST, lit: C::B) -> bool {
+        if self.atom_of.contains_key(&t) { return true }
+        let (pred, a, b) = match m.view_as_lra(&t) {
+            LRAView::Pred(p, a, b) => (p, *a, *b),
+            _ => return false,
+        };
+
+        // `a - b pred 0`
+        let LinExpr{terms, cst} = LinExpr::diff(m, &a, &b);
+
+        // normalize into `x pred c`, with a leading coefficient of 1
+        let mut c = -cst;
+        let mut pred = pred;
+        let var = if terms.len() == 0 {
+            None
+        } else {
+            let lead = terms[0].1.clone();
+            c = c / &lead;
+            if lead.is_negative() {
+                pred = match pred {
+                    Pred::Leq => Pred::Geq,
+                    Pred::Lt => Pred::Gt,
+                    Pred::Geq => Pred::Leq,
+                    Pred::Gt => Pred::Lt,
+                    Pred::Eq => Pred::Eq,
+                };
+            }
+            let le: Vec<(Var, Q)> =
+                terms.iter().map(|(t,q)| (self.var_of_term(m, *t), q / &lead)).collect();
+            if le.len() == 1 {
+                Some(le[0].0)
+            } else if let Some(x) = self.slacks.get(&le) {
+                Some(*x)
+            } else {
+                let x = self.simplex.new_slack(&le);
+                self.slacks.insert(le, x);
+                Some(x)
+            }
+        };
+
+        trace!("lra.add-atom {} as x{:?} {} {}", pp_t(m, &t), var, pred, c);
+        let i = self.atoms.len();
+        self.atoms.push(Atom{t, args: (a, b), lit, var, pred, c});
+        self.atom_of.insert(t, i);
+        self.assigned.push_nonbacktrack(false);
+        if let Some(x) = var {
+            self.atoms_of_var.entry(x).or_insert(vec!()).push(i);
+        }
+        true
+    }
+
+    // assert atom `i` with the given sign, justified by `lit`.
+    fn assert_atom<A>(&mut self, acts: &mut A, i: usize, sign: bool, lit: C::B)
+        where A: theory::Actions<C>
+    {
+        self.assigned.set(i, true);
+        let atom = &self.atoms[i];
+        let x = match atom.var {
+            Some(x) => x,
+            None => {
+                // constant atom
+                let c = &atom.c;
+                let holds = match atom.pred {
+                    Pred::Leq => Q::zero() <= *c,
+                    Pred::Lt => Q::zero() < *c,
+                    Pred::Geq => Q::zero() >= *c,
+                    Pred::Gt => Q::zero() > *c,
+                    Pred::Eq => c.is_zero(),
+                };
+                if holds != sign {
+                    acts.raise_conflict(&[!lit], false);
+                }
+                return
+            }
+        };
+        let c = atom.c.clone();
+        let res = match (atom.pred, sign) {
+            (Pred::Leq, true) | (Pred::Gt, false) => self.simplex.assert_upper(x, DQ::new(c), lit),
+            (Pred::Lt, true) | (Pred::Geq, false) => {
+                self.simplex.assert_upper(x, DQ::with_delta(c, -1), lit)
+            },
+            (Pred::Geq, true) | (Pred::Lt, false) => self.simplex.assert_lower(x, DQ::new(c), lit),
+            (Pred::Gt, true) | (Pred::Leq, false) => {
+                self.simplex.assert_lower(x, DQ::with_delta(c, 1), lit)
+            },
+            (Pred::Eq, true) => {
+                self.simplex.assert_lower(x, DQ::new(c.clone()), lit)
+                    .and_then(|()| self.simplex.assert_upper(x, DQ::new(c), lit))
+            },
+            (Pred::Eq, false) => {
+                self.diseqs.push(i);
+                Ok(())
+            },
+        };
+        match res {
+            Ok(()) => self.propagate_bounds(acts, x),
+            Err(c) => self.raise_conflict(acts, c),
+        }
+    }
+
+    fn raise_conflict<A>(&mut self, acts: &mut A, mut c: Vec<C::B>)
+        where A: theory::Actions<C>
+    {
+        c.sort();
+        c.dedup();
+        self.confl.clear();
+        self.confl.extend(c.into_iter().map(|b| !b));
+        acts.raise_conflict(&self.confl, false);
+    }
+
+    // propagate atoms on `x` that are implied by its current bounds
+    fn propagate_bounds<A>(&mut self, acts: &mut A, x: Var)
+        where A: theory::Actions<C>
+    {
+        if ! self.propagate || acts.has_conflict() { return }
+        let atoms = match self.atoms_of_var.get(&x) {
+            Some(v) => v,
+            None => return,
+        };
+        let lower = self.simplex.lower(x);
+        let upper = self.simplex.upper(x);
+        for &i in atoms.iter() {
+            if *self.assigned.get(i) { continue }
+            let atom = &self.atoms[i];
+            let c = &atom.c;
+            // does the upper bound imply `x ≤ c + kδ`?
+            let below = |k: i32| upper.map_or(false, |u| u.value <= DQ::with_delta(c.clone(), k));
+            // does the lower bound imply `x ≥ c + kδ`?
+            let above = |k: i32| lower.map_or(false, |l| l.value >= DQ::with_delta(c.clone(), k));
+            let (sign, reason) = match atom.pred {
+                Pred::Leq if below(0) => (true, upper),
+                Pred::Leq if above(1) => (false, lower),
+                Pred::Lt if below(-1) => (true, upper),
+                Pred::Lt if above(0) => (false, lower),
+                Pred::Geq if above(0) => (true, lower),
+                Pred::Geq if below(-1) => (false, upper),
+                Pred::Gt if above(1) => (true, lower),
+                Pred::Gt if below(0) => (false, upper),
+                Pred::Eq if above(1) => (false, lower),
+                Pred::Eq if below(-1) => (false, upper),
+                _ => continue,
+            };
+            let reason = reason.unwrap().reason;
+            let p = atom.lit.apply_sign(sign);
+            trace!("lra.propagate {:?} (reason {:?})", p, reason);
+            self.props.insert(p, reason);
+            self.assigned.set(i, true);
+            if ! acts.propagate(p) { return }
+        }
+    }
+
+    // handle the trail
+    fn add_trail<A>(&mut self, m: &mut C, acts: &mut A, trail: &theory::Trail<C>)
+        where A: theory::Actions<C>
+    {
+        for (t, sign, lit) in trail.iter() {
+            let i = match self.atom_of.get(&t) {
+                Some(i) => *i,
+                None => {
+                    // atoms might be introduced during search
+                    if ! self.add_atom(m, t, lit.apply_sign(sign)) {
+                        self.purify(m, t);
+                        continue
+                    }
+                    self.atoms.len() - 1
+                },
+            };
+            self.assert_atom(acts, i, sign, lit);
+            if acts.has_conflict() { return }
+        }
+    }
+
+    fn check_simplex<A>(&mut self, acts: &mut A)
+        where A: theory::Actions<C>
+    {
+        if acts.has_conflict() { return }
+        if let Err(c) = self.simplex.check() {
+            self.raise_conflict(acts, c)
+        }
+    }
+
+    // split disequalities `a ≠ b` that are violated in the current model,
+    // using the lemma `a = b ∨ a < b ∨ a > b`
+    fn split_diseqs<A>(&mut self, m: &mut C, acts: &mut A)
+        where A: theory::Actions<C>
+    {
+        let todo: Vec<usize> =
+            self.diseqs.iter().cloned()
+            .filter(|i| ! self.diseq_lemmas.contains_key(i))
+            .filter(|&i| {
+                let atom = &self.atoms[i];
+                atom.var.map_or(false, |x| *self.simplex.value(x) == DQ::new(atom.c.clone()))
+            })
+            .collect();
+        for i in todo {
+            let (a, b) = self.atoms[i].args;
+            let eq_lit = self.atoms[i].lit;
+            debug!("lra.split-diseq {}", pp_t(m, &self.atoms[i].t));
+            let t_lt = m.mk_pred(Pred::Lt, a, b);
+            let t_gt = m.mk_pred(Pred::Gt, a, b);
+            let lit_lt = acts.map_lit(m, TheoryLit::new_t(t_lt, true));
+            let lit_gt = acts.map_lit(m, TheoryLit::new_t(t_gt, true));
+            self.add_atom(m, t_lt, lit_lt);
+            self.add_atom(m, t_gt, lit_gt);
+            self.diseq_lemmas.insert(i, ());
+            acts.add_lemma(&[eq_lit, lit_lt, lit_gt]);
+        }
+    }
+}
+
+impl<C:Ctx> backtrack::Backtrackable<C> for LRATheory<C> {
+    fn push_level(&mut self, _c: &mut C) {
+        self.simplex.push_level();
+        self.assigned.push_level();
+        self.diseqs.push_level();
+    }
+
+    fn pop_levels(&mut self, _c: &mut C, n: usize) {
+        self.simplex.pop_levels(n);
+        self.assigned.pop_levels(n);
+        self.diseqs.pop_levels(n, |_| ());
+    }
+}
+
+impl<C:Ctx> theory::Theory<C> for LRATheory<C> {
+    fn final_check<A>(&mut self, m: &mut C, acts: &mut A, trail: &theory::Trail<C>)
+        where A: theory::Actions<C>
+    {
+        debug!("lra.final-check");
+        self.add_trail(m, acts, trail);
+        self.check_simplex(acts);
+        if ! acts.has_conflict() {
+            self.split_diseqs(m, acts);
+        }
+    }
+
+    fn partial_check<A>(&mut self, m: &mut C, acts: &mut A, trail: &theory::Trail<C>)
+        where A: theory::Actions<C>
+    {
+        debug!("lra.partial-check");
+        self.add_trail(m, acts, trail);
+        self.check_simplex(acts);
+    }
+
+    #[inline(always)]
+    fn has_partial_check() -> bool { true }
+
+    fn add_literal(&mut self, m: &mut C, t: C::AST, lit: C::B) {
+        if ! self.add_atom(m, t, lit) {
+            self.purify(m, t);
+        }
+    }
+
+    fn explain_propagation(&mut self, _m: &mut C, _t: C::AST, _sign: bool, p: C::B) -> &[C::B] {
+        self.expl.clear();
+        match self.props.get(&p) {
+            Some(r) => self.expl.push(*r),
+            None => panic!("lra: cannot explain {:?}", p),
+        }
+        &self.expl
+    }
+
+    fn enable_propagation(&mut self, b: bool) { self.propagate = b }
+
+    fn build_model(&mut self, m: &mut C, model: &mut theory::Model<C>) {
+        let diseqs: Vec<_> =
+            self.diseqs.iter()
+            .filter_map(|&i| self.atoms[i].var.map(|x| (x, self.atoms[i].c.clone())))
+            .collect();
+        let values = self.simplex.model(&diseqs);
+        for (t, x) in self.terms.iter() {
+            let v = m.mk_numeral(&values[*x as usize]);
+            model.add_repr(*t, v);
+        }
+        for (t, le, cst) in self.shared.iter() {
+            let q = le.iter().fold(cst.clone(), |q, (x, k)| q + k * &values[*x as usize]);
+            let v = m.mk_numeral(&q);
+            model.add_repr(*t, v);
+        }
+    }
+
+    fn iter_interface_terms<F>(&mut self, _m: &C, mut f: F) where F: FnMut(&C::AST) {
+        for (t, _) in self.terms.iter() { f(t) }
+        for (t, _, _) in self.shared.iter() { f(t) }
+    }
+}
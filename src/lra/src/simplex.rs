@@ -0,0 +1,394 @@
+
+//! General simplex, for checking the consistency of bounds on linear combinations.
+//!
+//! This follows "A Fast Linear-Arithmetic Solver for DPLL(T)"
+//! (Dutertre & de Moura, 2006). Every variable is either basic or non-basic;
+//! each basic variable is defined by a row of the tableau, as a linear
+//! combination of non-basic variables.
+//!
+//! Only bounds are backtrackable. The tableau and the current assignment
+//! are kept upon backtracking, since they remain valid (the assignment
+//! might violate bounds, but that is repaired by `check`).
+//!
+//! Strict bounds are handled with δ-rationals, i.e. pairs `c + kδ` where
+//! `δ` is an infinitesimal.
+
+use {
+    std::{fmt, ops::{Add, Sub, Mul}, },
+    batsmt_core::backtrack::BVec,
+    num_traits::{Zero, One, Signed, },
+    crate::intf::Q,
+};
+
+/// A variable of the simplex.
+pub type Var = u32;
+
+/// A δ-rational `c + kδ`, where `δ` is an infinitesimal.
+#[derive(Clone,Debug,Eq,PartialEq,Ord,PartialOrd,Hash)]
+pub struct DQ {
+    pub c: Q,
+    pub k: Q,
+}
+
+/// A row of the tableau, as a sorted list of `(variable, coefficient)`.
+type Row = Vec<(Var, Q)>;
+
+/// A bound on a variable, with the reason it holds.
+#[derive(Clone,Debug)]
+pub struct Bound<B> {
+    pub value: DQ,
+    pub reason: B,
+}
+
+/// The simplex.
+///
+/// `B` is the type of reasons for bounds, typically boolean literals.
+pub struct Simplex<B:Copy> {
+    rows: Vec<Option<Row>>, // `rows[x]` is `Some(row)` iff `x` is basic
+    value: Vec<DQ>, // current assignment
+    lower: BVec<Option<Bound<B>>>,
+    upper: BVec<Option<Bound<B>>>,
+    n_pivots: u64,
+}
+
+impl<B:Copy+fmt::Debug> Simplex<B> {
+    /// New simplex.
+    pub fn new() -> Self {
+        Simplex {
+            rows: vec!(), value: vec!(),
+            lower: BVec::new(), upper: BVec::new(),
+            n_pivots: 0,
+        }
+    }
+
+    /// Number of variables.
+    #[inline]
+    pub fn n_vars(&self) -> usize { self.rows.len() }
+
+    /// Number of pivoting operations so far.
+    pub fn n_pivots(&self) -> u64 { self.n_pivots }
+
+    /// New non-basic variable, with no bounds.
+    pub fn new_var(&mut self) -> Var {
+        let x = self.rows.len() as Var;
+        self.rows.push(None);
+        self.value.push(DQ::zero());
+        // variables are not backtracked
+        self.lower.push_nonbacktrack(None);
+        self.upper.push_nonbacktrack(None);
+        x
+    }
+
+    /// New basic variable `s`, such that `s = Σ aᵢ·xᵢ`.
+    pub fn new_slack(&mut self, le: &[(Var, Q)]) -> Var {
+        // express `le` in terms of non-basic variables
+        let mut row: Row = vec!();
+        for (x, a) in le.iter() {
+            match &self.rows[*x as usize] {
+                Some(r) => add_row(&mut row, a, r),
+                None => add_row(&mut row, a, &[(*x, Q::one())]),
+            }
+        }
+        let v = self.eval_row(&row);
+        let s = self.new_var();
+        self.value[s as usize] = v;
+        self.rows[s as usize] = Some(row);
+        s
+    }
+
+    /// Current value of `x`.
+    #[inline]
+    pub fn value(&self, x: Var) -> &DQ { &self.value[x as usize] }
+
+    /// Current lower bound of `x`.
+    #[inline]
+    pub fn lower(&self, x: Var) -> Option<&Bound<B>> { self.lower.get(x as usize).as_ref() }
+
+    /// Current upper bound of `x`.
+    #[inline]
+    pub fn upper(&self, x: Var) -> Option<&Bound<B>> { self.upper.get(x as usize).as_ref() }
+
+    #[inline]
+    fn is_basic(&self, x: Var) -> bool { self.rows[x as usize].is_some() }
+
+    fn eval_row(&self, row: &[(Var, Q)]) -> DQ {
+        let mut v = DQ::zero();
+        for (x, a) in row.iter() {
+            v = v + self.value[*x as usize].clone() * a;
+        }
+        v
+    }
+
+    /// Assert `x ≥ v`, because of `reason`.
+    ///
+    /// Returns the reasons of the conflict if it contradicts the upper bound of `x`.
+    pub fn assert_lower(&mut self, x: Var, v: DQ, reason: B) -> Result<(), Vec<B>> {
+        let i = x as usize;
+        trace!("simplex.assert-lower x{} >= {} ({:?})", x, v, reason);
+        if let Some(l) = self.lower.get(i) {
+            if l.value >= v { return Ok(()) } // weaker
+        }
+        if let Some(u) = self.upper.get(i) {
+            if u.value < v { return Err(vec!(reason, u.reason)) }
+        }
+        self.lower.set(i, Some(Bound{value: v.clone(), reason}));
+        if ! self.is_basic(x) && self.value[i] < v {
+            self.update(x, v);
+        }
+        Ok(())
+    }
+
+    /// Assert `x ≤ v`, because of `reason`.
+    ///
+    /// Returns the reasons of the conflict if it contradicts the lower bound of `x`.
+    pub fn assert_upper(&mut self, x: Var, v: DQ, reason: B) -> Result<(), Vec<B>> {
+        let i = x as usize;
+        trace!("simplex.assert-upper x{} <= {} ({:?})", x, v, reason);
+        if let Some(u) = self.upper.get(i) {
+            if u.value <= v { return Ok(()) } // weaker
+        }
+        if let Some(l) = self.lower.get(i) {
+            if l.value > v { return Err(vec!(reason, l.reason)) }
+        }
+        self.upper.set(i, Some(Bound{value: v.clone(), reason}));
+        if ! self.is_basic(x) && self.value[i] > v {
+            self.update(x, v);
+        }
+        Ok(())
+    }
+
+    // can the value of `x` increase?
+    fn can_increase(&self, x: Var) -> bool {
+        self.upper(x).map_or(true, |u| self.value[x as usize] < u.value)
+    }
+
+    // can the value of `x` decrease?
+    fn can_decrease(&self, x: Var) -> bool {
+        self.lower(x).map_or(true, |l| self.value[x as usize] > l.value)
+    }
+
+    /// Find an assignment that satisfies all bounds.
+    ///
+    /// Returns the reasons of the bounds that are inconsistent otherwise.
+    pub fn check(&mut self) -> Result<(), Vec<B>> {
+        loop {
+            // find the smallest basic variable that violates its bounds (Bland's rule)
+            let mut viol = None;
+            for x in 0 .. self.rows.len() as Var {
+                if ! self.is_basic(x) { continue }
+                let v = &self.value[x as usize];
+                if self.lower(x).map_or(false, |l| *v < l.value) {
+                    viol = Some((x, true));
+                    break
+                } else if self.upper(x).map_or(false, |u| *v > u.value) {
+                    viol = Some((x, false));
+                    break
+                }
+            }
+
+            let (x, below) = match viol {
+                None => return Ok(()),
+                Some(p) => p,
+            };
+
+            // find the smallest non-basic variable that can compensate
+            let row = self.rows[x as usize].as_ref().unwrap();
+            let y = row.iter().filter(|(y,a)| {
+                if below == a.is_positive() { self.can_increase(*y) } else { self.can_decrease(*y) }
+            }).map(|(y,_)| *y).min();
+
+            match y {
+                Some(y) => {
+                    let v = if below {
+                        self.lower(x).unwrap().value.clone()
+                    } else {
+                        self.upper(x).unwrap().value.clone()
+                    };
+                    self.pivot_and_update(x, y, v);
+                },
+                None => {
+                    // conflict: the row and the bounds of its variables
+                    let mut confl = vec!();
+                    if below {
+                        confl.push(self.lower(x).unwrap().reason);
+                        for (y,a) in row.iter() {
+                            let b = if a.is_positive() { self.upper(*y) } else { self.lower(*y) };
+                            confl.push(b.unwrap().reason);
+                        }
+                    } else {
+                        confl.push(self.upper(x).unwrap().reason);
+                        for (y,a) in row.iter() {
+                            let b = if a.is_positive() { self.lower(*y) } else { self.upper(*y) };
+                            confl.push(b.unwrap().reason);
+                        }
+                    }
+                    debug!("simplex.conflict {:?}", &confl);
+                    return Err(confl)
+                },
+            }
+        }
+    }
+
+    // set the value of non-basic `x` to `v`, updating basic variables
+    fn update(&mut self, x: Var, v: DQ) {
+        let diff = v.clone() - self.value[x as usize].clone();
+        for (b, row) in self.rows.iter().enumerate() {
+            if let Some(row) = row {
+                if let Some(a) = row_coeff(row, x) {
+                    let vb = self.value[b].clone() + diff.clone() * a;
+                    self.value[b] = vb;
+                }
+            }
+        }
+        self.value[x as usize] = v;
+    }
+
+    // make basic `x` non-basic with value `v`, and non-basic `y` basic
+    fn pivot_and_update(&mut self, x: Var, y: Var, v: DQ) {
+        trace!("simplex.pivot x{} x{}", x, y);
+        self.n_pivots += 1;
+        let a_xy = row_coeff(self.rows[x as usize].as_ref().unwrap(), y).unwrap().clone();
+        let theta = (v.clone() - self.value[x as usize].clone()) * &(Q::one() / &a_xy);
+        self.value[x as usize] = v;
+        let vy = self.value[y as usize].clone() + theta.clone();
+        self.value[y as usize] = vy;
+        for (b, row) in self.rows.iter().enumerate() {
+            if b == x as usize { continue }
+            if let Some(row) = row {
+                if let Some(a) = row_coeff(row, y) {
+                    let vb = self.value[b].clone() + theta.clone() * a;
+                    self.value[b] = vb;
+                }
+            }
+        }
+        self.pivot(x, y, &a_xy);
+    }
+
+    // `x = a_xy·y + Σ aᵢ·xᵢ` becomes `y = (x - Σ aᵢ·xᵢ) / a_xy`
+    fn pivot(&mut self, x: Var, y: Var, a_xy: &Q) {
+        let row_x = self.rows[x as usize].take().unwrap();
+        let inv = Q::one() / a_xy;
+        let mut row_y: Row = vec!((x, inv.clone()));
+        for (z, a) in row_x.into_iter() {
+            if z != y { add_row(&mut row_y, &(-a * &inv), &[(z, Q::one())]) }
+        }
+        // substitute `y` in other rows
+        for row in self.rows.iter_mut() {
+            if let Some(row) = row {
+                if let Some(a) = row_coeff(row, y).cloned() {
+                    row.retain(|(z,_)| *z != y);
+                    add_row(row, &a, &row_y);
+                }
+            }
+        }
+        self.rows[y as usize] = Some(row_y);
+    }
+
+    /// Concrete values for all variables, by picking a suitable value for `δ`.
+    ///
+    /// Each `(x, c)` in `diseqs` is a disequality `x ≠ c` that holds in the
+    /// current (symbolic) assignment, and must keep holding for concrete values.
+    /// Only meaningful after a successful `check`.
+    pub fn model(&self, diseqs: &[(Var, Q)]) -> Vec<Q> {
+        let mut delta = Q::one();
+        {
+            // `l ≤ v` must hold for concrete values, too
+            let mut refine = |l: &DQ, v: &DQ| {
+                if l.c < v.c && l.k > v.k {
+                    let d = (&v.c - &l.c) / (&l.k - &v.k);
+                    if d < delta { delta = d }
+                }
+            };
+            for (i, v) in self.value.iter().enumerate() {
+                if let Some(l) = self.lower.get(i) { refine(&l.value, v) }
+                if let Some(u) = self.upper.get(i) { refine(v, &u.value) }
+            }
+        }
+        // halve `δ` so that strict inequalities remain strict
+        let two = Q::from_integer(2.into());
+        delta /= &two;
+        // `c + k·δ ≠ c'` only fails for one value of `δ`, avoid it
+        for (x, c) in diseqs.iter() {
+            let v = &self.value[*x as usize];
+            if v.k.is_zero() { continue }
+            let d = (c - &v.c) / &v.k;
+            if d.is_positive() && d <= delta { delta = d / &two }
+        }
+        self.value.iter().map(|v| &v.c + &v.k * &delta).collect()
+    }
+
+    /// Push a backtracking level.
+    pub fn push_level(&mut self) {
+        self.lower.push_level();
+        self.upper.push_level();
+    }
+
+    /// Backtrack `n` levels, restoring bounds.
+    pub fn pop_levels(&mut self, n: usize) {
+        self.lower.pop_levels(n);
+        self.upper.pop_levels(n);
+    }
+
+    /// Number of backtracking levels.
+    #[inline]
+    pub fn n_levels(&self) -> usize { self.lower.n_levels() }
+}
+
+// coefficient of `x` in `row`
+fn row_coeff(row: &[(Var, Q)], x: Var) -> Option<&Q> {
+    row.binary_search_by_key(&x, |(y,_)| *y).ok().map(|i| &row[i].1)
+}
+
+// `row += a·r`, where `r` is sorted
+fn add_row(row: &mut Row, a: &Q, r: &[(Var, Q)]) {
+    for (x, b) in r.iter() {
+        let c = a * b;
+        match row.binary_search_by_key(x, |(y,_)| *y) {
+            Ok(i) => {
+                let c2 = &row[i].1 + c;
+                if c2.is_zero() { row.remove(i); } else { row[i].1 = c2 }
+            },
+            Err(i) => if ! c.is_zero() { row.insert(i, (*x, c)) },
+        }
+    }
+}
+
+mod dq {
+    use super::*;
+
+    impl DQ {
+        /// The δ-rational `c`.
+        pub fn new(c: Q) -> Self { DQ{c, k: Q::zero()} }
+
+        /// The δ-rational `c + kδ`.
+        pub fn with_delta(c: Q, k: i32) -> Self { DQ{c, k: Q::from_integer(k.into())} }
+
+        /// Zero.
+        pub fn zero() -> Self { DQ::new(Q::zero()) }
+    }
+
+    impl Add for DQ {
+        type Output = DQ;
+        fn add(self, o: DQ) -> DQ { DQ{c: self.c + o.c, k: self.k + o.k} }
+    }
+
+    impl Sub for DQ {
+        type Output = DQ;
+        fn sub(self, o: DQ) -> DQ { DQ{c: self.c - o.c, k: self.k - o.k} }
+    }
+
+    impl<'a> Mul<&'a Q> for DQ {
+        type Output = DQ;
+        fn mul(self, q: &'a Q) -> DQ { DQ{c: self.c * q, k: self.k * q} }
+    }
+
+    impl fmt::Display for DQ {
+        fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+            if self.k.is_zero() {
+                write!(out, "{}", self.c)
+            } else {
+                write!(out, "{}+{}δ", self.c, self.k)
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+extern crate batsmt_lra;
+
+use {
+    fxhash::{FxHashMap, FxHashSet, },
+    batsmt_core::{ast::{self, Manager, }, AstView, ast_u32::AST, },
+    batsmt_hast::{HManager, StrSymbolManager, },
+    batsmt_pretty as pp,
+    batsmt_theory::{self as theory, BoolLit, Theory, },
+    batsmt_lra::{Q, LRAView, Pred, LRATheory, parse_numeral, lra_theory::LinExpr, },
+};
+
+type M = HManager<StrSymbolManager>;
+
+// a term and a sign
+#[derive(Debug,Clone,Copy,Eq,PartialEq,Ord,PartialOrd,Hash)]
+struct Lit(AST, bool);
+
+impl std::ops::Not for Lit {
+    type Output = Self;
+    fn not(self) -> Self { Lit(self.0, !self.1) }
+}
+
+impl BoolLit for Lit {
+    fn abs(&self) -> Self { Lit(self.0, true) }
+}
+
+// terms with `+`, `-`, `*`, `/`, `<=` and numerals
+struct Ctx {
+    m: M,
+    add: AST,
+    sub: AST,
+    mul: AST,
+    div: AST,
+    leq: AST,
+    nums: FxHashMap<AST, Q>,
+    reals: FxHashSet<AST>, // non-arithmetic terms of sort `Real`
+}
+
+impl Ctx {
+    fn new() -> Self {
+        let mut m = M::new();
+        let mut mk = |s: &str| m.mk_string(s.to_string(), None);
+        let (add, sub, mul, div, leq) = (mk("+"), mk("-"), mk("*"), mk("/"), mk("<="));
+        Ctx{m, add, sub, mul, div, leq, nums: FxHashMap::default(), reals: FxHashSet::default()}
+    }
+    fn cst(&mut self, s: &str) -> AST { self.m.mk_string(s.to_string(), None) }
+    fn num(&mut self, s: &str) -> AST {
+        let q = parse_numeral(s).unwrap();
+        batsmt_lra::Ctx::mk_numeral(self, &q)
+    }
+    fn app(&mut self, f: AST, args: &[AST]) -> AST { self.m.mk_app(f, args, None) }
+}
+
+impl ast::HasManager for Ctx {
+    type M = M;
+    fn m(&self) -> &M { &self.m }
+    fn m_mut(&mut self) -> &mut M { &mut self.m }
+}
+
+impl theory::BoolLitCtx for Ctx {
+    type B = Lit;
+}
+
+impl theory::Ctx for Ctx {
+    fn pp_ast(&self, t: &AST, ctx: &mut pp::Ctx) {
+        pp::Pretty1::pp1_into(&self.m, t, ctx)
+    }
+}
+
+impl batsmt_lra::Ctx for Ctx {
+    fn view_as_lra<'a>(&'a self, t: &'a AST) -> LRAView<'a, AST> {
+        if let Some(q) = self.nums.get(t) {
+            return LRAView::Const(q)
+        }
+        match self.m.view(t) {
+            AstView::App{f, args} if *f == self.add => LRAView::Add(args),
+            AstView::App{f, args} if *f == self.sub => LRAView::Sub(args),
+            AstView::App{f, args} if *f == self.mul => LRAView::Mul(args),
+            AstView::App{f, args} if *f == self.div => LRAView::Div(args),
+            AstView::App{f, args} if *f == self.leq => LRAView::Pred(Pred::Leq, &args[0], &args[1]),
+            _ => LRAView::Other(t),
+        }
+    }
+
+    fn is_real(&self, t: &AST) -> bool { self.reals.contains(t) }
+
+    fn mk_pred(&mut self, p: Pred, a: AST, b: AST) -> AST {
+        assert_eq!(p, Pred::Leq);
+        let leq = self.leq;
+        self.app(leq, &[a, b])
+    }
+
+    fn mk_numeral(&mut self, q: &Q) -> AST {
+        let t = self.m.mk_string(batsmt_lra::pp_q(q).to_string(), None);
+        self.nums.insert(t, q.clone());
+        t
+    }
+}
+
+fn q(s: &str) -> Q { parse_numeral(s).unwrap() }
+
+#[test]
+fn test_linear() {
+    let mut c = Ctx::new();
+    let (x, y) = (c.cst("x"), c.cst("y"));
+    let (n2, n3, n4) = (c.num("2"), c.num("3"), c.num("4"));
+    // (* 2 x 3) - (/ x 4) + (- y) - 1
+    let t1 = c.app(c.mul, &[n2, x, n3]);
+    let t2 = c.app(c.div, &[x, n4]);
+    let t3 = c.app(c.sub, &[y]);
+    let t = c.app(c.sub, &[t1, t2]);
+    let t = c.app(c.add, &[t, t3]);
+    let one = c.num("1");
+    let le = LinExpr::diff(&c, &t, &one);
+    let mut terms = vec!((x, q("23/4")), (y, q("-1")));
+    terms.sort_by_key(|(t,_)| *t);
+    assert_eq!(le, LinExpr{terms, cst: q("-1")});
+}
+
+#[test]
+fn test_non_linear() {
+    let mut c = Ctx::new();
+    let (x, y) = (c.cst("x"), c.cst("y"));
+    let (n2, n3) = (c.num("2"), c.num("3"));
+    let zero = c.num("0");
+
+    // `(* 2 x y)` is a variable, with coefficient 1
+    let t = c.app(c.mul, &[n2, x, y]);
+    assert_eq!(LinExpr::diff(&c, &t, &zero), LinExpr{terms: vec!((t, q("1"))), cst: q("0")});
+
+    // `(* 3 (* 2 x y))` is `3·(* 2 x y)`
+    let u = c.app(c.mul, &[n3, t]);
+    assert_eq!(LinExpr::diff(&c, &u, &zero), LinExpr{terms: vec!((t, q("3"))), cst: q("0")});
+
+    // `(/ x 2 y)` is a variable, with coefficient 1
+    let t = c.app(c.div, &[x, n2, y]);
+    assert_eq!(LinExpr::diff(&c, &zero, &t), LinExpr{terms: vec!((t, q("-1"))), cst: q("0")});
+}
+
+#[test]
+fn test_purify() {
+    let mut c = Ctx::new();
+    let (x, y, f, eq) = (c.cst("x"), c.cst("y"), c.cst("f"), c.cst("="));
+    let one = c.num("1");
+    // `(= (f (+ x 1)) (f y))` is not arithmetic, but `(+ x 1)` is shared
+    let x1 = c.app(c.add, &[x, one]);
+    let fx1 = c.app(f, &[x1]);
+    let fy = c.app(f, &[y]);
+    let t = c.app(eq, &[fx1, fy]);
+    let mut th = LRATheory::new(&mut c);
+    th.add_literal(&mut c, t, Lit(t, true));
+    let mut terms = vec!();
+    th.iter_interface_terms(&c, |u| terms.push(*u));
+    terms.sort();
+    let mut expect = vec!(x, x1);
+    expect.sort();
+    assert_eq!(terms, expect);
+}
+
+#[test]
+fn test_purify_real() {
+    let mut c = Ctx::new();
+    let (a, b, f, p) = (c.cst("a"), c.cst("b"), c.cst("f"), c.cst("p"));
+    // `(p (f a) b)`, where `(f a)` is real but `a` and `b` are not:
+    // `(f a)` is a variable, so it has a value in the model
+    let fa = c.app(f, &[a]);
+    c.reals.insert(fa);
+    let t = c.app(p, &[fa, b]);
+    let mut th = LRATheory::new(&mut c);
+    th.add_literal(&mut c, t, Lit(t, true));
+    let mut terms = vec!();
+    th.iter_interface_terms(&c, |u| terms.push(*u));
+    assert_eq!(terms, vec!(fa));
+}
@@ -0,0 +1,94 @@
+
+extern crate batsmt_lra;
+
+use {
+    batsmt_lra::{Q, parse_numeral, simplex::{Simplex, DQ}, },
+};
+
+fn q(s: &str) -> Q { parse_numeral(s).unwrap() }
+
+#[test]
+fn test_parse_numeral() {
+    assert_eq!(q("3"), Q::from_integer(3.into()));
+    assert_eq!(q("1.5"), Q::new(3.into(), 2.into()));
+    assert_eq!(q("2/4"), Q::new(1.into(), 2.into()));
+    assert!(parse_numeral("1/0").is_none());
+    assert!(parse_numeral("a").is_none());
+}
+
+#[test]
+fn test_sat() {
+    // x + y >= 2, x <= 1, y <= 1
+    let mut s: Simplex<u32> = Simplex::new();
+    let x = s.new_var();
+    let y = s.new_var();
+    let sum = s.new_slack(&[(x, q("1")), (y, q("1"))]);
+    s.assert_lower(sum, DQ::new(q("2")), 0).unwrap();
+    s.assert_upper(x, DQ::new(q("1")), 1).unwrap();
+    s.assert_upper(y, DQ::new(q("1")), 2).unwrap();
+    assert!(s.check().is_ok());
+    let m = s.model(&[]);
+    assert_eq!(m[x as usize], q("1"));
+    assert_eq!(m[y as usize], q("1"));
+}
+
+#[test]
+fn test_unsat_backtrack() {
+    // x + y > 2, x <= 1, y <= 1
+    let mut s: Simplex<u32> = Simplex::new();
+    let x = s.new_var();
+    let y = s.new_var();
+    let sum = s.new_slack(&[(x, q("1")), (y, q("1"))]);
+    s.assert_upper(x, DQ::new(q("1")), 1).unwrap();
+    s.assert_upper(y, DQ::new(q("1")), 2).unwrap();
+    s.push_level();
+    s.assert_lower(sum, DQ::with_delta(q("2"), 1), 0).unwrap();
+    let mut confl = s.check().unwrap_err();
+    confl.sort();
+    assert_eq!(confl, vec!(0, 1, 2));
+
+    // without the strict bound, it's satisfiable again
+    s.pop_levels(1);
+    assert!(s.check().is_ok());
+    s.assert_lower(sum, DQ::new(q("2")), 3).unwrap();
+    assert!(s.check().is_ok());
+}
+
+#[test]
+fn test_strict_model() {
+    // 0 < x < y < 1
+    let mut s: Simplex<u32> = Simplex::new();
+    let x = s.new_var();
+    let y = s.new_var();
+    let d = s.new_slack(&[(y, q("1")), (x, q("-1"))]);
+    s.assert_lower(x, DQ::with_delta(q("0"), 1), 0).unwrap();
+    s.assert_upper(y, DQ::with_delta(q("1"), -1), 1).unwrap();
+    s.assert_lower(d, DQ::with_delta(q("0"), 1), 2).unwrap();
+    assert!(s.check().is_ok());
+    let m = s.model(&[]);
+    let (vx, vy) = (m[x as usize].clone(), m[y as usize].clone());
+    assert!(q("0") < vx && vx < vy && vy < q("1"), "x={}, y={}", vx, vy);
+}
+
+#[test]
+fn test_diseq_model() {
+    // 0 < x < 1, x ≠ 1/4, x ≠ 1/8
+    let mut s: Simplex<u32> = Simplex::new();
+    let x = s.new_var();
+    s.assert_lower(x, DQ::with_delta(q("0"), 1), 0).unwrap();
+    s.assert_upper(x, DQ::with_delta(q("1"), -1), 1).unwrap();
+    assert!(s.check().is_ok());
+    let m = s.model(&[(x, q("1/4")), (x, q("1/8"))]);
+    let vx = m[x as usize].clone();
+    assert!(q("0") < vx && vx < q("1") && vx != q("1/4") && vx != q("1/8"), "x={}", vx);
+}
+
+#[test]
+fn test_bound_conflict() {
+    let mut s: Simplex<u32> = Simplex::new();
+    let x = s.new_var();
+    s.assert_upper(x, DQ::new(q("1")), 0).unwrap();
+    let mut c = s.assert_lower(x, DQ::new(q("2")), 1).unwrap_err();
+    c.sort();
+    assert_eq!(c, vec!(0, 1));
+}
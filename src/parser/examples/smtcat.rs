@@ -7,7 +7,7 @@ extern crate fxhash;
 #[macro_use] extern crate log;
 
 use {
-    std::{env,fs,error::Error},
+    std::{env,fs,io,error::Error},
     batsmt_parser::{self as parser, simple_ast},
 };
 
@@ -16,26 +16,24 @@ fn main() -> Result<(), Box<Error>> {
 
     let mut b = simple_ast::Builder::new();
 
-    // parse
-    let stmts = {
-        let args = env::args();
-        match args.skip(1).next() {
-            None => {
-                info!("parse stdin");
-                parser::parse_stdin(&mut b)?
-            },
-            Some(file) => {
-                info!("parse file {:?}", file);
-                let file = fs::File::open(file)?;
-                parser::parse(&mut b, file)?
-            },
-        }
+    let input: Box<dyn io::Read> = match env::args().skip(1).next() {
+        None => {
+            info!("parse stdin");
+            Box::new(io::stdin())
+        },
+        Some(file) => {
+            info!("parse file {:?}", file);
+            Box::new(fs::File::open(file)?)
+        },
     };
 
-    info!("parsed {} statements", stmts.len());
-    for s in &stmts {
-        println!("parsed statement {}", s);
+    // print statements as they are parsed
+    let mut n = 0;
+    for s in parser::StatementStream::new(&mut b, input) {
+        println!("parsed statement {}", s?);
+        n += 1;
     }
+    info!("parsed {} statements", n);
 
     Ok(())
 }
@@ -0,0 +1,103 @@
+
+//! Parser for DIMACS CNF problems.
+//!
+//! A problem starts with a header `p cnf <vars> <clauses>`, followed by
+//! clauses, each of them a list of non-zero integers terminated by `0`.
+//! Clauses can span several lines. Lines starting with `c` are comments.
+
+use {
+    std::io::{self, BufRead},
+    crate::parser::{Error, Loc, Pos, Result},
+};
+
+/// The `p cnf` line of a problem.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub struct Header {
+    pub n_vars: u32,
+    pub n_clauses: u32,
+}
+
+/// Parse a DIMACS problem from `r`, calling `f` on each clause.
+///
+/// Returns the header, if there is one. Variables must not be larger
+/// than the number of variables it declares. `file` is only used in
+/// error messages.
+pub fn parse<R, F>(r: R, file: Option<&str>, mut f: F) -> Result<Option<Header>>
+    where R: io::Read, F: FnMut(&[i32])
+{
+    let loc = |line: u32, col: u32, snippet: &str| {
+        Loc{file: file.map(|s| s.to_string()), pos: Pos{line, col}, snippet: snippet.to_string()}
+    };
+    let mut header: Option<Header> = None;
+    let mut clause = vec!();
+    let mut n_clauses = 0;
+
+    for (i, line) in io::BufReader::new(r).lines().enumerate() {
+        let line_no = i as u32 + 1;
+        let line = line.map_err(|e| Error::Io(e.to_string(), loc(line_no, 1, "")))?;
+        let l = line.trim();
+        if l.is_empty() || l.starts_with('c') {
+            continue
+        } else if l.starts_with('%') {
+            break // end of the problem, in some SATLIB files
+        } else if l.starts_with('p') {
+            if header.is_some() || n_clauses > 0 || clause.len() > 0 {
+                return Err(Error::Syntax("the header must come first".to_string(), loc(line_no, 1, l)))
+            }
+            let mut toks = l.split_whitespace();
+            let p = (
+                toks.next(), toks.next(),
+                toks.next().and_then(|s| s.parse().ok()),
+                toks.next().and_then(|s| s.parse().ok()),
+                toks.next(),
+            );
+            match p {
+                (Some("p"), Some("cnf"), Some(n_vars), Some(n_clauses), None) => {
+                    header = Some(Header{n_vars, n_clauses});
+                },
+                _ => {
+                    let msg = "expected header `p cnf <vars> <clauses>`".to_string();
+                    return Err(Error::Syntax(msg, loc(line_no, 1, l)))
+                },
+            }
+            continue
+        }
+
+        for tok in line.split_whitespace() {
+            let col = (tok.as_ptr() as usize - line.as_ptr() as usize) as u32 + 1;
+            let x: i32 = match tok.parse() {
+                Ok(x) => x,
+                Err(_) => {
+                    let msg = format!("expected a literal, got {:?}", tok);
+                    return Err(Error::Syntax(msg, loc(line_no, col, l)))
+                },
+            };
+            if x == 0 {
+                f(&clause);
+                clause.clear();
+                n_clauses += 1;
+                continue
+            }
+            if let Some(h) = header {
+                if (x as i64).abs() > h.n_vars as i64 {
+                    let msg = format!("variable {} is out of bounds, there are {} variables",
+                        (x as i64).abs(), h.n_vars);
+                    return Err(Error::Syntax(msg, loc(line_no, col, l)))
+                }
+            }
+            clause.push(x);
+        }
+    }
+
+    // tolerate a missing `0` after the last clause
+    if clause.len() > 0 {
+        f(&clause);
+        n_clauses += 1;
+    }
+    if let Some(h) = header {
+        if h.n_clauses != n_clauses {
+            warn!("the header announces {} clauses, but there are {}", h.n_clauses, n_clauses);
+        }
+    }
+    Ok(header)
+}
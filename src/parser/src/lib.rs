@@ -4,9 +4,11 @@
 pub mod types;
 pub mod parser;
 pub mod simple_ast;
+pub mod dimacs;
+mod typing;
 
 pub use crate::{
-    types::{Atom,Statement,TermBuilder,SortBuilder,Op as BuiltinOp,pp_stmt},
-    parser::{parse,parse_stdin,parse_str,Error,Result},
+    types::{Atom,Statement,Datatype,Quant,TermBuilder,SortBuilder,SortView,Op as BuiltinOp,pp_stmt},
+    parser::{parse,parse_stdin,parse_str,StatementStream,Error,Loc,Pos,Result},
 };
 
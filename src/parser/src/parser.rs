@@ -1,34 +1,102 @@
 
 use {
-    std::{error, result, fmt::{self,Display}, io, ops::Deref},
+    std::{error, result, fmt, io, ops::Deref, rc::Rc},
     fxhash::FxHashMap,
-    crate::types::*,
+    crate::{types::*, typing, },
 };
 
-/// Error messages
-#[derive(Debug)]
-pub struct Error(String);
+/// Position in the input (lines and columns start at 1).
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub struct Pos {
+    pub line: u32,
+    pub col: u32,
+}
 
-impl fmt::Display for Error {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        Display::fmt(&self.0, fmt)
-    }
+/// Location of an error.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub struct Loc {
+    /// Name of the input file, if known.
+    pub file: Option<String>,
+    pub pos: Pos,
+    /// The innermost s-expression around the error, possibly truncated.
+    pub snippet: String,
 }
 
-impl error::Error for Error {
-  fn description(&self) -> &str { &self.0 }
-  fn cause(&self) -> Option<&error::Error> { None }
+/// Parse errors, along with where they occurred.
+#[derive(Clone,Debug)]
+pub enum Error {
+    /// Use of an undeclared sort.
+    UnknownSort(String, Loc),
+    /// Use of an undeclared function, constant, or variable.
+    UnknownSymbol(String, Loc),
+    /// Wrong number of arguments.
+    Arity(String, Loc),
+    /// Ill-sorted term.
+    Sort(String, Loc),
+    UnexpectedEof(Loc),
+    /// Any other malformed input.
+    Syntax(String, Loc),
+    /// The input could not be read.
+    Io(String, Loc),
 }
 
-pub type Result<T> = result::Result<T, Box<error::Error>>;
+impl Error {
+    /// The message, without the location.
+    pub fn msg(&self) -> String {
+        match self {
+            Error::UnknownSort(s, _) => format!("{} is not a known sort", s),
+            Error::UnknownSymbol(s, _) => format!("{} is not a known symbol", s),
+            Error::UnexpectedEof(_) => "unexpected end-of-file".to_string(),
+            Error::Arity(s, _) | Error::Sort(s, _) | Error::Syntax(s, _) => s.clone(),
+            Error::Io(s, _) => format!("read error: {}", s),
+        }
+    }
+
+    /// Where the error occurred.
+    pub fn loc(&self) -> &Loc {
+        match self {
+            Error::UnknownSort(_, l) | Error::UnknownSymbol(_, l) | Error::Arity(_, l)
+                | Error::Sort(_, l) | Error::UnexpectedEof(l) | Error::Syntax(_, l)
+                | Error::Io(_, l) => l
+        }
+    }
+
+    /// Position in the input.
+    pub fn pos(&self) -> Pos { self.loc().pos }
+
+    /// Can parsing resume after this error?
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            Error::UnexpectedEof(_) | Error::Io(..) => false,
+            _ => true,
+        }
+    }
+}
 
-fn mk_err(s: String) -> Box<error::Error> {
-    Box::new(Error(s))
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let Loc{file, pos, snippet} = self.loc();
+        match file {
+            Some(f) => write!(fmt, "{}:{}:{}: {}", f, pos.line, pos.col, self.msg())?,
+            None => write!(fmt, "line {}, col {}: {}", pos.line, pos.col, self.msg())?,
+        }
+        if snippet.len() > 0 {
+            write!(fmt, " (in `{}`)", snippet)?;
+        }
+        Ok(())
+    }
 }
 
+impl error::Error for Error {}
+
+pub type Result<T> = result::Result<T, Error>;
+
 // parser's buffer size
 const BUF_SIZE : usize = 1_024 * 16;
 
+// maximum length of snippets in errors
+const SNIPPET_LEN : usize = 60;
+
 #[derive(Debug)]
 struct LetBinding<Var, Term>{
     name: Atom,
@@ -37,13 +105,25 @@ struct LetBinding<Var, Term>{
     old_v: Option<Var>, // if shadowed
 }
 
+// A declaration to undo on `pop`, with what it shadows
+enum Undo<B: TermBuilder> {
+    Sort(Atom, Option<(B::Sort, u8)>),
+    SortDef(Atom, Option<Rc<(Vec<Atom>, SortExpr)>>),
+    Fun(Atom, Option<(B::Fun, Vec<B::Sort>)>),
+}
+
 // A basic SMT-LIB parser
 struct ParserState<'a, R : io::Read, B : TermBuilder + 'a> {
     io: ParserIO<R>,
     build: &'a mut B,
-    sorts: FxHashMap<Atom, B::Sort>,
-    funs: FxHashMap<Atom, B::Fun>,
-    vars: FxHashMap<Atom, B::Var>, // let-bindings
+    sorts: FxHashMap<Atom, (B::Sort, u8)>, // sort, and its arity
+    sort_defs: FxHashMap<Atom, Rc<(Vec<Atom>, SortExpr)>>, // from `define-sort`
+    funs: FxHashMap<Atom, (B::Fun, Vec<B::Sort>)>, // function, and its arguments' sorts
+    vars: FxHashMap<Atom, B::Var>, // let-bindings and bound variables
+    bound: Vec<(Atom, Option<B::Var>)>, // entries of `vars` in scope, with what they shadow
+    patterns: Vec<Vec<B::Term>>, // `:pattern` of the current quantifier
+    undo: Vec<Undo<B>>, // declarations made inside `push` scopes
+    scopes: Vec<usize>, // length of `undo` at each active `push`
     atom_buf: Vec<u8>,
 }
 
@@ -55,6 +135,11 @@ struct ParserIO<R : io::Read> {
     len: usize, // current size of buf
     line: u32,
     col: u32,
+    file: Option<String>,
+    text: Vec<u8>, // text of the current statement
+    opens: Vec<(usize, Pos)>, // offset in `text` of the unclosed parenthesis
+    last_closed: (usize, Pos), // offset in `text` of the last closed s-expr
+    last_atom: Pos, // position of the last atom
 }
 
 impl<R : io::Read> ParserIO<R> {
@@ -63,20 +148,61 @@ impl<R : io::Read> ParserIO<R> {
         trace!("refill internal buffer (size {})", BUF_SIZE);
         debug_assert!(self.i >= self.len);
         self.i = 0;
-        self.len = self.r.read(&mut self.buf)?;
+        self.len = match self.r.read(&mut self.buf) {
+            Ok(n) => n,
+            Err(e) => return Err(Error::Io(e.to_string(), self.loc())),
+        };
         if self.len == 0 {
             self.eof = true;
         }
         Ok(())
     }
 
+    fn pos(&self) -> Pos { Pos{line: self.line, col: self.col} }
+
+    // location of an error at position `pos`, in the s-expr starting at `start`
+    fn loc_at(&self, start: usize, pos: Pos) -> Loc {
+        // collapse whitespace, and truncate
+        let text = String::from_utf8_lossy(&self.text[start.min(self.text.len())..]);
+        let mut snippet = String::new();
+        for (i, w) in text.split_whitespace().enumerate() {
+            if i > 0 { snippet.push(' ') }
+            snippet.push_str(w);
+        }
+        if snippet.chars().count() > SNIPPET_LEN {
+            snippet = snippet.chars().take(SNIPPET_LEN).collect();
+            snippet.push('…');
+        }
+        Loc{file: self.file.clone(), pos, snippet}
+    }
+
+    // location of an error at the current position
+    fn loc(&self) -> Loc {
+        let start = self.opens.last().map_or(0, |(i,_)| *i);
+        self.loc_at(start, self.pos())
+    }
+
+    // location of an error at `pos`
+    fn loc_pos(&self, pos: Pos) -> Loc {
+        let start = self.opens.last().map_or(0, |(i,_)| *i);
+        self.loc_at(start, pos)
+    }
+
+    // location of an error about the last atom
+    fn loc_atom(&self) -> Loc { self.loc_pos(self.last_atom) }
+
+    // location of an error about the s-expression that was just closed
+    fn loc_closed(&self) -> Loc {
+        let (start, pos) = self.last_closed;
+        self.loc_at(start, pos)
+    }
+
     fn err_with<T>(&self, s: impl Deref<Target=str>) -> Result<T> {
-        let s: &str = &*s;
-        Err(mk_err(format!("{} (line {}, col {})", s, self.line, self.col)))
+        Err(Error::Syntax(s.to_string(), self.loc()))
     }
 
     fn err_eof<T>(&self) -> Result<T> {
-        self.err_with("unexpected end-of-file")
+        Err(Error::UnexpectedEof(self.loc()))
     }
 
     // get current char, or EOF
@@ -109,10 +235,22 @@ impl<R : io::Read> ParserIO<R> {
 
     // discard current char (must be valid!)
     fn junk(&mut self) {
+        // track the nesting of s-expressions
+        match self.buf[self.i] {
+            b'(' => self.opens.push((self.text.len(), self.pos())),
+            b')' => if let Some(o) = self.opens.pop() { self.last_closed = o },
+            _ => (),
+        }
+        self.junk_raw()
+    }
+
+    // discard current char, which is not part of the structure (e.g. in `|a b|`)
+    fn junk_raw(&mut self) {
+        self.text.push(self.buf[self.i]);
         // update pos
         if self.buf[self.i] == b'\n' {
             self.line += 1;
-            self.col = 0;
+            self.col = 1;
         } else {
             self.col += 1;
         }
@@ -144,6 +282,36 @@ impl<R : io::Read> ParserIO<R> {
         }
         Ok(())
     }
+
+    // start a new statement
+    fn start_statement(&mut self) {
+        self.text.clear();
+        self.opens.clear();
+        self.last_closed = (0, self.pos());
+    }
+
+    // skip the rest of the current statement, or to the next one if we are
+    // not inside any statement
+    fn skip_statement(&mut self) -> Result<()> {
+        let mut quoted = None; // inside `|…|` or `"…"`
+        while let Some(c) = self.try_get()? {
+            match c {
+                b'|' | b'"' if quoted.is_none() || quoted == Some(c) => {
+                    quoted = if quoted.is_none() { Some(c) } else { None };
+                    self.junk_raw()
+                },
+                _ if quoted.is_some() => self.junk_raw(),
+                b';' => self.skip_to_eol()?,
+                b'(' if self.opens.len() == 0 => break, // next statement
+                b')' => {
+                    self.junk();
+                    if self.opens.len() == 0 { break }
+                },
+                _ => self.junk(),
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<'a, R : io::Read, B : TermBuilder> ParserState<'a, R, B> {
@@ -152,12 +320,20 @@ impl<'a, R : io::Read, B : TermBuilder> ParserState<'a, R, B> {
         ParserState {
             funs: FxHashMap::default(),
             sorts: FxHashMap::default(),
+            sort_defs: FxHashMap::default(),
             vars: FxHashMap::default(),
+            bound: vec!(),
+            patterns: vec!(),
+            undo: vec!(),
+            scopes: vec!(),
             atom_buf: vec!(),
             build, 
             io: ParserIO {
                 r, eof: false, buf: [0; BUF_SIZE], len: 0,
-                i: 0, line: 1, col: 1,
+                i: 0, line: 1, col: 1, file: None,
+                text: vec!(), opens: vec!(),
+                last_closed: (0, Pos{line: 1, col: 1}),
+                last_atom: Pos{line: 1, col: 1},
             },
         }
     }
@@ -181,19 +357,33 @@ impl<'a, R : io::Read, B : TermBuilder> ParserState<'a, R, B> {
         let s = &mut self.atom_buf;
         s.clear();
 
+        self.io.last_atom = self.io.pos();
         let c = self.io.get()?;
         if c == b'|' {
             // escaped atom
             loop {
-                self.io.junk();
+                self.io.junk_raw();
                 let c = self.io.get()?;
                 if c == b'|' {
-                    self.io.junk();
+                    self.io.junk_raw();
                     break
                 } else {
                     s.push(c);
                 }
             }
+        } else if c == b'"' {
+            // string literal, kept with its quotes. `""` is an escaped quote.
+            s.push(c);
+            loop {
+                self.io.junk_raw();
+                let c = self.io.get()?;
+                s.push(c);
+                if c == b'"' {
+                    self.io.junk_raw();
+                    if self.io.get()? != b'"' { break }
+                    s.push(c);
+                }
+            }
         } else {
             s.push(c);
             loop {
@@ -207,11 +397,78 @@ impl<'a, R : io::Read, B : TermBuilder> ParserState<'a, R, B> {
         }
 
         // convert to utf8 and onto the heap
-        let str = std::str::from_utf8(&s)?;
+        let str = match std::str::from_utf8(&s) {
+            Ok(s) => s,
+            Err(e) => return self.io.err_with(format!("invalid utf8 in atom: {}", e)),
+        };
         let s: Atom = str.into();; // now convert to utf8
         Ok(s)
     }
 
+    // parse the name of a new symbol. SMT-LIB reserves symbols starting
+    // with `@` for solvers, we use them in proofs.
+    fn new_symbol(&mut self) -> Result<Atom> {
+        let a = self.atom()?;
+        if a.starts_with('@') {
+            return self.io.err_with(format!("symbol {} is reserved, it starts with `@`", a))
+        } else if self.funs.contains_key(&a) {
+            return self.io.err_with(format!("symbol {} is already declared", a))
+        }
+        Ok(a)
+    }
+
+    // parse the name of a new sort
+    fn new_sort_name(&mut self) -> Result<Atom> {
+        let a = self.atom()?;
+        self.check_new_sort(&a)?;
+        Ok(a)
+    }
+
+    fn check_new_sort(&self, a: &Atom) -> Result<()> {
+        if self.sorts.contains_key(a) || self.sort_defs.contains_key(a) {
+            return self.io.err_with(format!("sort {} is already declared", a))
+        }
+        Ok(())
+    }
+
+    // declare sort `a`, so that `pop` can forget it
+    fn add_sort(&mut self, a: Atom, sort: B::Sort, n: u8) {
+        let old = self.sorts.insert(a.clone(), (sort, n));
+        if ! self.scopes.is_empty() { self.undo.push(Undo::Sort(a, old)) }
+    }
+
+    // define sort `a`, so that `pop` can forget it
+    fn add_sort_def(&mut self, a: Atom, def: Rc<(Vec<Atom>, SortExpr)>) {
+        let old = self.sort_defs.insert(a.clone(), def);
+        if ! self.scopes.is_empty() { self.undo.push(Undo::SortDef(a, old)) }
+    }
+
+    // declare function `a`, so that `pop` can forget it
+    fn add_fun(&mut self, a: Atom, f: B::Fun, args: Vec<B::Sort>) {
+        let old = self.funs.insert(a.clone(), (f, args));
+        if ! self.scopes.is_empty() { self.undo.push(Undo::Fun(a, old)) }
+    }
+
+    fn push_scopes(&mut self, n: u32) {
+        for _ in 0 .. n { self.scopes.push(self.undo.len()) }
+    }
+
+    // forget declarations of the last `n` scopes. Like the solver, ignore
+    // `pop` if there are not enough scopes.
+    fn pop_scopes(&mut self, n: u32) {
+        let n = n as usize;
+        if n == 0 || n > self.scopes.len() { return }
+        let len = self.scopes[self.scopes.len() - n];
+        self.scopes.truncate(self.scopes.len() - n);
+        while self.undo.len() > len {
+            match self.undo.pop().unwrap() {
+                Undo::Sort(a, old) => restore(&mut self.sorts, a, old),
+                Undo::SortDef(a, old) => restore(&mut self.sort_defs, a, old),
+                Undo::Fun(a, old) => restore(&mut self.funs, a, old),
+            }
+        }
+    }
+
     // parse a list of `A`, without consuming closing parenthesis
     fn many_until_paren<A, F>(&mut self, mut f: F) -> Result<Vec<A>>
         where F: FnMut(&mut Self) -> Result<A>
@@ -241,35 +498,322 @@ impl<'a, R : io::Read, B : TermBuilder> ParserState<'a, R, B> {
         Ok(x)
     }
 
+    // parse a numeral, or return `1` if there is none
+    fn opt_numeral(&mut self) -> Result<u32> {
+        self.io.skip_spaces()?;
+        if self.io.get()? == b')' {
+            Ok(1)
+        } else {
+            let a = self.atom()?;
+            match a.parse::<u32>() {
+                Ok(n) => Ok(n),
+                Err(_) => self.io.err_with(format!("expected numeral, got '{}'", a)),
+            }
+        }
+    }
+
+    // parse the numeral index of an indexed identifier
+    fn index(&mut self) -> Result<u32> {
+        let a = self.atom()?;
+        match a.parse::<u32>() {
+            Ok(n) => Ok(n),
+            Err(_) => self.io.err_with(format!("expected numeral index, got '{}'", a)),
+        }
+    }
+
+    // parse the arity of a sort constructor
+    fn arity(&mut self) -> Result<u8> {
+        let a = self.atom()?;
+        match a.parse::<u8>() {
+            Ok(n) => Ok(n),
+            Err(_) => self.io.err_with(format!("expected arity, got '{}'", a)),
+        }
+    }
+
+    // parse a bit-vector literal `#b0101` or `#x1f`, least significant bit first
+    fn bv_literal(&mut self, a: &str) -> Result<Vec<bool>> {
+        let mut bits = vec!();
+        if a.starts_with("#b") && a.len() > 2 {
+            for c in a[2..].bytes().rev() {
+                match c {
+                    b'0' => bits.push(false),
+                    b'1' => bits.push(true),
+                    _ => return self.io.err_with(format!("invalid binary literal {}", a)),
+                }
+            }
+        } else if a.starts_with("#x") && a.len() > 2 {
+            for c in a[2..].chars().rev() {
+                let d = match c.to_digit(16) {
+                    Some(d) => d,
+                    None => return self.io.err_with(format!("invalid hexadecimal literal {}", a)),
+                };
+                for i in 0 .. 4 { bits.push((d >> i) & 1 == 1) }
+            }
+        } else {
+            return self.io.err_with(format!("invalid bit-vector literal {}", a))
+        }
+        Ok(bits)
+    }
+
+    // parse `BitVec n)` after `(_`
+    fn bv_sort_width(&mut self) -> Result<u32> {
+        let bv = self.atom()?;
+        if &*bv != "BitVec" {
+            return Err(Error::UnknownSort(format!("(_ {} …)", &bv), self.io.loc_atom()))
+        }
+        let n = self.index()?;
+        if n == 0 {
+            return self.io.err_with("bit-vectors must have a positive width")
+        }
+        self.io.skip_spaces()?;
+        self.expect_char(b')')?;
+        Ok(n)
+    }
+
     // parse a sort
     fn sort(&mut self) -> Result<B::Sort> {
+        self.io.skip_spaces()?;
+        if self.io.get()? == b'(' {
+            self.io.junk();
+            let a = self.atom()?;
+            if &*a == "_" {
+                // `(_ BitVec n)`
+                let n = self.bv_sort_width()?;
+                return Ok(self.build.get_bv(n))
+            }
+            let pos = self.io.last_atom;
+            let args = self.many_until_paren(|m| m.sort())?;
+            self.expect_char(b')')?;
+            return self.app_sort(&a, args, pos)
+        }
         let a = self.atom()?;
-        if &*a == "Bool" { return Ok(self.build.get_bool()) }; // builtin
-        match self.sorts.get(&a) {
-            Some(s) => Ok(s.clone()),
-            None => self.io.err_with(format!("{} is not a known sort", &a).to_string()),
+        self.sort_of_atom(&a)
+    }
+
+    // parse a sort without interpreting it, for `define-sort`
+    fn sort_expr(&mut self) -> Result<SortExpr> {
+        self.io.skip_spaces()?;
+        if self.io.get()? == b'(' {
+            self.io.junk();
+            let a = self.atom()?;
+            if &*a == "_" {
+                let n = self.bv_sort_width()?;
+                return Ok(SortExpr::BitVec(n))
+            }
+            let args = self.many_until_paren(|m| m.sort_expr())?;
+            self.expect_char(b')')?;
+            Ok(SortExpr::App(a, args))
+        } else {
+            Ok(SortExpr::Atom(self.atom()?))
+        }
+    }
+
+    // find the sort with this name
+    fn sort_of_atom(&mut self, a: &Atom) -> Result<B::Sort> {
+        let pos = self.io.last_atom;
+        self.app_sort(a, vec!(), pos)
+    }
+
+    // apply the sort `a` to `args`; `pos` is the position of `a`
+    fn app_sort(&mut self, a: &Atom, args: Vec<B::Sort>, pos: Pos) -> Result<B::Sort> {
+        match (&**a, args.len()) {
+            ("Bool", 0) => return Ok(self.build.get_bool()), // builtin
+            ("Real", 0) => return Ok(self.build.get_real()),
+            ("Array", 2) => return Ok(self.build.get_array(args[0].clone(), args[1].clone())),
+            _ => (),
+        }
+        if let Some(def) = self.sort_defs.get(a).cloned() {
+            // expand the definition
+            let (params, body) = &*def;
+            if params.len() != args.len() {
+                let msg = format!("sort {} expects {} arguments, got {}", a, params.len(), args.len());
+                return Err(Error::Arity(msg, self.io.loc_pos(pos)))
+            }
+            let env: Vec<_> = params.iter().cloned().zip(args.into_iter()).collect();
+            return self.expand_sort(body, &env, pos)
+        }
+        match self.sorts.get(a) {
+            Some((s, 0)) if args.len() == 0 => Ok(s.clone()),
+            Some((s, n)) if *n as usize == args.len() => {
+                let s = s.clone();
+                Ok(self.build.app_sort(&s, &args))
+            },
+            Some((_, n)) => {
+                let msg = format!("sort {} expects {} arguments, got {}", a, n, args.len());
+                Err(Error::Arity(msg, self.io.loc_pos(pos)))
+            },
+            None => Err(Error::UnknownSort(a.to_string(), self.io.loc_pos(pos))),
+        }
+    }
+
+    // expand the body of a `define-sort`, with the parameters bound in `env`
+    fn expand_sort(&mut self, e: &SortExpr, env: &[(Atom, B::Sort)], pos: Pos) -> Result<B::Sort> {
+        match e {
+            SortExpr::Atom(a) => match env.iter().find(|(p,_)| p == a) {
+                Some((_, s)) => Ok(s.clone()),
+                None => self.app_sort(a, vec!(), pos),
+            },
+            SortExpr::BitVec(n) => Ok(self.build.get_bv(*n)),
+            SortExpr::App(f, args) => {
+                let mut args2 = Vec::with_capacity(args.len());
+                for u in args.iter() {
+                    args2.push(self.expand_sort(u, env, pos)?);
+                }
+                self.app_sort(f, args2, pos)
+            },
+        }
+    }
+
+    // check that the body of `define-sort` only refers to known sorts
+    fn check_sort_expr(&self, e: &SortExpr, params: &[Atom]) -> Result<()> {
+        let (a, n) = match e {
+            SortExpr::BitVec(_) => return Ok(()),
+            SortExpr::Atom(a) if params.contains(a) => return Ok(()),
+            SortExpr::Atom(a) => (a, 0),
+            SortExpr::App(f, args) => {
+                for u in args.iter() { self.check_sort_expr(u, params)? }
+                (f, args.len())
+            },
+        };
+        let arity = match &**a {
+            "Bool" | "Real" => Some(0),
+            "Array" => Some(2),
+            _ => self.sort_defs.get(a).map(|d| d.0.len())
+                .or_else(|| self.sorts.get(a).map(|(_, n)| *n as usize)),
+        };
+        match arity {
+            Some(n2) if n == n2 => Ok(()),
+            Some(n2) => {
+                let msg = format!("sort {} expects {} arguments, got {}", a, n2, n);
+                Err(Error::Arity(msg, self.io.loc()))
+            },
+            None => Err(Error::UnknownSort(a.to_string(), self.io.loc())),
         }
     }
 
     // find function with this name
     fn find_fun_apply(&mut self, s: &str, args: &[B::Term]) -> Result<B::Term> {
-        match s {
-            "true" => Ok(self.build.app_op(Op::True, args)),
-            "false" => Ok(self.build.app_op(Op::False, args)),
-            "and" => Ok(self.build.app_op(Op::And, args)),
-            "or" => Ok(self.build.app_op(Op::Or, args)),
-            "not" => Ok(self.build.app_op(Op::Not, args)),
-            "=>" => Ok(self.build.app_op(Op::Imply, args)),
-            "=" => Ok(self.build.app_op(Op::Eq, args)),
-            "distinct" => Ok(self.build.app_op(Op::Distinct, args)),
+        let op = match s {
+            "true" => Op::True,
+            "false" => Op::False,
+            "and" => Op::And,
+            "or" => Op::Or,
+            "not" => Op::Not,
+            "=>" => Op::Imply,
+            "=" => Op::Eq,
+            "distinct" => Op::Distinct,
+            "+" => Op::Add,
+            "-" => Op::Sub,
+            "*" => Op::Mul,
+            "/" => Op::Div,
+            "<=" => Op::Leq,
+            "<" => Op::Lt,
+            ">=" => Op::Geq,
+            ">" => Op::Gt,
+            "select" => Op::Select,
+            "store" => Op::Store,
+            "bvadd" => Op::BvAdd,
+            "bvsub" => Op::BvSub,
+            "bvneg" => Op::BvNeg,
+            "bvmul" => Op::BvMul,
+            "bvand" => Op::BvAnd,
+            "bvor" => Op::BvOr,
+            "bvxor" => Op::BvXor,
+            "bvnot" => Op::BvNot,
+            "bvult" => Op::BvUlt,
+            "bvule" => Op::BvUle,
+            "bvugt" => Op::BvUgt,
+            "bvuge" => Op::BvUge,
+            "bvslt" => Op::BvSlt,
+            "bvsle" => Op::BvSle,
+            "bvsgt" => Op::BvSgt,
+            "bvsge" => Op::BvSge,
+            "concat" => Op::Concat,
             _ => {
-                let f =
-                    self.funs.get(s).ok_or_else(|| {
-                    mk_err(format!("{} is not a known function", &s))
-                })?;
-                Ok(self.build.app_fun(f.clone(), args))
+                let f = match self.funs.get(s) {
+                    Some((f, tys)) => {
+                        if tys.len() != args.len() {
+                            let msg = format!("{} expects {} arguments, got {}", s, tys.len(), args.len());
+                            return Err(Error::Arity(msg, self.loc_app(args)))
+                        }
+                        self.check(typing::app(&*self.build, s, tys, args), args)?;
+                        f.clone()
+                    },
+                    None => return Err(Error::UnknownSymbol(s.to_string(), self.loc_app(args))),
+                };
+                return Ok(self.build.app_fun(f, args))
+            }
+        };
+        self.check(typing::op(&*self.build, op, args), args)?;
+        Ok(self.build.app_op(op, args))
+    }
+
+    // the error is about the application, if there is one
+    fn loc_app(&self, args: &[B::Term]) -> Loc {
+        if args.len() > 0 { self.io.loc_closed() } else { self.io.loc_atom() }
+    }
+
+    // turn a sort checking error into an error about the application
+    fn check(&self, r: typing::Result, args: &[B::Term]) -> Result<()> {
+        r.map_err(|msg| Error::Sort(msg, self.loc_app(args)))
+    }
+
+    // declare the sorts of a group of datatypes
+    fn declare_dt_sorts(&mut self, names: &[(Atom, u8)]) -> Result<()> {
+        for (a, n) in names.iter() {
+            if *n != 0 {
+                return self.io.err_with(format!("parametric datatype {} is not supported", a))
+            }
+            self.check_new_sort(a)?;
+            let sort = self.build.declare_sort(a.clone(), 0);
+            self.add_sort(a.clone(), sort, 0);
+        }
+        Ok(())
+    }
+
+    // parse `((c1 (sel1 ty1)…) … (cn …))`
+    fn datatype_body(&mut self) -> Result<Vec<(Atom, Vec<(Atom, B::Sort)>)>> {
+        self.within_parens(|m| {
+            m.expect_char(b'(')?;
+            let c = m.new_symbol()?;
+            if &*c == "par" {
+                return m.io.err_with("parametric datatypes are not supported")
+            }
+            let sels = m.many_until_paren(|m| {
+                m.expect_char(b'(')?;
+                let s = m.new_symbol()?;
+                let ty = m.sort()?;
+                m.io.skip_spaces()?;
+                m.expect_char(b')')?;
+                Ok((s, ty))
+            })?;
+            m.expect_char(b')')?;
+            Ok((c, sels))
+        })
+    }
+
+    // declare constructors, selectors and testers of the datatypes
+    fn declare_datatypes(&mut self, dts: &[Datatype<B::Sort>]) -> Result<()> {
+        for d in dts.iter() {
+            let ty = self.sort_of_atom(&d.name)?;
+            let mut cstors = Vec::with_capacity(d.cstors.len());
+            for (c, sels) in d.cstors.iter() {
+                let args: Vec<_> = sels.iter().map(|(_,s)| s.clone()).collect();
+                let f = self.build.declare_cstor(c.clone(), &args, ty.clone());
+                self.add_fun(c.clone(), f.clone(), args);
+                for (i, (s, s_ty)) in sels.iter().enumerate() {
+                    let sel = self.build.declare_selector(
+                        s.clone(), &f, i as u32, ty.clone(), s_ty.clone());
+                    self.add_fun(s.clone(), sel, vec![ty.clone()]);
+                }
+                let name: Atom = format!("is-{}", c).into();
+                let tester = self.build.declare_tester(name.clone(), &f, ty.clone());
+                self.add_fun(name, tester, vec![ty.clone()]);
+                cstors.push(f);
             }
+            self.build.declare_datatype(ty, &cstors);
         }
+        Ok(())
     }
 
     // parse one `(var term)` pair
@@ -301,6 +845,7 @@ impl<'a, R : io::Read, B : TermBuilder> ParserState<'a, R, B> {
 
         for sc in scope.iter() {
             self.vars.insert(sc.name.clone(), sc.var.clone());
+            self.bound.push((sc.name.clone(), sc.old_v.clone()));
         }
 
         // now parse the body
@@ -308,15 +853,7 @@ impl<'a, R : io::Read, B : TermBuilder> ParserState<'a, R, B> {
         self.expect_char(b')')?;
 
         // exit scope
-        for sc in scope.iter() {
-            if let Some(old_v) = & sc.old_v {
-                // restore old shadowed binding
-                self.vars.insert(sc.name.clone(), old_v.clone());
-            } else {
-                // remove binding
-                self.vars.remove(&sc.name);
-            }
-        }
+        self.exit_scope(scope.len());
 
         // simplified scope, to be given to the builder
         let bs: Vec<_> = scope.into_iter().map(|b| (b.var, b.t)).collect();
@@ -325,53 +862,199 @@ impl<'a, R : io::Read, B : TermBuilder> ParserState<'a, R, B> {
         Ok(t)
     }
 
+    // remove the `n` innermost bindings of `vars`, restoring what they shadowed
+    fn exit_scope(&mut self, n: usize) {
+        for _ in 0 .. n {
+            let (name, old_v) = self.bound.pop().expect("no binding in scope");
+            match old_v {
+                Some(v) => { self.vars.insert(name, v); },
+                None => { self.vars.remove(&name); },
+            }
+        }
+    }
+
+    // parse `((x1 s1) … (xn sn))`
+    fn sorted_vars(&mut self) -> Result<Vec<(Atom, B::Sort)>> {
+        self.within_parens(|m| {
+            m.io.skip_spaces()?;
+            m.expect_char(b'(')?;
+            let v = m.atom()?;
+            let ty = m.sort()?;
+            m.io.skip_spaces()?;
+            m.expect_char(b')')?;
+            Ok((v, ty))
+        })
+    }
+
+    // parse `((x1 s1) … (xn sn)) body)` after `forall` or `exists`
+    fn parse_quant(&mut self, q: Quant) -> Result<B::Term> {
+        let vs = self.sorted_vars()?;
+
+        // enter local scope
+        let mut vars = Vec::with_capacity(vs.len());
+        for (name, ty) in vs.into_iter() {
+            let v = self.build.bound_var(name.clone(), ty);
+            let old_v = self.vars.insert(name.clone(), v.clone());
+            self.bound.push((name, old_v));
+            vars.push(v);
+        }
+
+        // parse the body, collecting its patterns
+        let outer_patterns = std::mem::replace(&mut self.patterns, vec!());
+        let body = self.term()?;
+        self.io.skip_spaces()?;
+        self.expect_char(b')')?;
+        let r = typing::expect_bool(&*self.build, "body of quantifier", &body);
+        r.map_err(|msg| Error::Sort(msg, self.io.loc_closed()))?;
+        let patterns = std::mem::replace(&mut self.patterns, outer_patterns);
+
+        // exit scope
+        self.exit_scope(vars.len());
+
+        Ok(self.build.quant(q, &vars, body, &patterns))
+    }
+
     // parse a term
     fn term(&mut self) -> Result<B::Term> {
+        let (t, _) = self.term_named()?;
+        Ok(t)
+    }
+
+    // parse a term, returning its name if it is `(! t :named name)`
+    fn term_named(&mut self) -> Result<(B::Term, Option<Atom>)> {
         self.io.skip_spaces()?;
         match self.io.get()? {
             b'(' => {
                 self.io.junk();
+                self.io.skip_spaces()?;
+                if self.io.get()? == b'(' {
+                    // indexed identifier: `((_ is c) t)` is the tester `is-c`
+                    self.io.junk();
+                    let u = self.atom()?;
+                    let f = self.atom()?;
+                    if &*u != "_" {
+                        return self.io.err_with(format!("expected indexed identifier, got ({} …)", u))
+                    }
+                    let t = match &*f {
+                        "is" => {
+                            let c = self.atom()?;
+                            self.io.skip_spaces()?;
+                            self.expect_char(b')')?;
+                            let args = self.terms()?;
+                            self.find_fun_apply(&format!("is-{}", c), &args)?
+                        },
+                        "extract" => {
+                            let hi = self.index()?;
+                            let lo = self.index()?;
+                            self.io.skip_spaces()?;
+                            self.expect_char(b')')?;
+                            if hi < lo {
+                                return self.io.err_with(format!("invalid (_ extract {} {})", hi, lo))
+                            }
+                            let args = self.terms()?;
+                            let op = Op::Extract(hi, lo);
+                            self.check(typing::op(&*self.build, op, &args), &args)?;
+                            self.build.app_op(op, &args)
+                        },
+                        _ => {
+                            return self.io.err_with(format!("unknown indexed identifier (_ {} …)", f))
+                        },
+                    };
+                    self.expect_char(b')')?;
+                    return Ok((t, None))
+                }
                 let a = self.atom()?;
                 match &*a {
                     "!" => {
                         let t = self.term()?;
-                        let a = self.atom()?;
-                        if *a == *":named" {
-                            let _ = self.atom()?;
-                            self.expect_char(b')')?;
-                            Ok(t)
-                        } else {
-                            self.io.err_with(format!("expected ':named atom', got '{}'", a))
+                        let mut name = None;
+                        loop {
+                            self.io.skip_spaces()?;
+                            if self.io.get()? == b')' { break }
+                            let a = self.atom()?;
+                            match &*a {
+                                ":named" => {
+                                    let n = self.atom()?;
+                                    // the name can be used to refer to `t` from now on
+                                    let v = self.build.bind(n.clone(), t.clone());
+                                    self.vars.insert(n.clone(), v);
+                                    name = Some(n);
+                                },
+                                ":pattern" => {
+                                    // trigger for the enclosing quantifier
+                                    self.io.skip_spaces()?;
+                                    self.expect_char(b'(')?;
+                                    let p = self.terms()?;
+                                    self.expect_char(b')')?;
+                                    self.patterns.push(p);
+                                },
+                                _ => {
+                                    return self.io.err_with(format!("expected ':named' or ':pattern', got '{}'", a))
+                                },
+                            }
                         }
+                        self.expect_char(b')')?;
+                        Ok((t, name))
                     },
                     "ite" => {
                         let t1 = self.term()?;
                         let t2 = self.term()?;
                         let t3 = self.term()?;
                         self.expect_char(b')')?;
-                        Ok(self.build.ite(t1,t2,t3))
+                        let r = typing::ite(&*self.build, &t1, &t2, &t3);
+                        r.map_err(|msg| Error::Sort(msg, self.io.loc_closed()))?;
+                        Ok((self.build.ite(t1,t2,t3), None))
                     },
                     "let" => {
-                        self.parse_let()
+                        let t = self.parse_let()?;
+                        Ok((t, None))
+                    },
+                    "forall" => Ok((self.parse_quant(Quant::Forall)?, None)),
+                    "exists" => Ok((self.parse_quant(Quant::Exists)?, None)),
+                    "_" => {
+                        // `(_ bvN w)` is the bit-vector constant `N` of width `w`
+                        let v = self.atom()?;
+                        let w = self.index()?;
+                        if w == 0 {
+                            return self.io.err_with("bit-vectors must have a positive width")
+                        }
+                        self.io.skip_spaces()?;
+                        self.expect_char(b')')?;
+                        let n = if v.starts_with("bv") { v[2..].parse::<u64>().ok() } else { None };
+                        let n = match n {
+                            Some(n) => n,
+                            None => return self.io.err_with(format!("unknown indexed constant (_ {} {})", v, w)),
+                        };
+                        let bits: Vec<bool> = (0 .. w).map(|i| i < 64 && (n >> i) & 1 == 1).collect();
+                        Ok((self.build.bv_const(&bits), None))
                     },
                     _ => {
                         // function application
                         let args = self.terms()?;
                         self.expect_char(b')')?;
-                        self.find_fun_apply(&a, &args)
+                        let t = self.find_fun_apply(&a, &args)?;
+                        Ok((t, None))
                     }
                 }
             },
             _ => {
                 let a = self.atom()?;
-                match self.vars.get(&a) {
+                if a.len() > 0 && a.as_bytes()[0].is_ascii_digit() {
+                    return Ok((self.build.numeral(a), None))
+                }
+                if a.starts_with("#") {
+                    let bits = self.bv_literal(&a)?;
+                    return Ok((self.build.bv_const(&bits), None))
+                }
+                let t = match self.vars.get(&a) {
                     Some(v) => {
-                        Ok(self.build.var(v.clone())) // term from bound var
+                        self.build.var(v.clone()) // term from bound var
                     },
                     None => {
-                        self.find_fun_apply(&a, &[])
+                        self.find_fun_apply(&a, &[])?
                     }
-                }
+                };
+                Ok((t, None))
             }
         }
     }
@@ -384,6 +1067,7 @@ impl<'a, R : io::Read, B : TermBuilder> ParserState<'a, R, B> {
     // entry point for a toplevel statement, or None (for EOF)
     fn statement(&mut self) -> Result<Option<Statement<B::Term, B::Sort>>> {
         self.io.skip_spaces()?;
+        self.io.start_statement();
 
         if self.io.eof {
             Ok(None)
@@ -400,16 +1084,59 @@ impl<'a, R : io::Read, B : TermBuilder> ParserState<'a, R, B> {
                     let a = self.atom()?;
                     Statement::SetLogic(a)
                 },
-                "declare-sort" => {
+                "set-option" => {
                     let a = self.atom()?;
-                    let n = self.atom()?.parse::<u8>()?;
+                    let b = self.atom()?;
+                    Statement::SetOption(a,b)
+                },
+                "declare-sort" => {
+                    let a = self.new_sort_name()?;
+                    let n = self.arity()?;
                     // make a sort and store it
                     let sort = self.build.declare_sort(a.clone(), n);
-                    self.sorts.insert(a.clone(), sort);
+                    self.add_sort(a.clone(), sort, n);
                     Statement::DeclareSort(a, n)
                 },
+                "define-sort" => {
+                    let a = self.new_sort_name()?;
+                    let params = self.within_parens(|m| m.atom())?;
+                    let body = self.sort_expr()?;
+                    self.check_sort_expr(&body, &params)?;
+                    self.add_sort_def(a.clone(), Rc::new((params.clone(), body.clone())));
+                    Statement::DefineSort(a, params, body)
+                },
+                "declare-const" => {
+                    let a = self.new_symbol()?;
+                    let ret = self.sort()?;
+                    let f = self.build.declare_fun(a.clone(), &[], ret.clone());
+                    self.add_fun(a.clone(), f, vec!());
+                    Statement::DeclareFun(a, vec!(), ret)
+                },
+                "define-fun" => {
+                    let a = self.new_symbol()?;
+                    let vs = self.sorted_vars()?;
+                    let ret = self.sort()?;
+
+                    // parse the body with the parameters in scope
+                    let mut vars = Vec::with_capacity(vs.len());
+                    for (name, ty) in vs.iter() {
+                        let v = self.build.bound_var(name.clone(), ty.clone());
+                        let old_v = self.vars.insert(name.clone(), v.clone());
+                        self.bound.push((name.clone(), old_v));
+                        vars.push(v);
+                    }
+                    let body = self.term()?;
+                    self.exit_scope(vars.len());
+                    let r = typing::expect(&*self.build, || format!("body of {}", a), &body, &ret);
+                    r.map_err(|msg| Error::Sort(msg, self.io.loc()))?;
+
+                    let f = self.build.define_fun(a.clone(), &vars, ret.clone(), body.clone());
+                    let tys = vs.iter().map(|(_,ty)| ty.clone()).collect();
+                    self.add_fun(a.clone(), f, tys);
+                    Statement::DefineFun(a, vs, ret, body)
+                },
                 "declare-fun" | "declare-cstor" => {
-                    let a = self.atom()?;
+                    let a = self.new_symbol()?;
                     let tys = self.within_parens(|m| m.sort())?;
                     let ret = self.sort()?;
                     // store function
@@ -422,18 +1149,92 @@ impl<'a, R : io::Read, B : TermBuilder> ParserState<'a, R, B> {
                             self.build.declare_cstor(a, &tys, ret)
                         }
                     };
-                    self.funs.insert(a.clone(), f);
+                    self.add_fun(a.clone(), f, tys.clone());
                     Statement::DeclareFun(a, tys, ret)
                 },
+                "declare-datatype" => {
+                    let a = self.atom()?;
+                    self.declare_dt_sorts(&[(a.clone(), 0)])?;
+                    let cstors = self.datatype_body()?;
+                    let dts = vec![Datatype{name: a, cstors}];
+                    self.declare_datatypes(&dts)?;
+                    Statement::DeclareDatatypes(dts)
+                },
+                "declare-datatypes" => {
+                    let names = self.within_parens(|m| {
+                        m.expect_char(b'(')?;
+                        let a = m.atom()?;
+                        let n = m.arity()?;
+                        m.io.skip_spaces()?;
+                        m.expect_char(b')')?;
+                        Ok((a,n))
+                    })?;
+                    // declare sorts first, constructors may refer to them
+                    self.declare_dt_sorts(&names)?;
+                    let bodies = self.within_parens(|m| m.datatype_body())?;
+                    if bodies.len() != names.len() {
+                        let msg = format!(
+                            "expected {} datatype definitions, got {}", names.len(), bodies.len());
+                        return Err(Error::Arity(msg, self.io.loc()))
+                    }
+                    let dts: Vec<_> =
+                        names.into_iter().zip(bodies.into_iter())
+                        .map(|((name,_), cstors)| Datatype{name, cstors})
+                        .collect();
+                    self.declare_datatypes(&dts)?;
+                    Statement::DeclareDatatypes(dts)
+                },
                 "assert" => {
-                    let t = self.term()?;
-                    Statement::Assert(t)
+                    let (t, name) = self.term_named()?;
+                    let r = typing::expect_bool(&*self.build, "assertion", &t);
+                    r.map_err(|msg| Error::Sort(msg, self.io.loc()))?;
+                    match (t, name) {
+                        (t, None) => Statement::Assert(t),
+                        (t, Some(name)) => Statement::AssertNamed(name, t),
+                    }
                 },
                 "check-sat" => Statement::CheckSat,
                 "check-sat-assumptions" => {
                     let v = self.terms()?;
+                    for t in v.iter() {
+                        let r = typing::expect_bool(&*self.build, "assumption", t);
+                        r.map_err(|msg| Error::Sort(msg, self.io.loc()))?;
+                    }
                     Statement::CheckSatAssumptions(v)
                 },
+                "get-model" => Statement::GetModel,
+                "get-unsat-core" => Statement::GetUnsatCore,
+                "get-proof" => Statement::GetProof,
+                "push" => {
+                    let n = self.opt_numeral()?;
+                    self.push_scopes(n);
+                    Statement::Push(n)
+                },
+                "pop" => {
+                    let n = self.opt_numeral()?;
+                    self.pop_scopes(n);
+                    Statement::Pop(n)
+                },
+                "get-value" => {
+                    let v = self.within_parens(|m| m.term())?;
+                    Statement::GetValue(v)
+                },
+                "get-info" => Statement::GetInfo(self.atom()?),
+                "get-option" => Statement::GetOption(self.atom()?),
+                "echo" => {
+                    let a = self.atom()?;
+                    if ! a.starts_with('"') {
+                        self.io.err_with(format!("echo expects a string literal, got {}", a))?
+                    }
+                    Statement::Echo(a)
+                },
+                "reset" => Statement::Reset,
+                "reset-assertions" => {
+                    // declarations made inside scopes are removed, too
+                    let n = self.scopes.len() as u32;
+                    self.pop_scopes(n);
+                    Statement::ResetAssertions
+                },
                 "exit" => Statement::Exit,
                 _ => {
                     self.io.err_with(format!("unknown directive {:?}", dir))?
@@ -445,20 +1246,96 @@ impl<'a, R : io::Read, B : TermBuilder> ParserState<'a, R, B> {
         }
     }
 
-    // parse a bunch of statements until the EOF
-    fn statements(&mut self) -> Result<Vec<Statement<B::Term, B::Sort>>> {
-        let mut res = Vec::new();
+    // after an error, skip the rest of the current statement
+    fn recover(&mut self) -> Result<()> {
+        let n = self.bound.len();
+        self.exit_scope(n);
+        self.patterns.clear();
+        self.io.skip_statement()
+    }
+}
 
-        loop {
-            match self.statement() {
-                Ok(None) => break,
-                Ok(Some(st)) => {
-                    res.push(st);
-                },
-                Err(e) => return Err(e),
-            }
+/// A stream of statements, parsed one at a time from a reader.
+///
+/// Statements are only read from `r` when they are asked for, so memory
+/// stays bounded on large inputs, and this is suitable for interactive use.
+///
+/// After an error, the rest of the faulty statement is skipped and parsing
+/// resumes with the next statement, unless the error is not recoverable
+/// (see `Error::is_recoverable`).
+pub struct StatementStream<'a, R : io::Read, B : TermBuilder + 'a> {
+    st: ParserState<'a, R, B>,
+    done: bool,
+}
+
+impl<'a, R : io::Read, B : TermBuilder> StatementStream<'a, R, B> {
+    /// New stream reading from `r`, allocating terms with `b`.
+    pub fn new(b: &'a mut B, r: R) -> Self {
+        StatementStream{st: ParserState::new(b, r), done: false}
+    }
+
+    /// Set the name of the input file, used in error messages.
+    pub fn set_file(&mut self, file: &str) {
+        self.st.io.file = Some(file.to_string());
+    }
+
+    /// Parse the next statement, or return `None` at the end of the input.
+    pub fn next_statement(&mut self) -> Result<Option<Statement<B::Term, B::Sort>>> {
+        if self.done { return Ok(None) }
+        match self.st.statement() {
+            Ok(None) => {
+                self.done = true;
+                Ok(None)
+            },
+            Ok(Some(st)) => Ok(Some(st)),
+            Err(e) => {
+                if ! e.is_recoverable() || self.st.recover().is_err() {
+                    self.done = true;
+                }
+                Err(e)
+            },
         }
-        Ok(res)
+    }
+
+    /// Current position in the input.
+    pub fn pos(&self) -> Pos { self.st.io.pos() }
+
+    /// Access the term builder.
+    pub fn builder(&mut self) -> &mut B { self.st.build }
+
+    /// Forget all declared sorts and symbols, as required by `(reset)`.
+    ///
+    /// The builder is not reset, this is up to the caller.
+    pub fn reset(&mut self) {
+        let st = &mut self.st;
+        st.sorts.clear();
+        st.sort_defs.clear();
+        st.funs.clear();
+        st.vars.clear();
+        st.bound.clear();
+        st.patterns.clear();
+        st.undo.clear();
+        st.scopes.clear();
+    }
+}
+
+impl<'a, R : io::Read, B : TermBuilder> Iterator for StatementStream<'a, R, B> {
+    type Item = Result<Statement<B::Term, B::Sort>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_statement() {
+            Ok(None) => None,
+            Ok(Some(st)) => Some(Ok(st)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+// restore `m[a]` to `old`, on `pop`
+fn restore<V>(m: &mut FxHashMap<Atom, V>, a: Atom, old: Option<V>) {
+    match old {
+        Some(v) => { m.insert(a, v); },
+        None => { m.remove(&a); },
     }
 }
 
@@ -466,8 +1343,7 @@ impl<'a, R : io::Read, B : TermBuilder> ParserState<'a, R, B> {
 pub fn parse<R,B>(b: &mut B, r: R) -> Result<Vec<Statement<B::Term, B::Sort>>>
     where R : io::Read, B: TermBuilder
 {
-    let mut p = ParserState::new(b, r);
-    p.statements()
+    StatementStream::new(b, r).collect()
 }
 
 /// Parse from given string
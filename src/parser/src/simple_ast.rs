@@ -2,8 +2,8 @@
 //! Simple representation of terms, sorts, etc.
 
 use {
-    std::{ops::Deref,rc::Rc, fmt, ptr},
-    crate::types::{self,Op,Atom},
+    std::{ops::Deref,rc::Rc, fmt, collections::HashMap},
+    crate::types::{self,Op,Atom,Quant},
     batsmt_pretty as pp,
 };
 
@@ -14,6 +14,8 @@ pub use batsmt_pretty::Pretty;
 struct SortCell {
     name: Atom,
     arity: u8,
+    args: Vec<Sort>, // for parametric sorts
+    width: Option<u32>, // for bit-vectors
 }
 
 /// A sort
@@ -29,8 +31,22 @@ impl fmt::Debug for Sort {
 impl Sort {
     /// New sort
     fn new(name: Atom, arity: u8) -> Self {
-        Sort(Rc::new(SortCell{name, arity}))
+        Sort(Rc::new(SortCell{name, arity, args: vec!(), width: None}))
     }
+
+    /// Apply a sort constructor to arguments
+    fn app(name: Atom, args: Vec<Sort>) -> Self {
+        Sort(Rc::new(SortCell{name, arity: 0, args, width: None}))
+    }
+
+    /// The sort `(_ BitVec width)`
+    fn bv(width: u32) -> Self {
+        let name = format!("(_ BitVec {})", width).into();
+        Sort(Rc::new(SortCell{name, arity: 0, args: vec!(), width: Some(width)}))
+    }
+
+    /// Width of this sort, if it's a bit-vector sort.
+    pub fn bv_width(&self) -> Option<u32> { self.0.width }
 }
 
 #[derive(Eq,PartialEq,Hash)]
@@ -67,6 +83,7 @@ impl fmt::Debug for Fun {
 pub enum TermCell {
     App(Fun, Vec<Term>),
     Ite(Term,Term,Term),
+    Quant(Quant, Vec<Term>, Term), // bound variables, body
 }
 
 /// A term
@@ -76,7 +93,7 @@ pub struct Term(Rc<TermCell>);
 impl Eq for Term {}
 impl PartialEq for Term {
     fn eq(&self, other: &Term) -> bool {
-        ptr::eq(&self.0, &other.0)
+        Rc::ptr_eq(&self.0, &other.0)
     }
 }
 
@@ -88,6 +105,11 @@ impl fmt::Debug for Term {
                 fmt.debug_list().entries(&[a,b,c]).finish()?;
                 write!(fmt, ")")
             },
+            TermCell::Quant(q, vars, body) => {
+                write!(fmt, "({:?} ", q)?;
+                fmt.debug_list().entries(vars.iter()).finish()?;
+                write!(fmt, " {:?})", body)
+            },
             TermCell::App(f, args) => {
                 if args.len () == 0 {
                     f.fmt(fmt)
@@ -112,11 +134,38 @@ impl Term {
     pub fn ite(a: Term, b: Term, c: Term) -> Self {
         Term(Rc::new(TermCell::Ite(a,b,c)))
     }
+
+    /// Replace the terms `v` by `u`, for each `(v,u)` in `m`.
+    pub fn subst(&self, m: &[(Term, Term)]) -> Term {
+        if let Some((_, u)) = m.iter().find(|(v,_)| v == self) {
+            return u.clone()
+        }
+        match self.0.deref() {
+            TermCell::App(f, args) => {
+                let args = args.iter().map(|u| u.subst(m)).collect();
+                Term::app(f.clone(), args)
+            },
+            TermCell::Ite(a, b, c) => Term::ite(a.subst(m), b.subst(m), c.subst(m)),
+            TermCell::Quant(q, vars, body) => {
+                Term(Rc::new(TermCell::Quant(*q, vars.clone(), body.subst(m))))
+            },
+        }
+    }
+
+    /// The sort of this term.
+    pub fn sort(&self) -> Sort {
+        match self.0.deref() {
+            TermCell::App(f, _) => f.ret(),
+            TermCell::Ite(_, b, _) => b.sort(),
+            TermCell::Quant(_, _, body) => body.sort(),
+        }
+    }
 }
 
 /// The builder used for holding context and parsing
 pub struct Builder {
     bool_: Sort,
+    real: Sort,
     true_ : Fun,
     false_ : Fun,
     and_ : Fun,
@@ -125,14 +174,25 @@ pub struct Builder {
     imply_ : Fun,
     eq : Fun,
     not_ : Fun,
+    add : Fun,
+    sub : Fun,
+    mul : Fun,
+    div : Fun,
+    leq : Fun,
+    lt : Fun,
+    geq : Fun,
+    gt : Fun,
+    defs: HashMap<Fun, (Vec<Term>, Term)>, // from `define-fun`
 }
 
 impl Builder {
     /// New builder
     pub fn new() -> Self {
         let b = Sort::new("Bool".into(), 0);
+        let r = Sort::new("Real".into(), 0);
         Builder {
             bool_: b.clone(),
+            real: r.clone(),
             true_: Fun::new("true".into(), None, b.clone()),
             false_: Fun::new("false".into(), None, b.clone()),
             and_: Fun::new("and".into(), None, b.clone()),
@@ -141,6 +201,15 @@ impl Builder {
             eq: Fun::new("=".into(), None, b.clone()),
             distinct: Fun::new("distinct".into(), None, b.clone()),
             not_: Fun::new("not".into(), Some(vec![b.clone()]), b.clone()),
+            add: Fun::new("+".into(), None, r.clone()),
+            sub: Fun::new("-".into(), None, r.clone()),
+            mul: Fun::new("*".into(), None, r.clone()),
+            div: Fun::new("/".into(), None, r.clone()),
+            leq: Fun::new("<=".into(), None, b.clone()),
+            lt: Fun::new("<".into(), None, b.clone()),
+            geq: Fun::new(">=".into(), None, b.clone()),
+            gt: Fun::new(">".into(), None, b.clone()),
+            defs: HashMap::new(),
         }
     }
 }
@@ -148,9 +217,33 @@ impl Builder {
 impl types::SortBuilder for Builder {
     type Sort = Sort;
     fn get_bool(&self) -> Sort { self.bool_.clone() }
+    fn get_real(&self) -> Sort { self.real.clone() }
+    fn get_array(&mut self, idx: Sort, val: Sort) -> Sort {
+        Sort::app("Array".into(), vec![idx, val])
+    }
+    fn get_bv(&mut self, width: u32) -> Sort { Sort::bv(width) }
     fn declare_sort(&mut self, s: Atom, n: u8) -> Sort {
         Sort::new(s,n)
     }
+    fn app_sort(&mut self, s: &Sort, args: &[Sort]) -> Sort {
+        Sort::app(s.0.name.clone(), args.to_vec())
+    }
+    fn view_sort(&self, s: &Sort) -> types::SortView<Sort> {
+        if *s == self.bool_ { types::SortView::Bool }
+        else if *s == self.real { types::SortView::Real }
+        else if let Some(w) = s.bv_width() { types::SortView::BitVec(w) }
+        else if &*s.0.name == "Array" && s.0.args.len() == 2 {
+            types::SortView::Array(s.0.args[0].clone(), s.0.args[1].clone())
+        } else { types::SortView::Other }
+    }
+    fn pp_sort(&self, s: &Sort) -> String {
+        if s.0.args.len() == 0 {
+            s.0.name.to_string()
+        } else {
+            let args: Vec<_> = s.0.args.iter().map(|s| self.pp_sort(s)).collect();
+            format!("({} {})", s.0.name, args.join(" "))
+        }
+    }
 }
 
 impl types::TermBuilder for Builder {
@@ -168,27 +261,107 @@ impl types::TermBuilder for Builder {
             Op::Eq => self.eq.clone(),
             Op::Not => self.not_.clone(),
             Op::Distinct => self.distinct.clone(),
+            Op::Add => self.add.clone(),
+            Op::Sub => self.sub.clone(),
+            Op::Mul => self.mul.clone(),
+            Op::Div => self.div.clone(),
+            Op::Leq => self.leq.clone(),
+            Op::Lt => self.lt.clone(),
+            Op::Geq => self.geq.clone(),
+            Op::Gt => self.gt.clone(),
+            Op::Select | Op::Store => {
+                // the sort depends on the array
+                let s = match args.get(0) {
+                    Some(a) if a.sort().0.args.len() == 2 => a.sort(),
+                    _ => panic!("{:?} expects an array as first argument", op),
+                };
+                let ret = match op {
+                    Op::Select => s.0.args[1].clone(),
+                    _ => s,
+                };
+                let name = if let Op::Select = op { "select" } else { "store" };
+                Fun::new(name.into(), None, ret)
+            },
+            Op::BvUlt | Op::BvUle | Op::BvUgt | Op::BvUge |
+            Op::BvSlt | Op::BvSle | Op::BvSgt | Op::BvSge => {
+                let name = format!("{:?}", op).to_lowercase();
+                Fun::new(name.into(), None, self.bool_.clone())
+            },
+            Op::Concat => {
+                let w = args.iter().map(|t| t.sort().bv_width().unwrap_or(0)).sum();
+                Fun::new("concat".into(), None, Sort::bv(w))
+            },
+            Op::Extract(hi, lo) => {
+                let name = format!("(_ extract {} {})", hi, lo);
+                Fun::new(name.into(), None, Sort::bv(hi - lo + 1))
+            },
+            Op::BvAdd | Op::BvSub | Op::BvNeg | Op::BvMul |
+            Op::BvAnd | Op::BvOr | Op::BvXor | Op::BvNot => {
+                // same sort as the arguments
+                let s = match args.get(0) {
+                    Some(a) => a.sort(),
+                    None => panic!("{:?} expects arguments", op),
+                };
+                let name = format!("{:?}", op).to_lowercase();
+                Fun::new(name.into(), None, s)
+            },
         };
         self.app_fun(f, args)
     }
 
+    fn numeral(&mut self, n: Atom) -> Term {
+        let f = Fun::new(n, None, self.real.clone());
+        Term::app(f, vec!())
+    }
+
+    fn bv_const(&mut self, bits: &[bool]) -> Term {
+        let mut name = String::from("#b");
+        for b in bits.iter().rev() { name.push(if *b { '1' } else { '0' }) }
+        let f = Fun::new(name.into(), None, Sort::bv(bits.len() as u32));
+        Term::app(f, vec!())
+    }
+
     fn declare_fun(&mut self, name: Atom, args: &[Sort], ret: Sort) -> Fun {
         let args = Some(args.iter().map(|s| s.clone()).collect());
         Fun::new(name, args, ret)
     }
 
+    fn define_fun(&mut self, name: Atom, vars: &[Term], ret: Sort, body: Term) -> Fun {
+        let args = Some(vars.iter().map(|v| v.sort()).collect());
+        let f = Fun::new(name, args, ret);
+        self.defs.insert(f.clone(), (vars.to_vec(), body));
+        f
+    }
+
     fn declare_cstor(&mut self, name: Atom, args: &[Sort], ret: Sort) -> Fun {
         let args = Some(args.iter().map(|s| s.clone()).collect());
         Fun::new_cstor(name, args, ret)
     }
 
+    fn declare_selector(&mut self, name: Atom, _c: &Fun, _idx: u32, ty: Sort, ret: Sort) -> Fun {
+        Fun::new(name, Some(vec![ty]), ret)
+    }
+
+    fn declare_tester(&mut self, name: Atom, _c: &Fun, ty: Sort) -> Fun {
+        Fun::new(name, Some(vec![ty]), self.bool_.clone())
+    }
+
+    fn declare_datatype(&mut self, _ty: Sort, _cstors: &[Fun]) {}
+
     fn var(&mut self, v: Self::Var) -> Term { v }
 
+    fn sort_of(&self, t: &Term) -> Sort { t.sort() }
+
     fn ite(&mut self, a: Term, b: Term, c: Term) -> Term {
         Term::ite(a,b,c)
     }
 
     fn app_fun(&mut self, f: Fun, args: &[Term]) -> Term {
+        if let Some((vars, body)) = self.defs.get(&f) {
+            // expand the macro
+            let m: Vec<_> = vars.iter().cloned().zip(args.iter().cloned()).collect();
+            return body.subst(&m)
+        }
         Term::app_ref(f, args)
     }
 
@@ -196,11 +369,26 @@ impl types::TermBuilder for Builder {
 
     // ignore bindings, they've been expanded already
     fn let_(&mut self, _bs: &[(Self::Var,Term)], body: Self::Term) -> Self::Term { body }
+
+    fn bound_var(&mut self, name: Atom, ty: Sort) -> Self::Var {
+        Term::app(Fun::new(name, None, ty), vec!())
+    }
+
+    // patterns are dropped
+    fn quant(&mut self, q: Quant, vars: &[Term], body: Term, _pats: &[Vec<Term>]) -> Term {
+        Term(Rc::new(TermCell::Quant(q, vars.to_vec(), body)))
+    }
 }
 
 impl pp::Pretty for Sort {
     fn pp_into(&self, ctx: &mut pp::Ctx) {
-        ctx.string(self.0.name.to_string());
+        if self.0.args.len() == 0 {
+            ctx.string(self.0.name.to_string());
+        } else {
+            ctx.sexp(|ctx| {
+                ctx.string(self.0.name.to_string()).space().array(pp::space(), &self.0.args);
+            });
+        }
     }
 }
 
@@ -218,6 +406,18 @@ impl pp::Pretty for Term {
                     ctx.str("ite ").array(pp::space(), &[a,b,c]);
                 });
             },
+            TermCell::Quant(q, vars, body) => {
+                ctx.sexp(|ctx| {
+                    ctx.str(if *q == Quant::Forall { "forall" } else { "exists" }).space();
+                    ctx.sexp(|ctx| {
+                        for (i,v) in vars.iter().enumerate() {
+                            if i > 0 { ctx.space(); }
+                            ctx.sexp(|ctx| { ctx.pp(v).space().pp(&v.sort()); });
+                        }
+                    });
+                    ctx.space().pp(body);
+                });
+            },
             TermCell::App(f, args) => {
                 if args.len() == 0 {
                     f.pp_into(ctx);
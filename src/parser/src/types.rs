@@ -9,17 +9,61 @@ pub use self::pp::Pretty;
 pub type Atom = Rc<str>;
 
 pub trait SortBuilder {
-    type Sort : Clone + Debug;
+    type Sort : Clone + Debug + Eq;
 
     fn get_bool(&self) -> Self::Sort;
 
+    /// The builtin sort of real numbers
+    fn get_real(&self) -> Self::Sort;
+
+    /// The builtin sort of arrays, `(Array idx val)`
+    fn get_array(&mut self, idx: Self::Sort, val: Self::Sort) -> Self::Sort;
+
+    /// The builtin sort of bit-vectors, `(_ BitVec width)`
+    fn get_bv(&mut self, width: u32) -> Self::Sort;
+
     /// Declare a sort of the given arity
     fn declare_sort(&mut self, name: Atom, arity: u8) -> Self::Sort;
+
+    /// Apply a sort `s` of arity `args.len()`, declared by `declare_sort`,
+    /// to arguments.
+    fn app_sort(&mut self, s: &Self::Sort, args: &[Self::Sort]) -> Self::Sort;
+
+    /// Inspect a sort, for sort checking.
+    fn view_sort(&self, s: &Self::Sort) -> SortView<Self::Sort>;
+
+    /// Print a sort, for error messages.
+    fn pp_sort(&self, s: &Self::Sort) -> String { format!("{:?}", s) }
+}
+
+/// The builtin sorts, as seen by sort checking.
+#[derive(Clone,Debug)]
+pub enum SortView<Sort> {
+    Bool,
+    Real,
+    Array(Sort, Sort),
+    BitVec(u32),
+    Other, // declared sorts and datatypes
 }
 
 /// The builtins recognized by the parser
 #[derive(Copy,Debug,Clone)]
-pub enum Op { True, False, Or, And, Imply, Eq, Not, Distinct }
+pub enum Op {
+    True, False, Or, And, Imply, Eq, Not, Distinct,
+    Add, Sub, Mul, Div, Leq, Lt, Geq, Gt,
+    Select, Store,
+    BvAdd, BvSub, BvNeg, BvMul, BvAnd, BvOr, BvXor, BvNot,
+    BvUlt, BvUle, BvUgt, BvUge, BvSlt, BvSle, BvSgt, BvSge,
+    Concat,
+    Extract(u32, u32), // `(_ extract hi lo)`
+}
+
+/// A quantifier
+#[derive(Debug,Clone,Copy,Eq,PartialEq)]
+pub enum Quant {
+    Forall,
+    Exists,
+}
 
 pub trait TermBuilder : SortBuilder {
     type Fun : Clone + Debug;
@@ -29,18 +73,44 @@ pub trait TermBuilder : SortBuilder {
     /// Term from a bound variable
     fn var(&mut self, v: Self::Var) -> Self::Term;
 
+    /// The sort of a term built by this builder.
+    fn sort_of(&self, t: &Self::Term) -> Self::Sort;
+
     /// Declare a function
     fn declare_fun(&mut self, name: Atom, args: &[Self::Sort], ret: Self::Sort) -> Self::Fun;
 
+    /// Define `name` as a macro: applying it to arguments yields `body`
+    /// where the variables `vars` (built by `bound_var`) are replaced
+    /// by the arguments.
+    fn define_fun(&mut self, name: Atom, vars: &[Self::Var], ret: Self::Sort, body: Self::Term) -> Self::Fun;
+
     /// Declare a constructor
     fn declare_cstor(&mut self, name: Atom, args: &[Self::Sort], ret: Self::Sort) -> Self::Fun;
 
+    /// Declare the selector for the `idx`-th argument of the constructor `c`,
+    /// from the datatype `ty` to `ret`.
+    fn declare_selector(&mut self, name: Atom, c: &Self::Fun, idx: u32, ty: Self::Sort, ret: Self::Sort) -> Self::Fun;
+
+    /// Declare the tester `is-c` for the constructor `c` of the datatype `ty`.
+    fn declare_tester(&mut self, name: Atom, c: &Self::Fun, ty: Self::Sort) -> Self::Fun;
+
+    /// Declare that `cstors` are all the constructors of the datatype `ty`.
+    ///
+    /// Called once the constructors, selectors and testers are declared.
+    fn declare_datatype(&mut self, ty: Self::Sort, cstors: &[Self::Fun]);
+
     /// Build a term by function application
     fn app_fun(&mut self, f: Self::Fun, args: &[Self::Term]) -> Self::Term;
 
     /// Apply a builtin to some arguments.
     fn app_op(&mut self, op: Op, args: &[Self::Term]) -> Self::Term;
 
+    /// A numeral, such as `42` or `1.5`, of sort `Real`.
+    fn numeral(&mut self, n: Atom) -> Self::Term;
+
+    /// A bit-vector constant, such as `#b0101`, least significant bit first.
+    fn bv_const(&mut self, bits: &[bool]) -> Self::Term;
+
     /// Build a `ite` term
     fn ite(&mut self, _: Self::Term, _: Self::Term, _: Self::Term) -> Self::Term;
 
@@ -49,19 +119,60 @@ pub trait TermBuilder : SortBuilder {
 
     /// Build a let binding. The variables may be called from now on.
     fn let_(&mut self, bs: &[(Self::Var, Self::Term)], body: Self::Term) -> Self::Term;
+
+    /// Make a variable of sort `ty`, to be bound by a quantifier.
+    fn bound_var(&mut self, name: Atom, ty: Self::Sort) -> Self::Var;
+
+    /// Build a quantified formula over `vars`.
+    ///
+    /// `patterns` are the triggers given with `(! body :pattern (t1…tn))`,
+    /// if any.
+    fn quant(&mut self, q: Quant, vars: &[Self::Var], body: Self::Term, patterns: &[Vec<Self::Term>]) -> Self::Term;
+}
+
+
+/// A sort expression, as found in `define-sort`, before
+/// its parameters are replaced.
+#[derive(Debug,Clone)]
+pub enum SortExpr {
+    Atom(Atom),
+    App(Atom, Vec<SortExpr>),
+    BitVec(u32),
 }
 
+/// A datatype, as declared by `declare-datatype(s)`.
+#[derive(Debug,Clone)]
+pub struct Datatype<Sort> {
+    pub name: Atom,
+    pub cstors: Vec<(Atom, Vec<(Atom, Sort)>)>, // constructors, with their selectors
+}
 
 /// A toplevel statement
 #[derive(Debug,Clone)]
 pub enum Statement<Term, Sort> {
     SetInfo(Atom,Atom),
     SetLogic(Atom),
+    SetOption(Atom,Atom),
     DeclareSort(Atom,u8),
     DeclareFun(Atom,Vec<Sort>,Sort),
+    DefineFun(Atom,Vec<(Atom,Sort)>,Sort,Term),
+    DefineSort(Atom,Vec<Atom>,SortExpr),
+    DeclareDatatypes(Vec<Datatype<Sort>>),
     Assert(Term),
+    AssertNamed(Atom,Term),
     CheckSat,
     CheckSatAssumptions(Vec<Term>),
+    GetModel,
+    GetValue(Vec<Term>),
+    GetUnsatCore,
+    GetProof,
+    Push(u32),
+    Pop(u32),
+    GetInfo(Atom),
+    GetOption(Atom),
+    Echo(Atom), // string literal, with its quotes
+    Reset,
+    ResetAssertions,
     Exit,
 }
 
@@ -74,18 +185,50 @@ impl<T,S> Statement<T,S> {
         match self {
             SetInfo(a,b) => SetInfo(a,b),
             SetLogic(a) => SetLogic(a),
+            SetOption(a,b) => SetOption(a,b),
             DeclareSort(s,n) => DeclareSort(s,n),
             DeclareFun(s,args,ret) => {
                 let args = args.into_iter().map(|s| fs(s)).collect();
                 let ret = fs(ret);
                 DeclareFun(s,args,ret)
             },
+            DefineFun(f,vars,ret,body) => {
+                let vars = vars.into_iter().map(|(v,s)| (v, fs(s))).collect();
+                let ret = fs(ret);
+                DefineFun(f,vars,ret,ft(body))
+            },
+            DefineSort(s,params,body) => DefineSort(s,params,body),
+            DeclareDatatypes(dts) => {
+                let dts = dts.into_iter().map(|d| {
+                    let cstors = d.cstors.into_iter().map(|(c,sels)| {
+                        let sels = sels.into_iter().map(|(s,ty)| (s, fs(ty))).collect();
+                        (c, sels)
+                    }).collect();
+                    Datatype{name: d.name, cstors}
+                }).collect();
+                DeclareDatatypes(dts)
+            },
             Assert(t) => Assert(ft(t)),
+            AssertNamed(name,t) => AssertNamed(name, ft(t)),
             CheckSat => CheckSat,
             CheckSatAssumptions(v) => {
                 let v = v.into_iter().map(|x| ft(x)).collect();
                 CheckSatAssumptions(v)
             },
+            GetModel => GetModel,
+            GetValue(v) => {
+                let v = v.into_iter().map(|x| ft(x)).collect();
+                GetValue(v)
+            },
+            GetUnsatCore => GetUnsatCore,
+            GetProof => GetProof,
+            Push(n) => Push(n),
+            Pop(n) => Pop(n),
+            GetInfo(a) => GetInfo(a),
+            GetOption(a) => GetOption(a),
+            Echo(a) => Echo(a),
+            Reset => Reset,
+            ResetAssertions => ResetAssertions,
             Exit => Exit,
         }
     }
@@ -106,6 +249,11 @@ pub fn pp_stmt<T,S,FT,FS>(st: &Statement<T,S>, mut ft: FT, mut fs: FS, ctx: &mut
                 ctx.str("set-logic").space().pp(&a);
             });
         },
+        &Statement::SetOption(ref a, ref b) => {
+            ctx.sexp(|ctx| {
+                ctx.str("set-option").space().pp(&a).space().pp(&b);
+            });
+        },
         &Statement::DeclareSort(ref s,n) => {
             ctx.sexp(|ctx| {
                 ctx.str("declare-sort").space().pp(s).space().string(n.to_string());
@@ -125,12 +273,76 @@ pub fn pp_stmt<T,S,FT,FS>(st: &Statement<T,S>, mut ft: FT, mut fs: FS, ctx: &mut
 
             });
         },
+        &Statement::DefineFun(ref f, ref vars, ref ret, ref body) => {
+            ctx.sexp(|ctx| {
+                ctx.str("define-fun").space().pp(&f).space();
+                ctx.sexp(|ctx| {
+                    for (i,(v,s)) in vars.iter().enumerate() {
+                        if i>0 { ctx.space(); }
+                        ctx.sexp(|ctx| { ctx.pp(v).space(); fs(s, ctx); });
+                    }
+                }).space();
+                fs(&ret, ctx);
+                ctx.space();
+                ft(&body, ctx);
+            });
+        },
+        &Statement::DefineSort(ref s, ref params, ref body) => {
+            ctx.sexp(|ctx| {
+                ctx.str("define-sort").space().pp(s).space();
+                ctx.sexp(|ctx| {
+                    for (i,p) in params.iter().enumerate() {
+                        if i>0 { ctx.space(); }
+                        ctx.pp(p);
+                    }
+                }).space().pp(body);
+            });
+        },
+        &Statement::DeclareDatatypes(ref dts) => {
+            ctx.sexp(|ctx| {
+                ctx.str("declare-datatypes").space();
+                ctx.sexp(|ctx| {
+                    for (i,d) in dts.iter().enumerate() {
+                        if i>0 { ctx.space(); }
+                        ctx.sexp(|ctx| { ctx.pp(&d.name).space().str("0"); });
+                    }
+                }).space();
+                ctx.sexp(|ctx| {
+                    for (i,d) in dts.iter().enumerate() {
+                        if i>0 { ctx.space(); }
+                        ctx.sexp(|ctx| {
+                            for (j,(c,sels)) in d.cstors.iter().enumerate() {
+                                if j>0 { ctx.space(); }
+                                ctx.sexp(|ctx| {
+                                    ctx.pp(c);
+                                    for (s,ty) in sels.iter() {
+                                        ctx.space().sexp(|ctx| {
+                                            ctx.pp(s).space();
+                                            fs(ty, ctx);
+                                        });
+                                    }
+                                });
+                            }
+                        });
+                    }
+                });
+            });
+        },
         &Statement::Assert(ref t) => {
             ctx.sexp(|ctx| {
                 ctx.str("assert").space();
                 ft(t, ctx);
             });
         },
+        &Statement::AssertNamed(ref name, ref t) => {
+            ctx.sexp(|ctx| {
+                ctx.str("assert").space().sexp(|ctx| {
+                    ctx.str("!").space();
+                    ft(t, ctx);
+                    ctx.space().str(":named").space().pp(name);
+                });
+            });
+        },
         &Statement::CheckSat => { ctx.str("(check-sat)"); },
         &Statement::CheckSatAssumptions(ref v) => {
             ctx.sexp(|ctx| {
@@ -138,10 +350,56 @@ pub fn pp_stmt<T,S,FT,FS>(st: &Statement<T,S>, mut ft: FT, mut fs: FS, ctx: &mut
                 for t in v { ctx.space(); ft(t,ctx); }
             });
         },
+        &Statement::GetModel => { ctx.str("(get-model)"); },
+        &Statement::GetValue(ref v) => {
+            ctx.sexp(|ctx| {
+                ctx.str("get-value").space();
+                ctx.sexp(|ctx| {
+                    for (i,t) in v.iter().enumerate() {
+                        if i>0 { ctx.space(); }
+                        ft(t,ctx);
+                    }
+                });
+            });
+        },
+        &Statement::GetUnsatCore => { ctx.str("(get-unsat-core)"); },
+        &Statement::GetProof => { ctx.str("(get-proof)"); },
+        &Statement::Push(n) => {
+            ctx.sexp(|ctx| { ctx.str("push").space().string(n.to_string()); });
+        },
+        &Statement::Pop(n) => {
+            ctx.sexp(|ctx| { ctx.str("pop").space().string(n.to_string()); });
+        },
+        &Statement::GetInfo(ref a) => {
+            ctx.sexp(|ctx| { ctx.str("get-info").space().pp(&a); });
+        },
+        &Statement::GetOption(ref a) => {
+            ctx.sexp(|ctx| { ctx.str("get-option").space().pp(&a); });
+        },
+        &Statement::Echo(ref a) => {
+            ctx.sexp(|ctx| { ctx.str("echo").space().pp(&a); });
+        },
+        &Statement::Reset => { ctx.str("(reset)"); },
+        &Statement::ResetAssertions => { ctx.str("(reset-assertions)"); },
         &Statement::Exit => { ctx.str("(exit)"); },
     }
 }
 
+impl pp::Pretty for SortExpr {
+    fn pp_into(&self, ctx: &mut pp::Ctx) {
+        match self {
+            SortExpr::Atom(a) => { ctx.pp(a); },
+            SortExpr::BitVec(n) => { ctx.string(format!("(_ BitVec {})", n)); },
+            SortExpr::App(f, args) => {
+                ctx.sexp(|ctx| {
+                    ctx.pp(f);
+                    for s in args { ctx.space().pp(s); }
+                });
+            },
+        }
+    }
+}
+
 impl<T,S> pp::Pretty for Statement<T,S>
     where T: pp::Pretty, S: pp::Pretty
 {
@@ -0,0 +1,195 @@
+
+//! Sort checking.
+//!
+//! The parser checks the sorts of arguments before calling the builder,
+//! so that builders can assume their input is well-sorted.
+
+use {
+    std::{borrow::Cow, fmt::Display},
+    crate::types::{Op, SortBuilder, SortView, TermBuilder},
+};
+
+/// Result of sort checking, with a message in case of error.
+pub(crate) type Result = std::result::Result<(), String>;
+
+fn pp_ty<B: TermBuilder>(b: &B, t: &B::Term) -> String {
+    b.pp_sort(&b.sort_of(t))
+}
+
+fn is_bool<B: SortBuilder>(b: &B, ty: &B::Sort) -> bool {
+    match b.view_sort(ty) { SortView::Bool => true, _ => false }
+}
+
+fn is_real<B: SortBuilder>(b: &B, ty: &B::Sort) -> bool {
+    match b.view_sort(ty) { SortView::Real => true, _ => false }
+}
+
+fn bv_width<B: SortBuilder>(b: &B, ty: &B::Sort) -> Option<u32> {
+    match b.view_sort(ty) { SortView::BitVec(w) => Some(w), _ => None }
+}
+
+fn n_args(what: &str, args: usize, min: usize, max: Option<usize>) -> Result {
+    if args < min || max.map_or(false, |max| args > max) {
+        let expected = match max {
+            Some(max) if max == min => format!("{}", min),
+            Some(max) => format!("{} to {}", min, max),
+            None => format!("at least {}", min),
+        };
+        Err(format!("{} expects {} arguments, got {}", what, expected, args))
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that `t` has sort `ty`. `what` describes `t`, it is only
+/// called in case of error.
+pub(crate) fn expect<B: TermBuilder, W: Display>(
+    b: &B, what: impl Fn() -> W, t: &B::Term, ty: &B::Sort
+) -> Result {
+    let ty2 = b.sort_of(t);
+    if ty2 == *ty {
+        Ok(())
+    } else {
+        Err(format!("{} should have sort {}, not {}", what(), b.pp_sort(ty), b.pp_sort(&ty2)))
+    }
+}
+
+/// Check that `t` is a formula.
+pub(crate) fn expect_bool<B: TermBuilder>(b: &B, what: &str, t: &B::Term) -> Result {
+    expect(b, || what, t, &b.get_bool())
+}
+
+/// Check that all the `args` have the same sort.
+fn same_sort<B: TermBuilder>(b: &B, what: &str, args: &[B::Term]) -> Result {
+    if let Some(t) = args.get(0) {
+        let ty = b.sort_of(t);
+        for (i, u) in args.iter().enumerate().skip(1) {
+            if b.sort_of(u) != ty {
+                return Err(format!(
+                        "arguments of {} should have the same sort, but argument 1 has sort {} \
+                        and argument {} has sort {}", what, b.pp_sort(&ty), i+1, pp_ty(b, u)))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that all the `args` satisfy `p`.
+fn all_args<B: TermBuilder>(
+    b: &B, what: &str, expected: &str, args: &[B::Term], p: impl Fn(&B::Sort) -> bool
+) -> Result {
+    for (i, t) in args.iter().enumerate() {
+        if ! p(&b.sort_of(t)) {
+            return Err(format!("argument {} of {} should have sort {}, not {}",
+                i+1, what, expected, pp_ty(b, t)))
+        }
+    }
+    Ok(())
+}
+
+/// Check the application of a declared function to `args`.
+pub(crate) fn app<B: TermBuilder>(b: &B, f: &str, tys: &[B::Sort], args: &[B::Term]) -> Result {
+    n_args(f, args.len(), tys.len(), Some(tys.len()))?;
+    for (i, (t, ty)) in args.iter().zip(tys.iter()).enumerate() {
+        expect(b, || format!("argument {} of {}", i+1, f), t, ty)?;
+    }
+    Ok(())
+}
+
+/// Check `(ite a b c)`.
+pub(crate) fn ite<B: TermBuilder>(b: &B, a: &B::Term, t1: &B::Term, t2: &B::Term) -> Result {
+    expect_bool(b, "condition of ite", a)?;
+    let ty = b.sort_of(t1);
+    expect(b, || "else branch of ite", t2, &ty)
+}
+
+// name of `op`, for error messages
+fn op_name(op: Op) -> Cow<'static, str> {
+    let s = match op {
+        Op::Extract(hi, lo) => return format!("(_ extract {} {})", hi, lo).into(),
+        Op::True => "true", Op::False => "false", Op::Or => "or", Op::And => "and",
+        Op::Imply => "=>", Op::Eq => "=", Op::Not => "not", Op::Distinct => "distinct",
+        Op::Add => "+", Op::Sub => "-", Op::Mul => "*", Op::Div => "/",
+        Op::Leq => "<=", Op::Lt => "<", Op::Geq => ">=", Op::Gt => ">",
+        Op::Select => "select", Op::Store => "store",
+        Op::BvAdd => "bvadd", Op::BvSub => "bvsub", Op::BvNeg => "bvneg", Op::BvMul => "bvmul",
+        Op::BvAnd => "bvand", Op::BvOr => "bvor", Op::BvXor => "bvxor", Op::BvNot => "bvnot",
+        Op::BvUlt => "bvult", Op::BvUle => "bvule", Op::BvUgt => "bvugt", Op::BvUge => "bvuge",
+        Op::BvSlt => "bvslt", Op::BvSle => "bvsle", Op::BvSgt => "bvsgt", Op::BvSge => "bvsge",
+        Op::Concat => "concat",
+    };
+    s.into()
+}
+
+/// Check the application of a builtin to `args`.
+pub(crate) fn op<B: TermBuilder>(b: &B, op: Op, args: &[B::Term]) -> Result {
+    let what = op_name(op);
+    let what = &*what;
+    match op {
+        Op::True | Op::False => n_args(what, args.len(), 0, Some(0)),
+        Op::Not => {
+            n_args(what, args.len(), 1, Some(1))?;
+            all_args(b, what, "Bool", args, |ty| is_bool(b, ty))
+        },
+        Op::And | Op::Or => all_args(b, what, "Bool", args, |ty| is_bool(b, ty)),
+        Op::Imply => {
+            n_args(what, args.len(), 2, None)?;
+            all_args(b, what, "Bool", args, |ty| is_bool(b, ty))
+        },
+        Op::Eq | Op::Distinct => {
+            n_args(what, args.len(), 2, None)?;
+            same_sort(b, what, args)
+        },
+        Op::Add | Op::Sub | Op::Mul | Op::Div => {
+            n_args(what, args.len(), 1, None)?;
+            all_args(b, what, "Real", args, |ty| is_real(b, ty))
+        },
+        Op::Leq | Op::Lt | Op::Geq | Op::Gt => {
+            n_args(what, args.len(), 2, None)?;
+            all_args(b, what, "Real", args, |ty| is_real(b, ty))
+        },
+        Op::Select | Op::Store => {
+            let n = if let Op::Select = op { 2 } else { 3 };
+            n_args(what, args.len(), n, Some(n))?;
+            match b.view_sort(&b.sort_of(&args[0])) {
+                SortView::Array(idx, val) => {
+                    expect(b, || format!("index of {}", what), &args[1], &idx)?;
+                    if n == 3 {
+                        expect(b, || format!("value of {}", what), &args[2], &val)?;
+                    }
+                    Ok(())
+                },
+                _ => Err(format!("argument 1 of {} should be an array, not {}",
+                    what, pp_ty(b, &args[0]))),
+            }
+        },
+        Op::BvNeg | Op::BvNot => {
+            n_args(what, args.len(), 1, Some(1))?;
+            all_args(b, what, "(_ BitVec _)", args, |ty| bv_width(b, ty).is_some())
+        },
+        Op::BvAdd | Op::BvSub | Op::BvMul | Op::BvAnd | Op::BvOr | Op::BvXor => {
+            n_args(what, args.len(), 2, None)?;
+            all_args(b, what, "(_ BitVec _)", args, |ty| bv_width(b, ty).is_some())?;
+            same_sort(b, what, args)
+        },
+        Op::BvUlt | Op::BvUle | Op::BvUgt | Op::BvUge |
+        Op::BvSlt | Op::BvSle | Op::BvSgt | Op::BvSge => {
+            n_args(what, args.len(), 2, Some(2))?;
+            all_args(b, what, "(_ BitVec _)", args, |ty| bv_width(b, ty).is_some())?;
+            same_sort(b, what, args)
+        },
+        Op::Concat => {
+            n_args(what, args.len(), 2, None)?;
+            all_args(b, what, "(_ BitVec _)", args, |ty| bv_width(b, ty).is_some())
+        },
+        Op::Extract(hi, _) => {
+            n_args(what, args.len(), 1, Some(1))?;
+            match bv_width(b, &b.sort_of(&args[0])) {
+                Some(w) if hi < w => Ok(()),
+                Some(w) => Err(format!("{} is out of bounds for (_ BitVec {})", what, w)),
+                None => Err(format!("argument 1 of {} should have sort (_ BitVec _), not {}",
+                    what, pp_ty(b, &args[0]))),
+            }
+        },
+    }
+}
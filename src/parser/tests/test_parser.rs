@@ -0,0 +1,220 @@
+extern crate batsmt_parser;
+
+use {
+    std::io,
+    batsmt_parser::{simple_ast, Statement, StatementStream, Error, },
+};
+
+#[test]
+fn test_stream() {
+    let mut b = simple_ast::Builder::new();
+    let s = "(set-logic QF_UF) (declare-fun a () Bool)\n(assert a) (check-sat)";
+    let stmts: Vec<_> =
+        StatementStream::new(&mut b, io::Cursor::new(s.as_bytes()))
+        .collect::<Result<_,_>>().unwrap();
+    assert_eq!(stmts.len(), 4);
+    match &stmts[3] { Statement::CheckSat => (), st => panic!("unexpected {}", st) }
+}
+
+#[test]
+fn test_stream_error() {
+    // statements before an error are still returned
+    let mut b = simple_ast::Builder::new();
+    let s = "(declare-fun a () Bool)\n(assert a)\n(assert (f a))";
+    let mut stream = StatementStream::new(&mut b, io::Cursor::new(s.as_bytes()));
+    assert!(stream.next().unwrap().is_ok());
+    assert!(stream.next().unwrap().is_ok());
+    let e = stream.next().unwrap().unwrap_err();
+    assert_eq!(e.pos().line, 3);
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn test_error_pos() {
+    let mut b = simple_ast::Builder::new();
+    let s = "(set-logic QF_UF)\n  (assert 1 2";
+    let e = batsmt_parser::parse_str(&mut b, s).unwrap_err();
+    assert_eq!(e.pos().line, 2);
+    assert!(e.to_string().contains("line 2"));
+}
+
+#[test]
+fn test_error_kind() {
+    let mut b = simple_ast::Builder::new();
+    let s = "(declare-fun f (Bool) Bool)\n(declare-fun a () U)\n(assert (and (f a) (g a)))\n\
+        (assert (f true true))\n(assert (and";
+    let mut stream = StatementStream::new(&mut b, io::Cursor::new(s.as_bytes()));
+    stream.set_file("foo.smt2");
+    assert!(stream.next().unwrap().is_ok());
+    match stream.next().unwrap() {
+        Err(Error::UnknownSort(ref u, ref loc)) => {
+            assert_eq!(&**u, "U");
+            assert_eq!((loc.pos.line, loc.pos.col), (2, 19));
+        },
+        r => panic!("unexpected {:?}", r.map(|st| st.to_string())),
+    }
+    // recovers after the faulty statement
+    match stream.next().unwrap() {
+        Err(Error::UnknownSymbol(ref u, ref loc)) => {
+            assert_eq!(&**u, "a");
+            assert_eq!(loc.snippet, "(f a");
+        },
+        r => panic!("unexpected {:?}", r.map(|st| st.to_string())),
+    }
+    match stream.next().unwrap() {
+        Err(e @ Error::Arity(..)) => {
+            assert_eq!(e.loc().snippet, "(f true true)");
+            assert!(e.to_string().starts_with("foo.smt2:4:"));
+        },
+        r => panic!("unexpected {:?}", r.map(|st| st.to_string())),
+    }
+    match stream.next().unwrap() {
+        Err(Error::UnexpectedEof(_)) => (),
+        r => panic!("unexpected {:?}", r.map(|st| st.to_string())),
+    }
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn test_reserved_symbol() {
+    // symbols starting with `@` are reserved for the solver
+    let mut b = simple_ast::Builder::new();
+    let s = "(declare-fun @b1 () Bool) (declare-const |@b2| Bool) (declare-fun b@ () Bool)";
+    let res: Vec<_> = StatementStream::new(&mut b, io::Cursor::new(s.as_bytes())).collect();
+    assert_eq!(res.len(), 3);
+    match &res[0] {
+        Err(Error::Syntax(msg, _)) => assert!(msg.contains("reserved"), "{}", msg),
+        r => panic!("unexpected {:?}", r.as_ref().map(|st| st.to_string())),
+    }
+    assert!(res[1].is_err());
+    assert!(res[2].is_ok());
+}
+
+#[test]
+fn test_scoped_decls() {
+    // declarations are removed by `pop`, and cannot be repeated in scope
+    let mut b = simple_ast::Builder::new();
+    let s = "(declare-sort U 0) (push 1) (declare-const a U) (declare-sort V 0) (assert (= a a))
+        (pop 1) (assert (= a a)) (declare-const a Bool) (declare-const a Bool) (declare-sort U 0)
+        (declare-sort V 0) (push 2) (declare-const b Bool) (reset-assertions) (assert b)";
+    let res: Vec<_> =
+        StatementStream::new(&mut b, io::Cursor::new(s.as_bytes()))
+        .map(|r| r.is_ok()).collect();
+    assert_eq!(res, vec!(true, true, true, true, true, true, false, true, false, false,
+        true, true, true, true, false));
+}
+
+#[test]
+fn test_sort_errors() {
+    let mut b = simple_ast::Builder::new();
+    let s = "(declare-sort U 0) (declare-fun f (U) Bool) (declare-fun a () U)\n\
+        (declare-fun x () Real) (declare-fun v () (_ BitVec 4))\n\
+        (assert (f x)) (assert (= a x)) (assert (ite a x x)) (assert (and (f a) x))\n\
+        (assert (+ x 1)) (assert (bvult v ((_ extract 1 0) v)))\n\
+        (assert (or (f a) (< x 1))) (assert (=> (f a)))";
+    let mut errors = vec!();
+    let mut n_ok = 0;
+    for r in StatementStream::new(&mut b, io::Cursor::new(s.as_bytes())) {
+        match r {
+            Ok(_) => n_ok += 1,
+            Err(Error::Sort(msg, loc)) => errors.push((msg, loc.snippet)),
+            Err(e) => panic!("unexpected error {}", e),
+        }
+    }
+    assert_eq!(n_ok, 6);
+    assert_eq!(errors.len(), 7);
+    assert_eq!(errors[0].1, "(f x)");
+    assert!(errors[0].0.contains("should have sort U, not Real"), "{}", errors[0].0);
+    assert_eq!(errors[1].1, "(= a x)");
+    assert!(errors[2].0.contains("condition of ite"), "{}", errors[2].0);
+    assert_eq!(errors[3].1, "(and (f a) x)");
+    assert!(errors[4].0.contains("assertion"), "{}", errors[4].0);
+    assert!(errors[5].0.contains("bvult"), "{}", errors[5].0);
+    assert!(errors[6].0.contains("=> expects at least 2 arguments"), "{}", errors[6].0);
+}
+
+#[test]
+fn test_extract_errors() {
+    let mut b = simple_ast::Builder::new();
+    let s = "(declare-fun v () (_ BitVec 4))\n\
+        (assert (= ((_ extract 0 0)) #b1)) (assert (= ((_ extract 0 0) v v) #b1))\n\
+        (assert (= ((_ extract 7 0) v) #x00)) (assert (= ((_ extract 3 2) v) #b01))";
+    let mut errors = vec!();
+    let mut n_ok = 0;
+    for r in StatementStream::new(&mut b, io::Cursor::new(s.as_bytes())) {
+        match r {
+            Ok(_) => n_ok += 1,
+            Err(Error::Sort(msg, _)) => errors.push(msg),
+            Err(e) => panic!("unexpected error {}", e),
+        }
+    }
+    assert_eq!(n_ok, 2);
+    assert_eq!(errors.len(), 3);
+    assert!(errors[0].contains("(_ extract 0 0) expects 1 argument"), "{}", errors[0]);
+    assert!(errors[1].contains("(_ extract 0 0) expects 1 argument"), "{}", errors[1]);
+    assert!(errors[2].contains("out of bounds for (_ BitVec 4)"), "{}", errors[2]);
+}
+
+#[test]
+fn test_bv_zero_width() {
+    let mut b = simple_ast::Builder::new();
+    let s = "(assert (bvslt (_ bv0 0) (_ bv0 0))) (declare-fun v () (_ BitVec 0))";
+    let mut stream = StatementStream::new(&mut b, io::Cursor::new(s.as_bytes()));
+    for _ in 0 .. 2 {
+        match stream.next().unwrap() {
+            Err(e) => assert!(e.to_string().contains("positive width"), "{}", e),
+            r => panic!("unexpected {:?}", r.map(|st| st.to_string())),
+        }
+    }
+}
+
+#[test]
+fn test_define() {
+    let mut b = simple_ast::Builder::new();
+    let s = "(declare-sort Pair 2) (define-sort P (X) (Pair X Bool))\n\
+        (declare-const p (P Real)) (declare-fun f ((Pair Real Bool)) Bool)\n\
+        (define-fun g ((x (P Real)) (y Bool)) Bool (and (f x) y))\n\
+        (assert (g p true)) (assert (g p)) (declare-const q (P Real Real))";
+    let mut stream = StatementStream::new(&mut b, io::Cursor::new(s.as_bytes()));
+    for _ in 0 .. 5 { stream.next().unwrap().unwrap(); }
+    // the macro is expanded
+    let st = stream.next().unwrap().unwrap();
+    assert_eq!(st.to_string(), "(assert (and (f p) true))");
+    match stream.next().unwrap() {
+        Err(Error::Arity(..)) => (),
+        r => panic!("unexpected {:?}", r.map(|st| st.to_string())),
+    }
+    match stream.next().unwrap() {
+        Err(Error::Arity(msg, _)) => assert!(msg.contains("sort P"), "{}", msg),
+        r => panic!("unexpected {:?}", r.map(|st| st.to_string())),
+    }
+}
+
+#[test]
+fn test_admin() {
+    let mut b = simple_ast::Builder::new();
+    let s = "(echo \"a \"\"(b\") (get-info :name) (declare-fun a () Bool)\n\
+        (reset) (get-option :produce-models) (reset-assertions)";
+    let stmts = batsmt_parser::parse_str(&mut b, s).unwrap();
+    assert_eq!(stmts.len(), 6);
+    match &stmts[0] {
+        Statement::Echo(s) => assert_eq!(&**s, "\"a \"\"(b\""),
+        st => panic!("unexpected {}", st),
+    }
+    assert_eq!(stmts[1].to_string(), "(get-info :name)");
+    assert_eq!(stmts[3].to_string(), "(reset)");
+}
+
+#[test]
+fn test_dimacs() {
+    use batsmt_parser::dimacs;
+    let s = "c a comment\np cnf 3 3\n1 -2 0\n2 3\n -1 0 -3 0\n";
+    let mut clauses = vec!();
+    let h = dimacs::parse(s.as_bytes(), None, |c| clauses.push(c.to_vec())).unwrap();
+    assert_eq!(h, Some(dimacs::Header{n_vars: 3, n_clauses: 3}));
+    assert_eq!(clauses, vec!(vec!(1, -2), vec!(2, 3, -1), vec!(-3)));
+
+    let e = dimacs::parse("p cnf 2 1\n1 -3 0\n".as_bytes(), None, |_| ()).unwrap_err();
+    assert_eq!((e.pos().line, e.pos().col), (2, 3));
+    assert!(dimacs::parse("1 x 0\n".as_bytes(), None, |_| ()).is_err());
+}
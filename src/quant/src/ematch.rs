@@ -0,0 +1,288 @@
+
+//! E-matching, as a micro-theory of the congruence closure.
+
+use {
+    batsmt_core::{ast_u32::AST, AstView, Manager, ast::iter_dag, backtrack::Backtrackable, },
+    batsmt_theory::{TheoryLit, Actions, },
+    batsmt_cc::cc::{MicroTheory, NodeID, CC1, },
+    fxhash::{FxHashMap, FxHashSet, },
+    crate::{
+        intf::{Ctx, QuantView, },
+        trigger::{self, Pat, },
+        inst::{Clausifier, subst, pp_t, },
+    },
+};
+
+/// Terms created by instantiation have a generation, one more than
+/// the generation of the terms they were obtained from. Terms of
+/// this generation are not used for matching anymore.
+const MAX_GENERATION: u32 = 5;
+
+/// Maximum number of instances added in a single check.
+const MAX_INSTANCES_PER_ROUND: usize = 500;
+
+/// A substitution for the bound variables of a quantified formula.
+type Subst = Vec<Option<AST>>;
+
+/// Instantiation of quantified formulas by E-matching.
+///
+/// Quantified formulas that are asserted true are instantiated in
+/// `final_check`, using the substitutions obtained by matching their
+/// triggers against the E-graph. Instances are added as lemmas
+/// `¬(∀x. body) ∨ body[x:=t]`, where the instance is clausified on the fly.
+///
+/// Quantified formulas asserted false are skolemized:
+/// `(∀x. body) ∨ ¬body[x:=sk]`.
+pub struct EMatching {
+    quants: Vec<AST>, // quantified formulas in the E-graph
+    apps: Vec<AST>, // applications in the E-graph
+    levels: Vec<(usize, usize)>, // sizes of `quants`, `apps`
+    triggers: FxHashMap<AST, Vec<Vec<Pat>>>, // quantified formula -> its triggers
+    instances: FxHashSet<(AST, AST)>, // (formula, instance) already added
+    skolemized: FxHashSet<AST>,
+    clausify: Clausifier,
+    gen: FxHashMap<AST, u32>, // generation of terms created by instantiation
+    by_head: FxHashMap<AST, Vec<AST>>, // for matching, recomputed in each round
+}
+
+impl<C> Backtrackable<C> for EMatching {
+    fn push_level(&mut self, _: &mut C) {
+        self.levels.push((self.quants.len(), self.apps.len()));
+    }
+    fn pop_levels(&mut self, _: &mut C, n: usize) {
+        if n > 0 {
+            let idx = self.levels.len() - n;
+            let (n_quants, n_apps) = self.levels[idx];
+            self.quants.truncate(n_quants);
+            self.apps.truncate(n_apps);
+            self.levels.truncate(idx);
+        }
+    }
+}
+
+impl EMatching {
+    /// The triggers of `q`, inferred if the user did not provide any.
+    fn triggers<C: Ctx>(&mut self, c: &C, q: &AST) -> Vec<Vec<Pat>> {
+        if let Some(trs) = self.triggers.get(q) {
+            return trs.clone()
+        }
+        let trs: Vec<Vec<Pat>> = match c.view_as_quant(q) {
+            QuantView::Forall{vars, body, patterns} => {
+                // a user trigger that misses some variable cannot give
+                // an instance, ignore it
+                let user: Vec<Vec<AST>> =
+                    patterns.iter()
+                    .filter(|tr| {
+                        let ok = trigger::covers(c, vars, tr);
+                        if ! ok { info!("quant: ignore incomplete trigger in {}", pp_t(c, q)) }
+                        ok
+                    })
+                    .cloned().collect();
+                let terms =
+                    if ! user.is_empty() { user }
+                    else { trigger::infer(c, vars, body) };
+                if terms.len() == 0 {
+                    info!("quant: no trigger for {}", pp_t(c, q));
+                }
+                terms.iter()
+                    .map(|tr| tr.iter().map(|t| trigger::compile(c, vars, t)).collect::<Vec<_>>())
+                    // each term of a multi-trigger must have a head symbol
+                    .filter(|tr: &Vec<Pat>| tr.iter().all(|p| p.head().is_some()))
+                    .collect()
+            },
+            QuantView::Other(_) => unreachable!(),
+        };
+        self.triggers.insert(*q, trs.clone());
+        trs
+    }
+
+    /// Match `p` against the class `n`, extending `s`.
+    /// Resulting substitutions are pushed into `out`.
+    fn match_pat<C: Ctx>(
+        &self, c: &C, cc1: &mut CC1<C>, p: &Pat, n: NodeID, s: &Subst, out: &mut Vec<Subst>
+    ) {
+        match p {
+            Pat::Var(i) => {
+                match s[*i] {
+                    Some(u) => {
+                        if cc1.find_t(&u) == n { out.push(s.clone()) }
+                    },
+                    None => {
+                        let mut u = None;
+                        cc1.iter_class(n, |t| if u.is_none() { u = Some(*t) });
+                        let mut s2 = s.clone();
+                        s2[*i] = u;
+                        out.push(s2)
+                    },
+                }
+            },
+            Pat::Ground(t) => {
+                if cc1.contains(t) && cc1.find_t(t) == n { out.push(s.clone()) }
+            },
+            Pat::App(f, args) => {
+                let mut class = vec!();
+                cc1.iter_class(n, |t| class.push(*t));
+                for t in class.iter() {
+                    let t_args = match c.view(t) {
+                        AstView::App{f: f2, args: t_args} if f2 == f && t_args.len() == args.len() => t_args,
+                        _ => continue,
+                    };
+                    let mut cur = vec!(s.clone());
+                    for (p2, u) in args.iter().zip(t_args.iter()) {
+                        if ! cc1.contains(u) { cur.clear(); break }
+                        let n_u = cc1.find_t(u);
+                        let mut next = vec!();
+                        for s2 in cur.iter() {
+                            self.match_pat(c, cc1, p2, n_u, s2, &mut next);
+                        }
+                        cur = next;
+                        if cur.len() == 0 { break }
+                    }
+                    out.extend(cur);
+                }
+            },
+        }
+    }
+
+    /// All the substitutions for the multi-trigger `tr`.
+    fn match_trigger<C: Ctx>(
+        &self, c: &C, cc1: &mut CC1<C>, n_vars: usize, tr: &[Pat], out: &mut Vec<Subst>
+    ) {
+        let mut cur = vec!(vec![None; n_vars]);
+        for p in tr.iter() {
+            let ts = match p.head().and_then(|f| self.by_head.get(f)) {
+                Some(ts) => ts,
+                None => return, // no candidate
+            };
+            let mut next = vec!();
+            for t in ts.iter() {
+                let n = cc1.find_t(t);
+                for s in cur.iter() {
+                    self.match_pat(c, cc1, p, n, s, &mut next);
+                }
+            }
+            next.sort();
+            next.dedup();
+            cur = next;
+            if cur.len() == 0 { return }
+        }
+        out.extend(cur);
+    }
+
+    /// Instantiate `q` with all the substitutions from its triggers.
+    ///
+    /// Returns the number of new instances.
+    fn instantiate<C: Ctx>(
+        &mut self, c: &mut C, cc1: &mut CC1<C>, acts: &mut dyn Actions<C>, q: AST, budget: usize
+    ) -> usize {
+        let (vars, body) = match c.view_as_quant(&q) {
+            QuantView::Forall{vars, body, ..} => (vars.to_vec(), *body),
+            QuantView::Other(_) => unreachable!(),
+        };
+        let mut substs = vec!();
+        for tr in self.triggers(c, &q).iter() {
+            self.match_trigger(c, cc1, vars.len(), tr, &mut substs);
+        }
+
+        let mut n = 0;
+        for s in substs.iter() {
+            if n >= budget { break }
+            let g = s.iter().map(|u| u.and_then(|u| self.gen.get(&u).cloned()).unwrap_or(0)).max().unwrap_or(0);
+            if g >= MAX_GENERATION { continue }
+
+            let mut m = FxHashMap::default();
+            for (v, u) in vars.iter().zip(s.iter()) {
+                m.insert(*v, u.expect("quant: unbound variable"));
+            }
+            let inst = subst(c, &mut m, &body);
+            if ! self.instances.insert((q, inst)) { continue }
+
+            debug!("quant: instance {} (generation {})", pp_t(c, &inst), g+1);
+            n += 1;
+            {
+                let gen = &mut self.gen;
+                iter_dag(c, &inst, |_, t| {
+                    if ! cc1.contains(t) { gen.entry(*t).or_insert(g+1); }
+                });
+            }
+            let l_q = acts.map_lit(c, TheoryLit::new_t(q, true));
+            let l_inst = self.clausify.lit_of(c, acts, inst);
+            acts.add_lemma(&[!l_q, l_inst]);
+        }
+        n
+    }
+
+    /// Skolemize `¬q`, once.
+    fn skolemize<C: Ctx>(&mut self, c: &mut C, acts: &mut dyn Actions<C>, q: AST) {
+        if ! self.skolemized.insert(q) { return }
+        let (vars, body) = match c.view_as_quant(&q) {
+            QuantView::Forall{vars, body, ..} => (vars.to_vec(), *body),
+            QuantView::Other(_) => unreachable!(),
+        };
+        let mut m = FxHashMap::default();
+        for v in vars.iter() {
+            let sk = c.mk_skolem(v);
+            m.insert(*v, sk);
+        }
+        let inst = subst(c, &mut m, &body);
+        debug!("quant: skolemize {} into {}", pp_t(c, &q), pp_t(c, &inst));
+        let l_q = acts.map_lit(c, TheoryLit::new_t(q, true));
+        let l_inst = self.clausify.lit_of(c, acts, inst);
+        acts.add_lemma(&[l_q, !l_inst]);
+    }
+}
+
+impl<C: Ctx> MicroTheory<C> for EMatching {
+    fn init(_m: &mut C) -> Self {
+        EMatching {
+            quants: vec!(),
+            apps: vec!(),
+            levels: vec!(),
+            triggers: FxHashMap::default(),
+            instances: FxHashSet::default(),
+            skolemized: FxHashSet::default(),
+            clausify: Clausifier::new(),
+            gen: FxHashMap::default(),
+            by_head: FxHashMap::default(),
+        }
+    }
+
+    fn on_new_term(&mut self, c: &mut C, _cc1: &mut CC1<C>, t: &AST, _n: NodeID) {
+        match c.view_as_quant(t) {
+            QuantView::Forall{..} => self.quants.push(*t),
+            QuantView::Other(_) => {
+                if c.view(t).is_app() { self.apps.push(*t) }
+            },
+        }
+    }
+
+    fn final_check(&mut self, c: &mut C, cc1: &mut CC1<C>, acts: &mut dyn Actions<C>) {
+        if self.quants.len() == 0 { return }
+        let n_true = cc1.find_t(&c.get_bool_term(true));
+        let n_false = cc1.find_t(&c.get_bool_term(false));
+
+        // index applications by their head symbol
+        self.by_head.clear();
+        for t in self.apps.iter() {
+            if self.gen.get(t).map_or(false, |g| *g >= MAX_GENERATION) { continue }
+            if let AstView::App{f, ..} = c.view(t) {
+                self.by_head.entry(*f).or_insert(vec!()).push(*t);
+            }
+        }
+
+        let mut n_inst = 0;
+        for i in 0 .. self.quants.len() {
+            let q = self.quants[i];
+            let n = cc1.find_t(&q);
+            if n == n_true {
+                let budget = MAX_INSTANCES_PER_ROUND.saturating_sub(n_inst);
+                n_inst += self.instantiate(c, cc1, acts, q, budget);
+            } else if n == n_false {
+                self.skolemize(c, acts, q);
+            }
+        }
+        if n_inst > 0 {
+            debug!("quant: {} new instances", n_inst);
+        }
+    }
+}
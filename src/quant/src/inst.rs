@@ -0,0 +1,119 @@
+
+//! Instances of quantified formulas.
+
+use {
+    batsmt_core::{ast_u32::AST, AstView, Manager, },
+    batsmt_theory::{TheoryLit, Actions, },
+    batsmt_tseitin::View as FView,
+    fxhash::{FxHashMap, FxHashSet, },
+    crate::intf::{Ctx, QuantView, },
+};
+
+/// Clausification of instances, on the fly.
+///
+/// Instances are arbitrary formulas, but lemmas are clauses. Each boolean
+/// connective of an instance gets a pure boolean literal, defined
+/// by lemmas the first time it is met.
+pub struct Clausifier {
+    defined: FxHashSet<AST>, // connectives whose definition was added
+}
+
+impl Clausifier {
+    /// New clausifier.
+    pub fn new() -> Self { Clausifier { defined: FxHashSet::default() } }
+
+    /// Literal equivalent to the formula `t`.
+    ///
+    /// Boolean connectives are defined by lemmas, the first time they are met.
+    pub fn lit_of<C, A>(&mut self, c: &mut C, acts: &mut A, t: AST) -> C::B
+        where C: Ctx, A: Actions<C> + ?Sized
+    {
+        enum Conn { And, Or, Imply }
+        let (conn, args) = match c.view_as_formula(t) {
+            FView::Not(u) => return ! self.lit_of(c, acts, u),
+            FView::And(args) => (Conn::And, args.to_vec()),
+            FView::Or(args) => (Conn::Or, args.to_vec()),
+            FView::Imply(args) => (Conn::Imply, args.to_vec()),
+            FView::Bool(b) => {
+                let l = acts.map_lit(c, TheoryLit::new_b(t, true));
+                if self.defined.insert(t) {
+                    acts.add_lemma(&[if b { l } else { !l }]);
+                }
+                return l
+            },
+            _ => return acts.map_lit(c, TheoryLit::new_t(t, true)),
+        };
+
+        let l = acts.map_lit(c, TheoryLit::new_b(t, true));
+        if ! self.defined.insert(t) { return l }
+
+        let mut subs: Vec<C::B> = args.iter().map(|u| self.lit_of(c, acts, *u)).collect();
+        match conn {
+            Conn::And => {
+                // `l => sub_i`, and `∧_i sub_i => l`
+                for s in subs.iter() { acts.add_lemma(&[!l, *s]) }
+                let mut cl: Vec<C::B> = subs.iter().map(|s| !*s).collect();
+                cl.push(l);
+                acts.add_lemma(&cl);
+            },
+            Conn::Or | Conn::Imply => {
+                if let Conn::Imply = conn {
+                    // all but the last literal are negated
+                    let n = subs.len();
+                    for s in subs[.. n-1].iter_mut() { *s = !*s }
+                }
+                // `sub_i => l`, and `l => ∨_i sub_i`
+                for s in subs.iter() { acts.add_lemma(&[!*s, l]) }
+                subs.push(!l);
+                acts.add_lemma(&subs);
+            },
+        }
+        l
+    }
+}
+
+/// Apply the substitution `m` to `t`.
+///
+/// `m` is also used as a cache.
+pub fn subst<C: Ctx>(c: &mut C, m: &mut FxHashMap<AST, AST>, t: &AST) -> AST {
+    if let Some(u) = m.get(t) {
+        return *u
+    }
+    let u = match c.view_as_quant(t) {
+        QuantView::Forall{vars, body, patterns} => {
+            let vars = vars.to_vec();
+            let body = *body;
+            let patterns = patterns.to_vec();
+            let body2 = subst(c, m, &body);
+            if body2 == body {
+                *t
+            } else {
+                let patterns: Vec<Vec<AST>> =
+                    patterns.iter()
+                    .map(|p| p.iter().map(|u| subst(c, m, u)).collect())
+                    .collect();
+                c.mk_forall(&vars, body2, &patterns)
+            }
+        },
+        QuantView::Other(_) => {
+            let (f, args) = match c.view(t) {
+                AstView::App{f, args} => (*f, args.to_vec()),
+                _ => return *t,
+            };
+            let args2: Vec<AST> = args.iter().map(|u| subst(c, m, u)).collect();
+            if args == args2 {
+                *t
+            } else {
+                let ty = c.ty(t);
+                c.mk_app(f, &args2, ty)
+            }
+        },
+    };
+    m.insert(*t, u);
+    u
+}
+
+pub(crate) fn pp_t<'a, C: Ctx>(c: &'a C, t: &'a AST) -> impl 'a + std::fmt::Display {
+    batsmt_theory::pp_ast(c, t)
+}
+
@@ -0,0 +1,54 @@
+
+//! Interface between quantifiers and terms.
+
+use {
+    batsmt_core::ast_u32::AST,
+    batsmt_cc as cc,
+    batsmt_tseitin as tseitin,
+};
+
+/// A view of terms as quantified formulas.
+pub enum QuantView<'a, AST> {
+    /// `∀vars. body`, along with the triggers given by the user, if any.
+    Forall {
+        vars: &'a [AST],
+        body: &'a AST,
+        patterns: &'a [Vec<AST>],
+    },
+    Other(&'a AST),
+}
+
+impl<'a, AST> QuantView<'a, AST> {
+    /// Is it a quantified formula?
+    pub fn is_forall(&self) -> bool {
+        match self { QuantView::Forall{..} => true, QuantView::Other(_) => false }
+    }
+}
+
+/// The context needed for quantifiers.
+///
+/// Bound variables are ordinary constants, distinct from any other term,
+/// and quantified formulas are boolean constants that can be viewed
+/// through `view_as_quant`.
+pub trait Ctx : cc::Ctx + tseitin::Ctx {
+    /// View the term as a quantified formula, if it is.
+    fn view_as_quant<'a>(&'a self, t: &'a AST) -> QuantView<'a, AST>;
+
+    /// Build `∀vars. body`.
+    fn mk_forall(&mut self, vars: &[AST], body: AST, patterns: &[Vec<AST>]) -> AST;
+
+    /// Build a fresh constant with the same sort as the bound variable `v`.
+    fn mk_skolem(&mut self, v: &AST) -> AST;
+
+    /// Can applications of `f` be used as triggers?
+    ///
+    /// This should be false for builtin symbols such as
+    /// boolean connectives or arithmetic operators.
+    fn is_uninterpreted(&self, f: &AST) -> bool;
+
+    /// Is `ty` an uninterpreted sort?
+    ///
+    /// The classes of a model only cover the whole domain of such sorts,
+    /// unlike, say, reals, bit-vectors or datatypes.
+    fn is_uninterpreted_sort(&self, ty: &AST) -> bool;
+}
@@ -0,0 +1,32 @@
+
+//! Quantifiers, by E-matching over the congruence closure.
+//!
+//! A universally quantified formula `∀x1…xn. body` is an opaque boolean
+//! atom for the rest of the solver. When such an atom is asserted true,
+//! `EMatching` looks for instances of its triggers in the E-graph of
+//! the congruence closure, and adds the lemma `¬(∀x. body) ∨ body[x:=t]`
+//! for each substitution it finds. When it is asserted false, the
+//! negated body is skolemized once.
+//!
+//! The expected usage is to add `EMatching` to the tuple of micro-theories
+//! of the congruence closure.
+//!
+//! E-matching alone cannot tell that a model satisfies the quantified
+//! formulas. `MBQI` wraps the whole theory and checks them against its
+//! term-level model, which makes it possible to answer `SAT`.
+
+#[macro_use] extern crate log;
+
+pub mod intf;
+pub mod trigger;
+pub mod ematch;
+pub mod inst;
+pub mod mbqi;
+
+pub use {
+    crate::{
+        intf::{Ctx, QuantView, },
+        ematch::EMatching,
+        mbqi::MBQI,
+    },
+};
@@ -0,0 +1,469 @@
+
+//! Model-based quantifier instantiation.
+
+use {
+    batsmt_core::{ast_u32::AST, AstView, backtrack::Backtrackable, },
+    batsmt_theory::{Theory, TheoryLit, Actions, Trail, Model, ExplStep, },
+    batsmt_tseitin::View as FView,
+    fxhash::{FxHashMap, FxHashSet, },
+    crate::{
+        intf::{Ctx, QuantView, },
+        inst::{Clausifier, subst, pp_t, },
+    },
+};
+
+/// Maximum number of assignments tried for a quantified formula, in each round.
+const MAX_ASSIGNMENTS: usize = 10_000;
+
+/// Maximum number of instances of a quantified formula, in each round.
+const MAX_INSTANCES_PER_QUANT: usize = 10;
+
+/// Maximum number of instances in total.
+const MAX_INSTANCES: usize = 20_000;
+
+/// Model-based quantifier instantiation, on top of a theory `Th`.
+///
+/// When `Th` accepts a full model, each quantified formula `∀x. body`
+/// that is true in the SAT model is evaluated in the term-level
+/// model of `Th`. A small sub-solver enumerates assignments of `x`
+/// to the classes of the model, looking for one that makes `body` false.
+/// Such an assignment gives an instance that is added as a lemma
+/// `¬(∀x. body) ∨ body[x:=t]`.
+///
+/// Function applications that do not occur in the model have no value,
+/// so `body` might not evaluate under some assignments. If no
+/// counter-example is found, these assignments are instantiated instead,
+/// which extends the model for the next round.
+///
+/// If the budgets run out before all quantified formulas are satisfied,
+/// the model is partial (see `Theory::model_is_partial`). So is it if
+/// a variable of an interpreted sort (e.g. reals) has no counter-example,
+/// since the classes of the model only cover a part of its domain.
+pub struct MBQI<C:Ctx, Th> {
+    th: Th,
+    model: Model<C>,
+    partial: bool, // did we give up on the last model?
+    instances: FxHashSet<(AST, AST)>, // (formula, instance) already added
+    clausify: Clausifier,
+    witnesses: FxHashMap<AST, AST>, // bound variable -> witness, for empty sorts
+}
+
+/// Actions that record whether a lemma was added.
+struct Watch<'a, A> {
+    acts: &'a mut A,
+    new_lemma: bool,
+}
+
+/// Result of evaluating a formula under an assignment.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+enum Value {
+    True,
+    False,
+    Undefined,
+}
+
+/// Search for assignments that falsify formulas, in a term-level model.
+///
+/// Each class of the model is a domain element, designated by its
+/// representative.
+struct SubSolver<'a, C:Ctx> {
+    c: &'a C,
+    model: &'a Model<C>,
+    true_: Option<AST>, // representative of `true`
+    false_: Option<AST>, // representative of `false`
+    domains: FxHashMap<AST, Vec<AST>>, // sort -> its classes
+    apps: FxHashMap<(AST, Vec<AST>), AST>, // `f(classes)` -> class
+}
+
+impl<C:Ctx, Th:Theory<C>> MBQI<C, Th> {
+    /// Add model-based instantiation to the theory `th`.
+    pub fn new(th: Th) -> Self {
+        MBQI {
+            th, model: Model::new(), partial: false, instances: FxHashSet::default(),
+            clausify: Clausifier::new(), witnesses: FxHashMap::default(),
+        }
+    }
+
+    /// Access the underlying theory.
+    pub fn th(&self) -> &Th { &self.th }
+
+    /// Number of instances added so far.
+    pub fn n_instances(&self) -> usize { self.instances.len() }
+
+    /// Check quantified formulas against `self.model`.
+    fn check_model<A: Actions<C>>(&mut self, c: &mut C, acts: &mut A) {
+        let t_true = c.get_bool_term(true);
+        let r_true = self.model.repr(&t_true).cloned().unwrap_or(t_true);
+        let mut quants: Vec<AST> =
+            self.model.iter_repr()
+            .filter(|(t, r)| **r == r_true && c.view_as_quant(t).is_forall())
+            .map(|(t,_)| *t)
+            .collect();
+        if quants.len() == 0 { return }
+        quants.sort_unstable();
+        debug!("mbqi: check {} quantified formula(s)", quants.len());
+
+        // find instances, using the model
+        let mut todo: Vec<(AST, Vec<Option<AST>>)> = vec!();
+        {
+            let sub = SubSolver::new(c, &self.model);
+            for q in quants.iter() {
+                let (vars, body) = match c.view_as_quant(q) {
+                    QuantView::Forall{vars, body, ..} => (vars, body),
+                    QuantView::Other(_) => unreachable!(),
+                };
+                let (assigns, complete) = sub.search(vars, body, &self.witnesses);
+                if ! complete { self.partial = true }
+                todo.extend(assigns.into_iter().map(|a| (*q, a)));
+            }
+        }
+
+        let mut n = 0;
+        for (q, assign) in todo {
+            if self.instances.len() >= MAX_INSTANCES {
+                info!("mbqi: too many instances, give up");
+                self.partial = true;
+                break
+            }
+            let (vars, body) = match c.view_as_quant(&q) {
+                QuantView::Forall{vars, body, ..} => (vars.to_vec(), *body),
+                QuantView::Other(_) => unreachable!(),
+            };
+            let mut m = FxHashMap::default();
+            for (v, u) in vars.iter().zip(assign.iter()) {
+                // a sort without any class: use a witness instead
+                let u = match u {
+                    Some(u) => *u,
+                    None => match self.witnesses.get(v) {
+                        Some(w) => *w,
+                        None => {
+                            let w = c.mk_skolem(v);
+                            self.witnesses.insert(*v, w);
+                            w
+                        },
+                    },
+                };
+                m.insert(*v, u);
+            }
+            let inst = subst(c, &mut m, &body);
+            if ! self.instances.insert((q, inst)) {
+                // already added, but the model does not satisfy it yet
+                self.partial = true;
+                continue
+            }
+            debug!("mbqi: instance {}", pp_t(c, &inst));
+            n += 1;
+            let l_q = acts.map_lit(c, TheoryLit::new_t(q, true));
+            let l_inst = self.clausify.lit_of(c, acts, inst);
+            acts.add_lemma(&[!l_q, l_inst]);
+        }
+        if n > 0 {
+            // the next model will be checked again
+            self.partial = false;
+            debug!("mbqi: {} new instances", n);
+        }
+    }
+}
+
+impl<'a, C:Ctx> SubSolver<'a, C> {
+    fn new(c: &'a C, model: &'a Model<C>) -> Self {
+        let mut domains: FxHashMap<AST, Vec<AST>> = FxHashMap::default();
+        let mut apps = FxHashMap::default();
+        for (t, r) in model.iter_repr() {
+            if let Some(ty) = c.ty(t) {
+                domains.entry(ty).or_insert(vec!()).push(*r);
+            }
+            if let AstView::App{f, args} = c.view(t) {
+                let args: Option<Vec<AST>> = args.iter().map(|u| model.repr(u).cloned()).collect();
+                if let Some(args) = args {
+                    apps.insert((*f, args), *r);
+                }
+            }
+        }
+        for d in domains.values_mut() {
+            d.sort_unstable();
+            d.dedup();
+        }
+        let true_ = model.repr(&c.get_bool_term(true)).cloned();
+        let false_ = model.repr(&c.get_bool_term(false)).cloned();
+        SubSolver { c, model, true_, false_, domains, apps }
+    }
+
+    /// Find assignments of `vars` under which `body` is false, or else
+    /// undefined.
+    ///
+    /// `None` stands for a variable whose sort has no class.
+    /// Also returns `false` if the search was not exhaustive, or if
+    /// the classes do not cover the domain of some variable's sort.
+    fn search(
+        &self, vars: &[AST], body: &AST, witnesses: &FxHashMap<AST, AST>
+    ) -> (Vec<Vec<Option<AST>>>, bool) {
+        // a sort without any class is interpreted by the witness
+        // of the variable, once it exists.
+        let mut doms: Vec<&[AST]> = vec!();
+        let mut missing = false;
+        for v in vars.iter() {
+            match self.c.ty(v).and_then(|ty| self.domains.get(&ty)) {
+                Some(d) => doms.push(d),
+                None => match witnesses.get(v) {
+                    Some(w) => doms.push(std::slice::from_ref(w)),
+                    None => { missing = true; doms.push(&[]) },
+                },
+            }
+        }
+        if missing {
+            let assign = doms.iter().map(|d| d.first().cloned()).collect();
+            return (vec!(assign), true)
+        }
+        // only uninterpreted sorts and booleans are covered by the classes
+        let bool_ = self.c.ty(&self.c.get_bool_term(true));
+        let covered = vars.iter().zip(doms.iter()).all(|(v, d)| {
+            match self.c.ty(v) {
+                Some(ty) if Some(ty) == bool_ => {
+                    self.true_.map_or(false, |t| d.contains(&t)) &&
+                    self.false_.map_or(false, |f| d.contains(&f))
+                },
+                Some(ty) => self.c.is_uninterpreted_sort(&ty),
+                None => false,
+            }
+        });
+
+        let mut falsified = vec!();
+        let mut undefined = vec!();
+        let mut idx = vec![0; vars.len()];
+        let mut sigma: FxHashMap<AST, AST> = FxHashMap::default();
+        let mut n = 0;
+        loop {
+            if falsified.len() >= MAX_INSTANCES_PER_QUANT {
+                return (falsified, true)
+            } else if n >= MAX_ASSIGNMENTS {
+                let res = if falsified.len() > 0 { falsified } else { undefined };
+                return (res, false)
+            }
+            n += 1;
+            for (i, v) in vars.iter().enumerate() {
+                sigma.insert(*v, doms[i][idx[i]]);
+            }
+            let assign = || vars.iter().map(|v| sigma.get(v).cloned()).collect();
+            match self.eval_formula(&sigma, body) {
+                Value::False => falsified.push(assign()),
+                Value::Undefined if undefined.len() < MAX_INSTANCES_PER_QUANT => undefined.push(assign()),
+                _ => (),
+            }
+
+            // next assignment
+            let mut i = 0;
+            loop {
+                if i == vars.len() {
+                    // exhausted all assignments
+                    let res = if falsified.len() > 0 { falsified } else { undefined };
+                    return (res, covered)
+                }
+                idx[i] += 1;
+                if idx[i] < doms[i].len() { break }
+                idx[i] = 0;
+                i += 1;
+            }
+        }
+    }
+
+    fn bool_value(&self, r: Option<AST>) -> Value {
+        match r {
+            Some(r) if Some(r) == self.true_ => Value::True,
+            Some(r) if Some(r) == self.false_ => Value::False,
+            _ => Value::Undefined,
+        }
+    }
+
+    /// Evaluate the formula `t` under `sigma`.
+    fn eval_formula(&self, sigma: &FxHashMap<AST, AST>, t: &AST) -> Value {
+        let c = self.c;
+        match c.view_as_formula(*t) {
+            FView::Bool(true) => Value::True,
+            FView::Bool(false) => Value::False,
+            FView::Not(u) => match self.eval_formula(sigma, &u) {
+                Value::True => Value::False,
+                Value::False => Value::True,
+                Value::Undefined => Value::Undefined,
+            },
+            FView::And(args) => {
+                let mut res = Value::True;
+                for u in args.iter() {
+                    match self.eval_formula(sigma, u) {
+                        Value::False => return Value::False,
+                        Value::Undefined => res = Value::Undefined,
+                        Value::True => (),
+                    }
+                }
+                res
+            },
+            FView::Or(args) => {
+                let mut res = Value::False;
+                for u in args.iter() {
+                    match self.eval_formula(sigma, u) {
+                        Value::True => return Value::True,
+                        Value::Undefined => res = Value::Undefined,
+                        Value::False => (),
+                    }
+                }
+                res
+            },
+            FView::Imply(args) => {
+                // `a1 => … => an => b`
+                let n = args.len();
+                let mut res = self.eval_formula(sigma, &args[n-1]);
+                for u in args[.. n-1].iter() {
+                    match (self.eval_formula(sigma, u), res) {
+                        (Value::False, _) => return Value::True,
+                        (_, Value::True) => (),
+                        (Value::Undefined, _) => res = Value::Undefined,
+                        (Value::True, _) => (),
+                    }
+                }
+                res
+            },
+            FView::Eq(a, b) => {
+                match (self.eval_term(sigma, &a), self.eval_term(sigma, &b)) {
+                    (Some(a), Some(b)) => if a == b { Value::True } else { Value::False },
+                    _ => Value::Undefined,
+                }
+            },
+            FView::Distinct(args) => {
+                let vals: Option<Vec<AST>> = args.iter().map(|u| self.eval_term(sigma, u)).collect();
+                match vals {
+                    Some(mut vals) => {
+                        let n = vals.len();
+                        vals.sort_unstable();
+                        vals.dedup();
+                        if vals.len() == n { Value::True } else { Value::False }
+                    },
+                    None => Value::Undefined,
+                }
+            },
+            FView::Ite(a, b, c) => match self.eval_formula(sigma, &a) {
+                Value::True => self.eval_formula(sigma, &b),
+                Value::False => self.eval_formula(sigma, &c),
+                Value::Undefined => Value::Undefined,
+            },
+            FView::TyBool | FView::Atom(_) => self.bool_value(self.eval_term(sigma, t)),
+        }
+    }
+
+    /// Evaluate `t` under `sigma`, into a class of the model.
+    fn eval_term(&self, sigma: &FxHashMap<AST, AST>, t: &AST) -> Option<AST> {
+        if let Some(r) = sigma.get(t) {
+            return Some(*r)
+        } else if let Some(r) = self.model.repr(t) {
+            return Some(*r)
+        }
+        let c = self.c;
+        match c.view_as_formula(*t) {
+            FView::Atom(_) => (),
+            _ if c.ty(t) == c.ty(&c.get_bool_term(true)) => {
+                // boolean connective
+                return match self.eval_formula(sigma, t) {
+                    Value::True => self.true_,
+                    Value::False => self.false_,
+                    Value::Undefined => None,
+                }
+            },
+            FView::Ite(a, b, c) => {
+                return match self.eval_formula(sigma, &a) {
+                    Value::True => self.eval_term(sigma, &b),
+                    Value::False => self.eval_term(sigma, &c),
+                    Value::Undefined => None,
+                }
+            },
+            _ => (),
+        }
+        if c.view_as_quant(t).is_forall() {
+            return None // nested quantifier
+        }
+        match c.view(t) {
+            AstView::App{f, args} => {
+                let args: Option<Vec<AST>> = args.iter().map(|u| self.eval_term(sigma, u)).collect();
+                args.and_then(|args| self.apps.get(&(*f, args)).cloned())
+            },
+            _ => None,
+        }
+    }
+}
+
+impl<C:Ctx, Th:Theory<C>> Backtrackable<C> for MBQI<C, Th> {
+    fn push_level(&mut self, c: &mut C) { self.th.push_level(c) }
+    fn pop_levels(&mut self, c: &mut C, n: usize) { self.th.pop_levels(c, n) }
+}
+
+impl<C:Ctx, Th:Theory<C>> Theory<C> for MBQI<C, Th> {
+    fn final_check<A>(&mut self, c: &mut C, acts: &mut A, trail: &Trail<C>)
+        where A: Actions<C>
+    {
+        self.partial = false;
+        let new_lemma = {
+            let mut w = Watch{acts, new_lemma: false};
+            self.th.final_check(c, &mut w, trail);
+            w.new_lemma
+        };
+        if acts.has_conflict() || new_lemma { return }
+
+        self.model.clear();
+        self.th.build_model(c, &mut self.model);
+        self.check_model(c, acts);
+    }
+
+    fn partial_check<A>(&mut self, c: &mut C, acts: &mut A, trail: &Trail<C>)
+        where A: Actions<C>
+    {
+        self.th.partial_check(c, acts, trail)
+    }
+
+    fn has_partial_check() -> bool { Th::has_partial_check() }
+
+    fn add_literal(&mut self, c: &mut C, t: AST, lit: C::B) {
+        self.th.add_literal(c, t, lit)
+    }
+
+    fn explain_propagation(&mut self, c: &mut C, t: AST, sign: bool, p: C::B) -> &[C::B] {
+        self.th.explain_propagation(c, t, sign, p)
+    }
+
+    fn enable_propagation(&mut self, on: bool) { self.th.enable_propagation(on) }
+
+    fn enable_proofs(&mut self, on: bool) { self.th.enable_proofs(on) }
+
+    fn build_model(&mut self, c: &mut C, model: &mut Model<C>) {
+        self.th.build_model(c, model)
+    }
+
+    fn model_is_partial(&self) -> bool {
+        self.partial || self.th.model_is_partial()
+    }
+
+    fn iter_interface_terms<F>(&mut self, c: &C, f: F) where F: FnMut(&AST) {
+        self.th.iter_interface_terms(c, f)
+    }
+}
+
+impl<'a, C:Ctx, A:Actions<C>> Actions<C> for Watch<'a, A> {
+    fn add_lemma(&mut self, c: &[C::B]) {
+        self.new_lemma = true;
+        self.acts.add_lemma(c)
+    }
+
+    fn propagate(&mut self, p: C::B) -> bool { self.acts.propagate(p) }
+
+    fn raise_conflict(&mut self, c: &[C::B], costly: bool) {
+        self.acts.raise_conflict(c, costly)
+    }
+
+    fn map_lit(&mut self, m: &C, lit: TheoryLit<C>) -> C::B { self.acts.map_lit(m, lit) }
+
+    fn add_split(&mut self, p: C::B) {
+        self.new_lemma = true;
+        self.acts.add_split(p)
+    }
+
+    fn justify(&mut self, expl: &[ExplStep<AST, C::B>]) { self.acts.justify(expl) }
+
+    fn has_conflict(&self) -> bool { self.acts.has_conflict() }
+}
@@ -0,0 +1,154 @@
+
+//! Triggers, and their inference.
+//!
+//! A trigger is a list of terms (a multi-pattern) that, together,
+//! contain all the variables of a quantified formula. Each instance
+//! of the trigger in the E-graph gives a substitution for the variables.
+
+use {
+    batsmt_core::{ast_u32::AST, AstView, },
+    fxhash::{FxHashMap, FxHashSet, },
+    crate::intf::Ctx,
+};
+
+/// A pattern, compiled from a term of a trigger.
+#[derive(Clone,Debug)]
+pub enum Pat {
+    /// The `i`-th bound variable.
+    Var(usize),
+    /// A term without variables, matched up to congruence.
+    Ground(AST),
+    /// `f(p1…pn)`.
+    App(AST, Vec<Pat>),
+}
+
+impl Pat {
+    /// Head symbol, if this is an application.
+    pub fn head(&self) -> Option<&AST> {
+        match self {
+            Pat::App(f, _) => Some(f),
+            _ => None,
+        }
+    }
+}
+
+/// Compile the term `t` into a pattern, where `vars` are the bound variables.
+pub fn compile<C: Ctx>(c: &C, vars: &[AST], t: &AST) -> Pat {
+    let mut fv = FreeVars::new(vars);
+    compile_rec(c, &mut fv, t)
+}
+
+fn compile_rec<C: Ctx>(c: &C, fv: &mut FreeVars, t: &AST) -> Pat {
+    if let Some(i) = fv.vars.iter().position(|v| v == t) {
+        return Pat::Var(i)
+    }
+    if fv.get(c, t).is_empty() {
+        return Pat::Ground(*t)
+    }
+    match c.view(t) {
+        AstView::App{f, args} => {
+            Pat::App(*f, args.iter().map(|u| compile_rec(c, fv, u)).collect())
+        },
+        _ => Pat::Ground(*t),
+    }
+}
+
+/// Compute the sets of variables in terms, with caching.
+struct FreeVars<'a> {
+    vars: &'a [AST],
+    cache: FxHashMap<AST, Vec<usize>>,
+}
+
+impl<'a> FreeVars<'a> {
+    fn new(vars: &'a [AST]) -> Self {
+        FreeVars { vars, cache: FxHashMap::default() }
+    }
+
+    /// Indices of the variables occurring in `t`, sorted.
+    fn get<C: Ctx>(&mut self, c: &C, t: &AST) -> Vec<usize> {
+        if let Some(v) = self.cache.get(t) {
+            return v.clone()
+        }
+        let mut res = vec!();
+        if let Some(i) = self.vars.iter().position(|v| v == t) {
+            res.push(i);
+        } else if let AstView::App{args, ..} = c.view(t) {
+            for u in args.iter() {
+                res.extend(self.get(c, u));
+            }
+            res.sort_unstable();
+            res.dedup();
+        }
+        self.cache.insert(*t, res.clone());
+        res
+    }
+}
+
+/// Does the trigger `tr` contain all the variables `vars`?
+pub fn covers<C: Ctx>(c: &C, vars: &[AST], tr: &[AST]) -> bool {
+    let mut fv = FreeVars::new(vars);
+    let mut covered = vec![false; vars.len()];
+    for t in tr.iter() {
+        for i in fv.get(c, t) { covered[i] = true }
+    }
+    covered.iter().all(|b| *b)
+}
+
+/// Infer triggers for `∀vars. body`.
+///
+/// Candidates are the applications of uninterpreted symbols that
+/// contain at least one variable. If some candidates contain all the
+/// variables, the minimal ones (that have no such candidate as a subterm)
+/// are each a trigger. Otherwise, a single multi-trigger is built
+/// greedily from the candidates with the most variables.
+///
+/// The result is empty if some variable only occurs under
+/// interpreted symbols.
+pub fn infer<C: Ctx>(c: &C, vars: &[AST], body: &AST) -> Vec<Vec<AST>> {
+    let mut fv = FreeVars::new(vars);
+    let mut cands: Vec<(AST, Vec<usize>)> = vec!();
+    let mut seen = FxHashSet::default();
+    let mut todo = vec!(*body);
+    while let Some(t) = todo.pop() {
+        if ! seen.insert(t) { continue }
+        if let AstView::App{f, args} = c.view(&t) {
+            todo.extend_from_slice(args);
+            if c.is_uninterpreted(f) {
+                let vs = fv.get(c, &t);
+                if vs.len() > 0 { cands.push((t, vs)) }
+            }
+        }
+    }
+
+    // does `u` occur in `t`?
+    fn occurs<C: Ctx>(c: &C, u: &AST, t: &AST) -> bool {
+        u == t || match c.view(t) {
+            AstView::App{args, ..} => args.iter().any(|t2| occurs(c, u, t2)),
+            _ => false,
+        }
+    }
+
+    let full: Vec<AST> =
+        cands.iter()
+        .filter(|(_, vs)| vs.len() == vars.len())
+        .map(|(t,_)| *t)
+        .collect();
+    if full.len() > 0 {
+        return full.iter()
+            .filter(|t| ! full.iter().any(|u| u != *t && occurs(c, u, t)))
+            .map(|t| vec!(*t))
+            .collect()
+    }
+
+    // multi-trigger
+    cands.sort_by_key(|(t, vs)| (std::cmp::Reverse(vs.len()), *t));
+    let mut covered = vec![false; vars.len()];
+    let mut multi = vec!();
+    for (t, vs) in cands.iter() {
+        if vs.iter().any(|i| ! covered[*i]) {
+            for i in vs.iter() { covered[*i] = true }
+            multi.push(*t);
+        }
+    }
+    if covered.iter().all(|b| *b) { vec!(multi) } else { vec!() }
+}
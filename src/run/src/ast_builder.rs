@@ -1,17 +1,24 @@
 
 use {
+    std::rc::Rc,
     batsmt_parser as parser,
     batsmt_core::{ast_u32::AST, },
+    batsmt_lra::{self as lra, Ctx as LRACtx, },
+    batsmt_cc::HasDatatype,
+    batsmt_theory::{self as theory, HasEq, },
+    batsmt_tseitin::{Ctx as TseitinCtx, View as FView, },
+    batsmt_quant::{self as quant, Ctx as QuantCtx, QuantView, },
     fxhash::FxHashMap,
-    crate::{parser::Atom, Ctx, },
+    crate::{parser::Atom, Ctx, Decl, },
 };
 
 /// AST builder for the parser
+///
+/// The parser checks that symbols are not declared twice in the same scope,
+/// each declaration makes a new symbol.
 pub struct AstBuilder<'a> {
     m: &'a mut Ctx,
     b: crate::Builtins,
-    sorts: FxHashMap<Atom, (AST, u8)>,
-    funs: FxHashMap<Atom, (AST, Vec<AST>, AST)>, // sort
 }
 
 mod ast_builder {
@@ -21,11 +28,18 @@ mod ast_builder {
         /// Create an AST builder that uses the given manager.
         pub fn new(m: &'a mut Ctx) -> Self {
             let b = m.builtins();
-            Self {
-                m, b,
-                funs: FxHashMap::default(),
-                sorts: FxHashMap::default(),
-            }
+            Self { m, b, }
+        }
+
+        /// Access the underlying context.
+        pub fn ctx(&mut self) -> &mut Ctx { self.m }
+
+        /// Replace the context with a fresh one, and forget all declarations.
+        ///
+        /// All the ASTs built so far become invalid.
+        pub fn reset(&mut self) {
+            *self.m = Ctx::new();
+            self.b = self.m.builtins();
         }
     }
 
@@ -34,20 +48,47 @@ mod ast_builder {
 
         fn get_bool(&self) -> AST { self.b.bool_ }
 
+        fn get_real(&self) -> AST { self.b.real }
+
+        fn get_array(&mut self, idx: AST, val: AST) -> AST { self.m.mk_array_sort(idx, val) }
+
+        fn get_bv(&mut self, width: u32) -> AST { self.m.mk_bv_sort(width) }
+
         fn declare_sort(&mut self, s: Atom, arity: u8) -> AST {
             debug!("declare sort {:?} arity {}", &s, arity);
-            if self.sorts.contains_key(&s) {
-                panic!("sort {:?} already declared", &s);
-            } else {
-                let ast = self.m.m.mk_str(&s, None);
-                self.sorts.insert(s, (ast, arity));
-                ast
+            let ast = self.m.m.mk_str(&s, None);
+            if arity == 0 {
+                self.m.add_sort(ast);
+            }
+            ast
+        }
+
+        fn app_sort(&mut self, s: &AST, args: &[AST]) -> AST {
+            let ty = self.m.m.mk_app(*s, args, None);
+            if ! self.m.sorts().contains(&ty) {
+                self.m.add_sort(ty);
             }
+            ty
         }
+
+        fn view_sort(&self, s: &AST) -> parser::SortView<AST> {
+            if *s == self.b.bool_ { parser::SortView::Bool }
+            else if *s == self.b.real { parser::SortView::Real }
+            else if let Some(w) = self.m.bv_sort_width(s) { parser::SortView::BitVec(w) }
+            else if let Some((idx, val)) = self.m.view_array_sort(s) { parser::SortView::Array(idx, val) }
+            else { parser::SortView::Other }
+        }
+
+        fn pp_sort(&self, s: &AST) -> String { theory::pp_ast(&*self.m, s).to_string() }
     }
 
     #[derive(Clone,Debug)]
-    pub struct Fun { f: AST, ty_ret: AST }
+    pub struct Fun {
+        f: AST,
+        ty_ret: AST,
+        tester: Option<AST>, // `is-c` for this constructor
+        def: Option<Rc<(Vec<AST>, AST)>>, // parameters and body, for `define-fun`
+    }
 
     impl<'a> parser::TermBuilder for AstBuilder<'a> {
         type Term = AST;
@@ -56,50 +97,185 @@ mod ast_builder {
 
         fn var(&mut self, v: AST) -> AST { v }
 
+        fn sort_of(&self, t: &AST) -> AST {
+            self.m.m.ty(t).expect("term without a sort")
+        }
+
         fn app_op(&mut self, op: parser::BuiltinOp, args: &[AST]) -> AST {
             use crate::parser::BuiltinOp::*;
-            let f = match op {
-                True => self.b.true_,
-                False => self.b.false_,
-                Imply => self.b.imply_,
-                And => self.b.and_,
-                Or => self.b.or_,
-                Eq => self.b.eq,
-                Not => self.b.not_,
-                Distinct => self.b.distinct,
+            let (f, ty) = match op {
+                True => (self.b.true_, self.b.bool_),
+                False => (self.b.false_, self.b.bool_),
+                Imply => (self.b.imply_, self.b.bool_),
+                And => (self.b.and_, self.b.bool_),
+                Or => (self.b.or_, self.b.bool_),
+                Eq => (self.b.eq, self.b.bool_),
+                Not => (self.b.not_, self.b.bool_),
+                Distinct => (self.b.distinct, self.b.bool_),
+                Add => (self.b.add, self.b.real),
+                Sub => (self.b.sub, self.b.real),
+                Mul => (self.b.mul, self.b.real),
+                Div => (self.b.div, self.b.real),
+                Leq => (self.b.leq, self.b.bool_),
+                Lt => (self.b.lt, self.b.bool_),
+                Geq => (self.b.geq, self.b.bool_),
+                Gt => (self.b.gt, self.b.bool_),
+                Select | Store => {
+                    // the sort depends on the array
+                    let s = args.get(0).and_then(|a| self.m.m.ty(a));
+                    let (_, val) = match s.and_then(|s| self.m.view_array_sort(&s)) {
+                        Some(p) => p,
+                        None => panic!("{:?} expects an array as first argument", op),
+                    };
+                    match op {
+                        Select => (self.b.select, val),
+                        _ => (self.b.store, s.unwrap()),
+                    }
+                },
+                BvUlt | BvUle | BvUgt | BvUge | BvSlt | BvSle | BvSgt | BvSge => {
+                    let f = match op {
+                        BvUlt => self.b.bvult, BvUle => self.b.bvule,
+                        BvUgt => self.b.bvugt, BvUge => self.b.bvuge,
+                        BvSlt => self.b.bvslt, BvSle => self.b.bvsle,
+                        BvSgt => self.b.bvsgt, _ => self.b.bvsge,
+                    };
+                    (f, self.b.bool_)
+                },
+                BvAdd | BvSub | BvNeg | BvMul | BvAnd | BvOr | BvXor | BvNot => {
+                    let f = match op {
+                        BvAdd => self.b.bvadd, BvSub => self.b.bvsub,
+                        BvNeg => self.b.bvneg, BvMul => self.b.bvmul,
+                        BvAnd => self.b.bvand, BvOr => self.b.bvor,
+                        BvXor => self.b.bvxor, _ => self.b.bvnot,
+                    };
+                    // same sort as the arguments
+                    match args.get(0).and_then(|a| self.m.m.ty(a)) {
+                        Some(ty) => (f, ty),
+                        None => panic!("{:?} expects bit-vector arguments", op),
+                    }
+                },
+                Concat => {
+                    let width: u32 = args.iter().map(|a| {
+                        match self.m.m.ty(a).and_then(|ty| self.m.bv_sort_width(&ty)) {
+                            Some(w) => w,
+                            None => panic!("concat expects bit-vector arguments"),
+                        }
+                    }).sum();
+                    (self.b.concat, self.m.mk_bv_sort(width))
+                },
+                Extract(hi, lo) => {
+                    assert_eq!(args.len(), 1, "extract expects one argument");
+                    return self.m.mk_extract(hi, lo, args[0])
+                },
             };
-            self.m.m.mk_app(f, args, Some(self.b.bool_))
+            match op {
+                Leq | Lt | Geq | Gt if args.len() > 2 => {
+                    // chainable: `(< a b c)` is `(and (< a b) (< b c))`
+                    let pairs: Vec<AST> =
+                        args.windows(2)
+                        .map(|p| self.m.m.mk_app(f, p, Some(ty)))
+                        .collect();
+                    self.m.m.mk_app(self.b.and_, &pairs, Some(ty))
+                },
+                _ => self.m.m.mk_app(f, args, Some(ty)),
+            }
         }
 
-        fn declare_fun(&mut self, f: Atom, args: &[AST], ret: AST) -> Self::Fun {
-            if self.funs.contains_key(&f) {
-                panic!("fun {:?} already declared", &f);
-            } else {
-                let ty = if args.len() == 0 { Some(ret) } else { None };
-                let ast = self.m.m.mk_str(&*f, ty);
-                let args = args.iter().map(|t| t.clone()).collect();
-                self.funs.insert(f, (ast, args, ret));
-                Fun {f: ast, ty_ret: ret}
+        fn numeral(&mut self, n: Atom) -> AST {
+            match lra::parse_numeral(&n) {
+                Some(q) => self.m.mk_numeral(&q),
+                None => panic!("invalid numeral {:?}", &n),
             }
         }
 
+        fn bv_const(&mut self, bits: &[bool]) -> AST { self.m.mk_bv_const(bits) }
+
+        fn declare_fun(&mut self, f: Atom, args: &[AST], ret: AST) -> Self::Fun {
+            let ty = if args.len() == 0 { Some(ret) } else { None };
+            let ast = self.m.m.mk_str(&*f, ty);
+            let args: Vec<_> = args.iter().map(|t| t.clone()).collect();
+            self.m.add_decl(Decl{f: ast, args, ret});
+            Fun {f: ast, ty_ret: ret, tester: None, def: None}
+        }
+
+        fn define_fun(&mut self, f: Atom, vars: &[AST], ret: AST, body: AST) -> Self::Fun {
+            // not a real function, see `app_fun`
+            let ast = self.m.m.mk_str(&*f, None);
+            Fun {f: ast, ty_ret: ret, tester: None, def: Some(Rc::new((vars.to_vec(), body)))}
+        }
+
         fn declare_cstor(&mut self, f: Atom, args: &[AST], ret: AST) -> Self::Fun {
             let f = self.declare_fun(f, args, ret);
             self.m.set_cstor(&f.f);
             f
         }
 
+        fn declare_selector(&mut self, s: Atom, c: &Fun, idx: u32, ty: AST, ret: AST) -> Self::Fun {
+            let f = self.declare_fun(s, &[ty], ret);
+            self.m.add_selector(f.f, c.f, idx, ret);
+            f
+        }
+
+        fn declare_tester(&mut self, s: Atom, c: &Fun, _ty: AST) -> Self::Fun {
+            // not a real function, see `app_fun`
+            let ast = self.m.m.mk_str(&*s, None);
+            Fun {f: ast, ty_ret: self.b.bool_, tester: Some(c.f), def: None}
+        }
+
+        fn declare_datatype(&mut self, ty: AST, cstors: &[Fun]) {
+            self.m.add_datatype(ty, cstors.iter().map(|f| f.f).collect());
+        }
+
         fn ite(&mut self, a: AST, b: AST, c: AST) -> AST {
             let f = self.b.ite;
             self.m.m.mk_app(f, &[a,b,c], self.m.m.ty(&b))
         }
 
         fn app_fun(&mut self, f: Self::Fun, args: &[AST]) -> AST {
+            if let Some(c) = f.tester {
+                // `is-c(t)` is `t = c(select-c-1(t), …)`
+                assert_eq!(args.len(), 1, "tester expects one argument");
+                let u = self.m.mk_cstor_app(&c, &args[0]);
+                return self.m.mk_eq(&args[0], &u)
+            }
+            if let Some(def) = &f.def {
+                // expand the macro
+                let (vars, body) = &**def;
+                let mut m: FxHashMap<AST, AST> =
+                    vars.iter().cloned().zip(args.iter().cloned()).collect();
+                return quant::inst::subst(self.m, &mut m, body)
+            }
             self.m.m.mk_app(f.f, args, Some(f.ty_ret))
         }
 
         fn bind(&mut self, _v: Atom, t: AST) -> AST { t }
 
         fn let_(&mut self, _: &[(AST,AST)], body: AST) -> AST { body }
+
+        fn bound_var(&mut self, name: Atom, ty: AST) -> AST {
+            self.m.mk_bound_var(&name, ty)
+        }
+
+        fn quant(&mut self, q: parser::Quant, vars: &[AST], body: AST, pats: &[Vec<AST>]) -> AST {
+            match q {
+                parser::Quant::Forall => {
+                    // flatten `∀x. ∀y. body` into `∀x y. body`
+                    let (vars, body) = match self.m.view_as_quant(&body) {
+                        QuantView::Forall{vars: vars2, body: body2, patterns} if pats.len() == 0 && patterns.len() == 0 => {
+                            let vars: Vec<AST> = vars.iter().chain(vars2.iter()).cloned().collect();
+                            (vars, *body2)
+                        },
+                        _ => (vars.to_vec(), body),
+                    };
+                    self.m.mk_forall(&vars, body, pats)
+                },
+                parser::Quant::Exists => {
+                    // `∃x. body` is `¬∀x. ¬body`
+                    let body = self.m.mk_formula(FView::Not(body));
+                    let t = self.m.mk_forall(vars, body, pats);
+                    self.m.mk_formula(FView::Not(t))
+                },
+            }
+        }
     }
 }
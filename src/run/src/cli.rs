@@ -0,0 +1,182 @@
+
+//! Command-line arguments of `batsmt-run`.
+
+use {
+    std::{env, fmt, },
+    batsmt_solver::SolverConfig,
+};
+
+const USAGE: &str = "\
+usage: batsmt-run [options] [file…]
+
+Reads SMT-LIB statements from each file in turn, or from stdin if there
+is none. Files with the extension `.cnf` are DIMACS problems, solved
+without any theory. The exit code is 10 if the last `check-sat`
+returned `sat`, 20 if it returned `unsat`, and 0 otherwise.
+
+options:
+  --timeout SECS      time limit for each `check-sat` (default: $TIMEOUT)
+  --stats             print statistics after each file
+  --stats-json        print statistics after each file, as JSON
+  --model             print the model after each `sat` answer
+  --proof FILE        write the proof into FILE as the solver goes; each
+                      `unsat` answer ends with a refutation
+  --seed N            seed of the SAT solver's random choices
+  --incremental       print `success` after each command, for interactive use
+  --format FMT        input format, `smt2` or `dimacs` (default: from the extension)
+  --cc IMPL           congruence closure, `fast` or `naive` (default: fast);
+                      `naive` rejects arrays, datatypes, quantifiers and
+                      non-boolean `ite`
+  --check-model       check each model against the assertions, and fail if
+                      one of them is false
+  --log LEVEL         logging level (default: $RUST_LOG)
+  --KEY VALUE         any other solver option, see below
+  -h, --help          print this message
+";
+
+/// Format of the input files.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum Format {
+    Smt2,
+    Dimacs,
+}
+
+/// Implementation of congruence closure.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum CCImpl {
+    Fast,
+    Naive,
+}
+
+/// Parsed command-line arguments.
+#[derive(Clone,Debug)]
+pub struct Args {
+    pub files: Vec<String>,
+    pub format: Option<Format>,
+    pub timeout: Option<f64>,
+    pub stats: bool,
+    pub stats_json: bool,
+    pub model: bool,
+    pub proof: Option<String>,
+    pub produce_proofs: bool, // set by `(set-option :produce-proofs true)`
+    pub incremental: bool,
+    pub cc: CCImpl,
+    pub check_model: bool,
+    pub log: Option<String>,
+    pub conf: SolverConfig,
+    pub help: bool,
+}
+
+/// Error in the command line.
+#[derive(Debug)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        write!(out, "{}\n(see `batsmt-run --help`)", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Args {
+    fn new() -> Self {
+        Args {
+            files: vec!(), format: None, timeout: None, stats: false, stats_json: false,
+            model: false, proof: None, produce_proofs: false, incremental: false, cc: CCImpl::Fast,
+            check_model: false, log: None, conf: SolverConfig::new(), help: false,
+        }
+    }
+
+    /// Usage, with the list of solver options.
+    pub fn usage() -> String {
+        let conf = SolverConfig::new();
+        let mut s = USAGE.to_string();
+        s.push_str("\nsolver options (KEY and its default value):\n");
+        for k in SolverConfig::KEYS {
+            s.push_str(&format!("  --{:18}{}\n", k, conf.get(k).unwrap()));
+        }
+        s
+    }
+
+    /// Format of `file` (`None` for stdin).
+    pub fn format_of(&self, file: Option<&str>) -> Format {
+        match (self.format, file) {
+            (Some(f), _) => f,
+            (None, Some(file)) if file.ends_with(".cnf") => Format::Dimacs,
+            (None, _) => Format::Smt2,
+        }
+    }
+
+    /// Parse the arguments of the current process.
+    pub fn parse() -> Result<Self, Error> {
+        let mut args = Args::parse_from(env::args().skip(1))?;
+        // compatibility with `TIMEOUT=… batsmt-run file`
+        if args.timeout.is_none() {
+            if let Ok(t) = env::var("TIMEOUT") {
+                args.timeout = Some(parse_timeout(&t)?);
+            }
+        }
+        Ok(args)
+    }
+
+    /// Parse the given arguments (excluding the program's name).
+    pub fn parse_from<I>(args: I) -> Result<Self, Error> where I: IntoIterator<Item=String> {
+        let mut res = Args::new();
+        let mut args = args.into_iter();
+        while let Some(a) = args.next() {
+            if ! a.starts_with("-") || a == "-" {
+                res.files.push(a);
+                continue
+            }
+            // `--key=value` or `--key value`
+            let (key, mut inline) = match a.find('=') {
+                Some(i) => (&a[..i], Some(a[i+1..].to_string())),
+                None => (&a[..], None),
+            };
+            let mut value = || -> Result<String, Error> {
+                inline.take().or_else(|| args.next())
+                    .ok_or_else(|| Error(format!("missing value for {}", key)))
+            };
+            match key {
+                "-h" | "--help" => res.help = true,
+                "--stats" => res.stats = true,
+                "--stats-json" => res.stats_json = true,
+                "--model" => res.model = true,
+                "--incremental" => res.incremental = true,
+                "--check-model" => res.check_model = true,
+                "--timeout" => res.timeout = Some(parse_timeout(&value()?)?),
+                "--proof" => res.proof = Some(value()?),
+                "--log" => res.log = Some(value()?),
+                "--format" => {
+                    res.format = match &*value()? {
+                        "smt2" => Some(Format::Smt2),
+                        "dimacs" => Some(Format::Dimacs),
+                        f => return Err(Error(format!("unknown format {:?}", f))),
+                    }
+                },
+                "--cc" => {
+                    res.cc = match &*value()? {
+                        "fast" => CCImpl::Fast,
+                        "naive" => CCImpl::Naive,
+                        c => return Err(Error(format!("unknown congruence closure {:?}", c))),
+                    }
+                },
+                k if k.starts_with("--") && SolverConfig::KEYS.contains(&&k[2..]) => {
+                    let v = value()?;
+                    res.conf.set(&k[2..], &v).map_err(Error)?;
+                },
+                k => return Err(Error(format!("unknown option {}", k))),
+            }
+        }
+        Ok(res)
+    }
+}
+
+// time limit, in seconds
+fn parse_timeout(s: &str) -> Result<f64, Error> {
+    match s.parse::<f64>() {
+        Ok(t) if t > 0. => Ok(t),
+        _ => Err(Error(format!("invalid timeout {:?}", s))),
+    }
+}
@@ -3,11 +3,17 @@ use {
     batsmt_core::{ast, AstView},
     batsmt_hast::{HManager, StrSymbolManager},
     batsmt_theory::{self as theory, LitMapBuiltins},
-    batsmt_cc::{self as cc, CCView, HasConstructor, ConstructorView as CView, },
+    batsmt_cc::{
+        self as cc, CCView, HasConstructor, ConstructorView as CView,
+        HasConstructorSelect, ConstructorSelectView as CSView, HasDatatype, },
     batsmt_solver as solver,
     batsmt_pretty as pp,
     batsmt_tseitin::{self as tseitin, View as FView, },
+    batsmt_lra::{self as lra, LRAView, Pred, Q, },
+    batsmt_bv::{self as bv, BVView, BVPred, BVOp, },
+    batsmt_quant::{self as quant, QuantView, },
     bit_set::BitSet,
+    fxhash::{FxHashMap, FxHashSet, },
 };
 
 /// The Manager we use.
@@ -27,6 +33,43 @@ pub struct Builtins {
     pub or_: AST,
     pub imply_: AST,
     pub ite: AST,
+    pub real: AST,
+    pub add: AST,
+    pub sub: AST,
+    pub mul: AST,
+    pub div: AST,
+    pub leq: AST,
+    pub lt: AST,
+    pub geq: AST,
+    pub gt: AST,
+    pub array: AST,
+    pub select: AST,
+    pub store: AST,
+    pub bvadd: AST,
+    pub bvsub: AST,
+    pub bvneg: AST,
+    pub bvmul: AST,
+    pub bvand: AST,
+    pub bvor: AST,
+    pub bvxor: AST,
+    pub bvnot: AST,
+    pub bvult: AST,
+    pub bvule: AST,
+    pub bvugt: AST,
+    pub bvuge: AST,
+    pub bvslt: AST,
+    pub bvsle: AST,
+    pub bvsgt: AST,
+    pub bvsge: AST,
+    pub concat: AST,
+}
+
+/// A declared function symbol, along with its signature.
+#[derive(Clone,Debug)]
+pub struct Decl {
+    pub f: AST,
+    pub args: Vec<AST>,
+    pub ret: AST,
 }
 
 /// The main context.
@@ -35,6 +78,30 @@ pub struct Ctx {
     pub lmb: LitMapBuiltins,
     pub b: Builtins,
     cstor: BitSet,
+    sorts: Vec<AST>, // declared sorts
+    decls: Vec<Decl>, // declared functions
+    nums: FxHashMap<Q, AST>, // numerals
+    num_val: FxHashMap<AST, Q>, // value of numerals
+    diffs: FxHashMap<(AST,AST), AST>, // skolems for extensionality
+    datatypes: FxHashMap<AST, Vec<AST>>, // datatype -> its constructors
+    cstor_sels: FxHashMap<AST, Vec<(AST, AST)>>, // constructor -> selectors, with their sort
+    selectors: FxHashMap<AST, (AST, u32)>, // selector -> (constructor, index)
+    bv_sorts: FxHashMap<u32, AST>, // width -> `(_ BitVec width)`
+    bv_widths: FxHashMap<AST, u32>, // `(_ BitVec width)` -> width
+    bv_consts: FxHashMap<Vec<bool>, AST>, // bit-vector constants
+    bv_val: FxHashMap<AST, Vec<bool>>, // value of bit-vector constants
+    extracts: FxHashMap<(u32,u32), AST>, // `(_ extract hi lo)` symbols
+    extract_idx: FxHashMap<AST, (u32,u32)>,
+    quants: FxHashMap<AST, Quant>, // quantified formulas
+    quant_ids: FxHashMap<(Vec<AST>, AST), AST>, // (vars, body) -> formula
+    n_fresh: u32, // for naming bound variables and skolems
+}
+
+/// A quantified formula `∀vars. body`.
+struct Quant {
+    vars: Vec<AST>,
+    body: AST,
+    patterns: Vec<Vec<AST>>,
 }
 
 pub mod ctx {
@@ -46,7 +113,17 @@ pub mod ctx {
             let mut m = HManager::new();
             let b = Builtins::new(&mut m);
             let lmb = b.clone().into();
-            Ctx {m, b, lmb, cstor: BitSet::new(), }
+            Ctx {
+                m, b, lmb, cstor: BitSet::new(), sorts: vec!(), decls: vec!(),
+                nums: FxHashMap::default(), num_val: FxHashMap::default(),
+                diffs: FxHashMap::default(), datatypes: FxHashMap::default(),
+                cstor_sels: FxHashMap::default(), selectors: FxHashMap::default(),
+                bv_sorts: FxHashMap::default(), bv_widths: FxHashMap::default(),
+                bv_consts: FxHashMap::default(), bv_val: FxHashMap::default(),
+                extracts: FxHashMap::default(), extract_idx: FxHashMap::default(),
+                quants: FxHashMap::default(), quant_ids: FxHashMap::default(),
+                n_fresh: 0,
+            }
         }
 
         pub fn is_cstor(&self, t: &AST) -> bool { self.cstor.contains(t.idx() as usize) }
@@ -54,6 +131,156 @@ pub mod ctx {
             self.cstor.insert(t.idx() as usize);
         }
 
+        /// Remember that `s` selects the `idx`-th argument of `cstor`.
+        pub fn add_selector(&mut self, s: AST, cstor: AST, idx: u32, ty: AST) {
+            let sels = self.cstor_sels.entry(cstor).or_insert(vec!());
+            debug_assert_eq!(sels.len(), idx as usize);
+            sels.push((s, ty));
+            self.selectors.insert(s, (cstor, idx));
+        }
+
+        /// Remember that `cstors` are the constructors of the datatype `ty`.
+        pub fn add_datatype(&mut self, ty: AST, cstors: Vec<AST>) {
+            self.datatypes.insert(ty, cstors);
+        }
+
+        /// Is `f` a selector of some datatype?
+        pub fn is_selector(&self, f: &AST) -> bool { self.selectors.contains_key(f) }
+
+        /// A subterm of `t` that the congruence closure only handles through
+        /// a micro-theory (non-boolean `ite`, arrays, datatypes, quantifiers).
+        pub fn find_micro_theory_term(&self, t: &AST) -> Option<AST> {
+            let mut seen = FxHashSet::default();
+            let mut st = vec!(*t);
+            while let Some(t) = st.pop() {
+                if ! seen.insert(t) { continue }
+                if self.quants.contains_key(&t) || self.is_cstor(&t) { return Some(t) }
+                if let AstView::App{f, args} = self.m.view(&t) {
+                    if (*f == self.b.ite && self.m.ty(&t) != Some(self.b.bool_))
+                        || *f == self.b.select || *f == self.b.store
+                        || self.is_cstor(f) || self.is_selector(f)
+                    {
+                        return Some(t)
+                    }
+                    st.extend_from_slice(args);
+                }
+            }
+            None
+        }
+
+        /// Does `ty` have finitely many values?
+        ///
+        /// `visiting` contains the datatypes currently being checked,
+        /// recursive datatypes are infinite.
+        fn is_finite_sort(&self, ty: &AST, visiting: &mut Vec<AST>) -> bool {
+            if *ty == self.b.bool_ { return true }
+            if visiting.contains(ty) { return false }
+            let cstors = match self.datatypes.get(ty) {
+                Some(cstors) => cstors,
+                None => return false,
+            };
+            visiting.push(*ty);
+            let res = cstors.iter().all(|f| {
+                self.cstor_sels.get(f).map_or(true, |sels| {
+                    sels.iter().all(|(_,s_ty)| self.is_finite_sort(s_ty, visiting))
+                })
+            });
+            visiting.pop();
+            res
+        }
+
+        /// Remember that `s` is a declared sort.
+        pub fn add_sort(&mut self, s: AST) { self.sorts.push(s) }
+
+        /// Remember that `d.f` is a declared function.
+        pub fn add_decl(&mut self, d: Decl) { self.decls.push(d) }
+
+        /// Declared sorts, in order of declaration.
+        pub fn sorts(&self) -> &[AST] { &self.sorts }
+
+        /// Declared functions, in order of declaration.
+        pub fn decls(&self) -> &[Decl] { &self.decls }
+
+        /// Forget the sorts and functions declared after the first
+        /// `n_sorts` and `n_decls` ones, on `pop`.
+        pub fn truncate_decls(&mut self, n_sorts: usize, n_decls: usize) {
+            self.sorts.truncate(n_sorts);
+            self.decls.truncate(n_decls);
+        }
+
+        /// Numeral for `q`, if it was already built.
+        pub fn numeral(&self, q: &Q) -> Option<AST> { self.nums.get(q).cloned() }
+
+        /// Value of `t`, if it is a numeral.
+        pub fn numeral_value(&self, t: &AST) -> Option<&Q> { self.num_val.get(t) }
+
+        /// The sort `(Array idx val)`.
+        pub fn mk_array_sort(&mut self, idx: AST, val: AST) -> AST {
+            self.m.mk_app(self.b.array, &[idx, val], None)
+        }
+
+        /// If `ty` is `(Array idx val)`, returns `(idx, val)`.
+        pub fn view_array_sort(&self, ty: &AST) -> Option<(AST, AST)> {
+            match self.m.view(ty) {
+                AstView::App{f, args} if *f == self.b.array && args.len() == 2 => {
+                    Some((args[0], args[1]))
+                },
+                _ => None,
+            }
+        }
+
+        /// The sort `(_ BitVec width)`.
+        pub fn mk_bv_sort(&mut self, width: u32) -> AST {
+            if let Some(ty) = self.bv_sorts.get(&width) { return *ty }
+            let ty = self.m.mk_string(format!("(_ BitVec {})", width), None);
+            self.bv_sorts.insert(width, ty);
+            self.bv_widths.insert(ty, width);
+            ty
+        }
+
+        /// Width of `ty`, if it is a bit-vector sort.
+        pub fn bv_sort_width(&self, ty: &AST) -> Option<u32> { self.bv_widths.get(ty).cloned() }
+
+        /// The bit-vector constant `bits`, least significant bit first.
+        pub fn mk_bv_const(&mut self, bits: &[bool]) -> AST {
+            if let Some(t) = self.bv_consts.get(bits) { return *t }
+            let ty = self.mk_bv_sort(bits.len() as u32);
+            // the name is the value in SMT-LIB syntax, for printing
+            let name: String =
+                Some("#b").into_iter()
+                .chain(bits.iter().rev().map(|b| if *b { "1" } else { "0" }))
+                .collect();
+            let t = self.m.mk_string(name, Some(ty));
+            self.bv_consts.insert(bits.to_vec(), t);
+            self.bv_val.insert(t, bits.to_vec());
+            t
+        }
+
+        /// Bit-vector constant for `bits`, if it was already built.
+        pub fn bv_const(&self, bits: &[bool]) -> Option<AST> { self.bv_consts.get(bits).cloned() }
+
+        /// `((_ extract hi lo) t)`.
+        pub fn mk_extract(&mut self, hi: u32, lo: u32, t: AST) -> AST {
+            assert!(lo <= hi, "invalid extract ({} {})", hi, lo);
+            let f = match self.extracts.get(&(hi,lo)) {
+                Some(f) => *f,
+                None => {
+                    let f = self.m.mk_string(format!("(_ extract {} {})", hi, lo), None);
+                    self.extracts.insert((hi,lo), f);
+                    self.extract_idx.insert(f, (hi,lo));
+                    f
+                },
+            };
+            let ty = self.mk_bv_sort(hi - lo + 1);
+            self.m.mk_app(f, &[t], Some(ty))
+        }
+
+        /// A fresh constant for the bound variable `name` of sort `ty`.
+        pub fn mk_bound_var(&mut self, name: &str, ty: AST) -> AST {
+            self.n_fresh += 1;
+            self.m.mk_string(format!("{}!{}", name, self.n_fresh), Some(ty))
+        }
+
         /// Copy of builtins
         pub fn builtins<U>(&self) -> U
             where Builtins: Into<U>
@@ -187,12 +414,195 @@ pub mod ctx {
                     AstView::App{f, args} if *f == self.b.distinct => {
                         CCView::Distinct(args)
                     },
+                    // arithmetic atoms belong to LRA. This way, variables
+                    // only used in arithmetic are not shared with CC.
+                    AstView::App{f, ..} if self.b.is_arith_pred(f) => CCView::Opaque(t),
                     AstView::App{f,args} => CCView::ApplyHO(f,args),
                 }
             }
         }
     }
 
+    impl theory::HasEq for Ctx {
+        fn mk_eq(&mut self, a: &AST, b: &AST) -> AST {
+            self.m.mk_app(self.b.eq, &[*a, *b], Some(self.b.bool_))
+        }
+    }
+
+    impl lra::Ctx for Ctx {
+        fn view_as_lra<'a>(&'a self, t: &'a AST) -> LRAView<'a, AST> {
+            if let Some(q) = self.num_val.get(t) {
+                return LRAView::Const(q)
+            }
+            let b = &self.b;
+            match self.m.view(t) {
+                AstView::App{f, args} if *f == b.add => LRAView::Add(args),
+                AstView::App{f, args} if *f == b.sub => LRAView::Sub(args),
+                AstView::App{f, args} if *f == b.mul => LRAView::Mul(args),
+                AstView::App{f, args} if *f == b.div => LRAView::Div(args),
+                AstView::App{f, args} if args.len() == 2 => {
+                    let p =
+                        if *f == b.leq { Pred::Leq }
+                        else if *f == b.lt { Pred::Lt }
+                        else if *f == b.geq { Pred::Geq }
+                        else if *f == b.gt { Pred::Gt }
+                        else if *f == b.eq && self.m.ty(&args[0]) == Some(b.real) { Pred::Eq }
+                        else { return LRAView::Other(t) };
+                    LRAView::Pred(p, &args[0], &args[1])
+                },
+                _ => LRAView::Other(t),
+            }
+        }
+
+        fn is_real(&self, t: &AST) -> bool { self.m.ty(t) == Some(self.b.real) }
+
+        fn mk_pred(&mut self, p: Pred, a: AST, b: AST) -> AST {
+            let f = match p {
+                Pred::Leq => self.b.leq,
+                Pred::Lt => self.b.lt,
+                Pred::Geq => self.b.geq,
+                Pred::Gt => self.b.gt,
+                Pred::Eq => self.b.eq,
+            };
+            self.m.mk_app(f, &[a, b], Some(self.b.bool_))
+        }
+
+        fn mk_numeral(&mut self, q: &Q) -> AST {
+            if let Some(t) = self.nums.get(q) { return *t }
+            // the name is the value in SMT-LIB syntax, for printing
+            let t = self.m.mk_string(lra::pp_q(q).to_string(), Some(self.b.real));
+            self.nums.insert(q.clone(), t);
+            self.num_val.insert(t, q.clone());
+            t
+        }
+    }
+
+    impl cc::HasArray<AST> for Ctx {
+        fn view_as_array<'a>(&'a self, t: &'a AST) -> cc::ArrayView<'a, AST> {
+            match self.m.view(t) {
+                AstView::App{f, args} if *f == self.b.select && args.len() == 2 => {
+                    cc::ArrayView::Select{arr: &args[0], idx: &args[1]}
+                },
+                AstView::App{f, args} if *f == self.b.store && args.len() == 3 => {
+                    cc::ArrayView::Store{arr: &args[0], idx: &args[1], val: &args[2]}
+                },
+                _ => cc::ArrayView::Other(t),
+            }
+        }
+
+        fn is_array(&self, t: &AST) -> bool {
+            self.m.ty(t).map_or(false, |ty| self.view_array_sort(&ty).is_some())
+        }
+
+        fn mk_select(&mut self, a: &AST, i: &AST) -> AST {
+            let ty = self.m.ty(a).and_then(|ty| self.view_array_sort(&ty)).map(|(_,v)| v);
+            self.m.mk_app(self.b.select, &[*a, *i], ty)
+        }
+
+        fn mk_diff(&mut self, a: &AST, b: &AST) -> AST {
+            if let Some(k) = self.diffs.get(&(*a,*b)) {
+                return *k
+            }
+            let ty = self.m.ty(a).and_then(|ty| self.view_array_sort(&ty)).map(|(i,_)| i);
+            let name = format!("@diff_{}_{}", a.idx(), b.idx());
+            let k = self.m.mk_string(name, ty);
+            self.diffs.insert((*a,*b), k);
+            k
+        }
+    }
+
+    impl bv::Ctx for Ctx {
+        fn view_as_bv<'a>(&'a self, t: &'a AST) -> BVView<'a, AST> {
+            if let Some(bits) = self.bv_val.get(t) {
+                return BVView::Const(bits)
+            }
+            let b = &self.b;
+            match self.m.view(t) {
+                AstView::App{f, args} if *f == b.bvadd => BVView::Op(BVOp::Add, args),
+                AstView::App{f, args} if *f == b.bvsub => BVView::Op(BVOp::Sub, args),
+                AstView::App{f, args} if *f == b.bvmul => BVView::Op(BVOp::Mul, args),
+                AstView::App{f, args} if *f == b.bvand => BVView::Op(BVOp::And, args),
+                AstView::App{f, args} if *f == b.bvor => BVView::Op(BVOp::Or, args),
+                AstView::App{f, args} if *f == b.bvxor => BVView::Op(BVOp::Xor, args),
+                AstView::App{f, args} if *f == b.bvneg => BVView::Neg(&args[0]),
+                AstView::App{f, args} if *f == b.bvnot => BVView::Not(&args[0]),
+                AstView::App{f, args} if *f == b.concat => BVView::Concat(args),
+                AstView::App{f, args} if self.extract_idx.contains_key(f) => {
+                    let (hi, lo) = self.extract_idx[f];
+                    BVView::Extract(hi, lo, &args[0])
+                },
+                AstView::App{f, args} if *f == b.ite && self.bv_width(t).is_some() => {
+                    BVView::Ite(&args[0], &args[1], &args[2])
+                },
+                AstView::App{f, args} if args.len() == 2 => {
+                    let p =
+                        if *f == b.bvult { BVPred::Ult }
+                        else if *f == b.bvule { BVPred::Ule }
+                        else if *f == b.bvugt { BVPred::Ugt }
+                        else if *f == b.bvuge { BVPred::Uge }
+                        else if *f == b.bvslt { BVPred::Slt }
+                        else if *f == b.bvsle { BVPred::Sle }
+                        else if *f == b.bvsgt { BVPred::Sgt }
+                        else if *f == b.bvsge { BVPred::Sge }
+                        else if *f == b.eq && self.bv_width(&args[0]).is_some() { BVPred::Eq }
+                        else { return BVView::Other(t) };
+                    BVView::Pred(p, &args[0], &args[1])
+                },
+                _ => BVView::Other(t),
+            }
+        }
+
+        fn bv_width(&self, t: &AST) -> Option<u32> {
+            self.m.ty(t).and_then(|ty| self.bv_sort_width(&ty))
+        }
+    }
+
+    impl quant::Ctx for Ctx {
+        fn view_as_quant<'a>(&'a self, t: &'a AST) -> QuantView<'a, AST> {
+            match self.quants.get(t) {
+                Some(q) => QuantView::Forall{vars: &q.vars, body: &q.body, patterns: &q.patterns},
+                None => QuantView::Other(t),
+            }
+        }
+
+        fn mk_forall(&mut self, vars: &[AST], body: AST, patterns: &[Vec<AST>]) -> AST {
+            if let Some(t) = self.quant_ids.get(&(vars.to_vec(), body)) {
+                return *t
+            }
+            // the formula is a boolean constant named after its
+            // pretty-printed form, for printing.
+            let name = {
+                let vars: Vec<String> =
+                    vars.iter()
+                    .map(|v| format!("({} {})", theory::pp_ast(self, v),
+                        theory::pp_ast(self, &self.m.ty(v).expect("untyped variable"))))
+                    .collect();
+                format!("(forall ({}) {})", vars.join(" "), theory::pp_ast(self, &body))
+            };
+            let t = self.m.mk_string(name, Some(self.b.bool_));
+            self.quant_ids.insert((vars.to_vec(), body), t);
+            self.quants.insert(t, Quant{vars: vars.to_vec(), body, patterns: patterns.to_vec()});
+            t
+        }
+
+        fn mk_skolem(&mut self, v: &AST) -> AST {
+            self.n_fresh += 1;
+            let name = format!("{}!sk{}", theory::pp_ast(self, v), self.n_fresh);
+            let ty = self.m.ty(v);
+            self.m.mk_string(name, ty)
+        }
+
+        fn is_uninterpreted(&self, f: &AST) -> bool {
+            let b = &self.b;
+            *f == b.select || *f == b.store ||
+                ! (b.is_builtin(f) || self.extract_idx.contains_key(f))
+        }
+
+        fn is_uninterpreted_sort(&self, ty: &AST) -> bool {
+            self.sorts.contains(ty) && ! self.datatypes.contains_key(ty)
+        }
+    }
+
     impl cc::HasIte<AST> for Ctx {
         fn view_as_ite<'a>(&'a self, t: &'a AST) -> cc::IteView<'a, AST> {
             match self.m.view(t) {
@@ -225,6 +635,60 @@ pub mod ctx {
             }
         }
     }
+
+    impl HasConstructorSelect<AST> for Ctx {
+        type F = AST;
+
+        fn view_as_constructor_select<'a>(
+            &'a self, t: &'a AST
+        ) -> CSView<'a, Self::F, AST>
+        {
+            match self.view(t) {
+                AstView::Const(_) if self.is_cstor(t) => {
+                    CSView::AppConstructor(t, &[])
+                },
+                AstView::App {f, args} if self.is_cstor(f) => {
+                    CSView::AppConstructor(f,args)
+                },
+                AstView::App {f, args} if args.len() == 1 => {
+                    match self.selectors.get(f) {
+                        Some((c, idx)) => CSView::Select{f: c, idx: *idx, sub: &args[0]},
+                        None => CSView::Other(t),
+                    }
+                },
+                _ => {
+                    CSView::Other(t)
+                },
+            }
+        }
+    }
+
+    impl HasDatatype<AST> for Ctx {
+        fn datatype_cstors(&self, t: &AST) -> Option<&[AST]> {
+            let ty = self.m.ty(t)?;
+            self.datatypes.get(&ty).map(|v| &v[..])
+        }
+
+        fn is_finite_datatype(&self, t: &AST) -> bool {
+            match self.m.ty(t) {
+                Some(ty) => self.is_finite_sort(&ty, &mut vec!()),
+                None => false,
+            }
+        }
+
+        fn mk_cstor_app(&mut self, f: &AST, t: &AST) -> AST {
+            let sels = match self.cstor_sels.get(f) {
+                Some(sels) => sels.clone(),
+                None => return *f, // constant constructor
+            };
+            let args: Vec<AST> =
+                sels.iter()
+                .map(|(s, ty)| self.m.mk_app(*s, &[*t], Some(*ty)))
+                .collect();
+            let ty = self.m.ty(t);
+            self.m.mk_app(*f, &args, ty)
+        }
+    }
 }
 
 mod builtins {
@@ -245,8 +709,54 @@ mod builtins {
                 imply_: m.mk_str("=>", None),
                 not_: m.mk_str("not", None),
                 distinct: m.mk_str("distinct", None),
+                real: m.mk_str("Real", None),
+                add: m.mk_str("+", None),
+                sub: m.mk_str("-", None),
+                mul: m.mk_str("*", None),
+                div: m.mk_str("/", None),
+                leq: m.mk_str("<=", None),
+                lt: m.mk_str("<", None),
+                geq: m.mk_str(">=", None),
+                gt: m.mk_str(">", None),
+                array: m.mk_str("Array", None),
+                select: m.mk_str("select", None),
+                store: m.mk_str("store", None),
+                bvadd: m.mk_str("bvadd", None),
+                bvsub: m.mk_str("bvsub", None),
+                bvneg: m.mk_str("bvneg", None),
+                bvmul: m.mk_str("bvmul", None),
+                bvand: m.mk_str("bvand", None),
+                bvor: m.mk_str("bvor", None),
+                bvxor: m.mk_str("bvxor", None),
+                bvnot: m.mk_str("bvnot", None),
+                bvult: m.mk_str("bvult", None),
+                bvule: m.mk_str("bvule", None),
+                bvugt: m.mk_str("bvugt", None),
+                bvuge: m.mk_str("bvuge", None),
+                bvslt: m.mk_str("bvslt", None),
+                bvsle: m.mk_str("bvsle", None),
+                bvsgt: m.mk_str("bvsgt", None),
+                bvsge: m.mk_str("bvsge", None),
+                concat: m.mk_str("concat", None),
             }
         }
+
+        /// Is `f` one of the builtin symbols?
+        pub fn is_builtin(&self, f: &AST) -> bool {
+            [self.true_, self.false_, self.not_, self.eq, self.distinct,
+             self.and_, self.or_, self.imply_, self.ite,
+             self.add, self.sub, self.mul, self.div,
+             self.leq, self.lt, self.geq, self.gt, self.select, self.store,
+             self.bvadd, self.bvsub, self.bvneg, self.bvmul, self.bvand,
+             self.bvor, self.bvxor, self.bvnot, self.bvult, self.bvule,
+             self.bvugt, self.bvuge, self.bvslt, self.bvsle, self.bvsgt,
+             self.bvsge, self.concat].contains(f)
+        }
+
+        /// Is `f` one of the arithmetic comparisons?
+        pub fn is_arith_pred(&self, f: &AST) -> bool {
+            *f == self.leq || *f == self.lt || *f == self.geq || *f == self.gt
+        }
     }
 
     impl Into<LitMapBuiltins> for Builtins {
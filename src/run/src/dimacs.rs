@@ -0,0 +1,111 @@
+
+//! Pure SAT mode, for DIMACS CNF problems.
+//!
+//! Clauses are given directly to the SAT solver, along with a theory that
+//! does nothing, so as to measure the SAT layer in isolation.
+//! The output follows the conventions of the SAT competition.
+
+use {
+    std::error::Error,
+    batsmt_core::{Chrono, stats, },
+    batsmt_parser::dimacs,
+    batsmt_solver::{self as solver, solver::Res, InterruptHandle, },
+    batsmt_theory::EmptyTheory,
+    crate::{Ctx, cli, open_input, enable_proofs, print_stats},
+};
+
+// number of literals on each `v` line
+const LITS_PER_LINE: usize = 16;
+
+/// Solve the DIMACS problem in `file` (or stdin), and return the result.
+pub fn run_file(
+    file: Option<&str>, args: &cli::Args, interrupt: &InterruptHandle, chrono: &Chrono
+) -> Result<Option<Res>, Box<Error>> {
+    let mut c = Ctx::new();
+    let mut solver = solver::Solver::new_with(c.builtins(), EmptyTheory, &args.conf);
+    solver.set_interrupt_handle(interrupt.clone());
+    if let Some(t) = args.timeout {
+        let mut limits = solver.limits().clone();
+        limits.time = Some(t);
+        solver.set_limits(limits);
+    }
+    enable_proofs(&mut solver, args)?;
+
+    // variable `i` is `vars[i-1]`
+    let mut vars = vec!();
+    let mut clause = vec!();
+    let mut clauses = vec!(); // kept for `--check-model`
+    let input = open_input(file)?;
+    let header = stats::timer("parse.time").time(|| dimacs::parse(input, file, |cl| {
+        if args.check_model {
+            clauses.push(cl.to_vec());
+        }
+        clause.clear();
+        for &x in cl {
+            let v = (x as i64).abs() as usize;
+            while vars.len() < v {
+                vars.push(solver.new_bool_lit());
+            }
+            let lit = vars[v-1];
+            clause.push(if x > 0 { lit } else { !lit });
+        }
+        solver.add_bool_clause_reuse(&mut clause);
+    }))?;
+    // declared variables that occur in no clause still get a value
+    if let Some(h) = header {
+        while vars.len() < h.n_vars as usize {
+            vars.push(solver.new_bool_lit());
+        }
+    }
+    info!("parsed {} variables and {} clauses", vars.len(), solver.n_clauses());
+
+    let r = solver.solve(&mut c);
+    solver.flush_proof(&c)?;
+    match &r {
+        Res::SAT => {
+            println!("s SATISFIABLE");
+            let vals: Vec<_> =
+                vars.iter().enumerate()
+                .map(|(i, lit)| {
+                    let v = i as i64 + 1;
+                    match solver.model_value(solver::BLit(*lit)) {
+                        Some(false) => -v,
+                        _ => v,
+                    }
+                }).collect();
+            if args.check_model {
+                check_model(&clauses, &vals)?;
+            }
+            for line in vals.chunks(LITS_PER_LINE) {
+                let line: Vec<_> = line.iter().map(|v| v.to_string()).collect();
+                println!("v {}", line.join(" "));
+            }
+            println!("v 0");
+        },
+        Res::UNSAT => println!("s UNSATISFIABLE"),
+        Res::Unknown(_) => println!("s UNKNOWN"),
+    }
+
+    // comment lines, as in the rest of the output
+    if args.stats {
+        print_stats(&c, &solver, chrono, false, "c ");
+    }
+    if args.stats_json {
+        print_stats(&c, &solver, chrono, true, "c ");
+    }
+    Ok(Some(r))
+}
+
+// check that each clause contains a literal of `vals`, where variable `i`
+// is true iff `vals[i-1] > 0`
+fn check_model(clauses: &[Vec<i32>], vals: &[i64]) -> Result<(), Box<Error>> {
+    for cl in clauses {
+        let sat = cl.iter().any(|&x| (x as i64) == vals[(x as i64).abs() as usize - 1]);
+        if ! sat {
+            let cl: Vec<_> = cl.iter().map(|x| x.to_string()).collect();
+            return Err(format!("--check-model: clause `{} 0` is false in the model", cl.join(" ")).into())
+        }
+    }
+    info!("model checked against {} clauses", clauses.len());
+    Ok(())
+}
@@ -1,101 +1,640 @@
 
-// A test binary
+// Command-line front-end: solves SMT-LIB 2 and DIMACS files
 
 #[macro_use] extern crate log;
 
 mod ctx;
 mod ast_builder;
 mod ast_printer;
+mod model;
+mod cli;
+mod dimacs;
 
 use {
-    std::{env,fs,error::Error},
-    batsmt_core::{Chrono, gc::HasInternalMemory, },
+    std::{fs,io,process,error::Error},
+    batsmt_core::{Chrono, stats, gc::HasInternalMemory, },
     batsmt_cc as cc,
     batsmt_parser::{self as parser, Statement},
     batsmt_tseitin::Tseitin,
-    batsmt_solver as solver,
+    batsmt_solver::{self as solver, proof::Rule, solver::Res, SolverConfig, InterruptHandle, },
+    batsmt_theory::{self as theory, TheoryLit, },
+    batsmt_lra as lra,
+    batsmt_bv as bv,
+    batsmt_quant as quant,
     batsmt_pretty as pp,
 };
 
 pub use {
-    crate::ctx::{M, Ctx, Builtins},
+    crate::ctx::{M, Ctx, Builtins, Decl},
 };
 
-fn main() -> Result<(), Box<Error>> {
-    batsmt_logger::init();
-    let chrono = Chrono::new();
-
-    let mut c = Ctx::new();
+type Th = quant::MBQI<Ctx, theory::Combine<
+    Ctx,
+    cc::CCTheory<Ctx, (
+        cc::theories::Ite,
+        cc::theories::ConstructorSelect<ctx::AST>,
+        cc::theories::Acyclic,
+        cc::theories::Array,
+        quant::EMatching,
+    )>,
+    lra::LRATheory<Ctx>,
+>>;
 
-    // parse
-    let stmts: Vec<_> = {
-        let mut builder = ast_builder::AstBuilder::new(&mut c);
-        let args = env::args();
-        match args.skip(1).next() {
-            None => {
-                info!("parse stdin");
-                parser::parse_stdin(&mut builder)?
-            },
-            Some(file) => {
-                info!("parse file {:?}", file);
-                let file = fs::File::open(file)?;
-                parser::parse(&mut builder, file)?
-            },
+/// Assert `t` at toplevel, under the given guards.
+///
+/// Bit-vector atoms are bit-blasted before the clauses are added.
+fn assert_term(
+    c: &mut Ctx, solver: &mut solver::Solver<Ctx, Th>,
+    tseitin: &mut Tseitin<Ctx>, bv: &mut bv::BitBlaster<Ctx>,
+    t: ctx::AST, guards: &[TheoryLit<Ctx>]
+) {
+    let (cs, lits) = tseitin.clauses_guarded(c, solver.lit_map_mut(), t, guards);
+    let cs: Vec<_> = cs.collect();
+    let lits: Vec<_> = lits.collect();
+    {
+        let clause_lits = cs.iter().flat_map(|cl| { let cl: &[TheoryLit<Ctx>] = cl; cl.iter() });
+        for lit in lits.iter().cloned().chain(clause_lits) {
+            if let TheoryLit::T(u, _) = lit {
+                bv.add_atom(c, solver, u);
+            }
         }
-    };
+    }
+    for lit in lits {
+        trace!("add side lit {}", pp::pp1(lit, c));
+        solver.add_lit(c, lit.clone());
+    }
+    // the last clause asserts `t`, the other ones are definitions
+    let mut cs = cs.into_iter().peekable();
+    while let Some(clause) = cs.next() {
+        let rule = if cs.peek().is_some() { Rule::Tseitin } else { Rule::Assume(Some(t)) };
+        solver.add_clause_with(c, clause, rule);
+    }
+}
+
+/// Enable proofs in `solver`, as required by `args`.
+///
+/// With `--proof FILE`, the proof is written into `FILE` as the solver goes;
+/// otherwise, with `(set-option :produce-proofs true)`, it is kept in memory.
+fn enable_proofs<T>(solver: &mut solver::Solver<Ctx, T>, args: &cli::Args) -> io::Result<()>
+    where T: theory::Theory<Ctx>
+{
+    if let Some(file) = &args.proof {
+        info!("write proof into {:?}", file);
+        let out = io::BufWriter::new(fs::File::create(file)?);
+        solver.enable_proofs_into(Box::new(out));
+    } else if args.produce_proofs {
+        solver.enable_proofs();
+    }
+    Ok(())
+}
+
+/// Print `(error "msg")`, with `msg` escaped as a SMT-LIB string literal.
+fn print_error(msg: &str) {
+    println!("(error \"{}\")", msg.replace('"', "\"\""))
+}
 
-    info!("parsed {} statements (after {}s)", stmts.len(), chrono.as_f64());
+/// Print `success` after commands that have no other output, if enabled.
+fn success(print_success: bool) {
+    if print_success { println!("success") }
+}
 
-    let th: cc::CCTheory<Ctx, (cc::theories::Ite, cc::theories::Constructor<ctx::AST>)> = cc::CCTheory::new(&mut c);
-    let mut solver = solver::Solver::new(c.builtins(), th);
+fn parse_bool(v: &str) -> Option<bool> {
+    match v {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
 
-    {
-        let propagate = match env::var("PROPAGATE") {
-            Ok(ref s) if s == "1" || s == "true" => true,
-            Ok(ref s) if s == "0" || s == "false" => false,
-            _ => true, // default
+/// Sizes before a `push`, restored by the matching `pop`.
+#[derive(Clone,Copy,Debug)]
+struct Scope {
+    n_named: usize,
+    n_assertions: usize,
+    n_sorts: usize, // declared sorts, in `Ctx`
+    n_decls: usize, // declared functions, in `Ctx`
+}
+
+/// Everything that `reset-assertions` throws away: the solver and the
+/// state built along with its literals.
+struct State {
+    solver: solver::Solver<Ctx, Th>,
+    tseitin: Tseitin<Ctx>, // Tseitin transformation, to handle formulas
+    bv: bv::BitBlaster<Ctx>, // bit-blasting of bit-vector atoms
+    model: Option<model::Model>, // model of the last `check-sat`, built lazily
+    named: Vec<(parser::Atom, solver::BLit)>, // named assertions, and their selector literal
+    assertions: Vec<ctx::AST>, // active assertions, for `--check-model`
+    scopes: Vec<Scope>, // for each active `push`, what to restore on `pop`
+    last_res: Option<Res>, // result of the last `check-sat`
+    asserted: bool, // anything asserted or pushed yet?
+}
+
+impl State {
+    /// New solver for `c`, configured by the command line.
+    fn new(c: &mut Ctx, args: &cli::Args, interrupt: &InterruptHandle) -> io::Result<Self> {
+        let cc = match args.cc {
+            cli::CCImpl::Fast => cc::CCTheory::new(c),
+            cli::CCImpl::Naive => cc::CCTheory::new_naive(c),
         };
-        solver.enable_th_propagation(propagate);
+        let th: Th = quant::MBQI::new(theory::Combine::new(cc, lra::LRATheory::new(c)));
+        let mut solver = solver::Solver::new_with(c.builtins(), th, &args.conf);
+        solver.set_interrupt_handle(interrupt.clone());
+
+        // time limit for each `check-sat`, in seconds
+        if let Some(t) = args.timeout {
+            let mut limits = solver.limits().clone();
+            limits.time = Some(t);
+            solver.set_limits(limits);
+        }
+
+        enable_proofs(&mut solver, args)?;
+
+        Ok(State {
+            solver, tseitin: Tseitin::new(), bv: bv::BitBlaster::new(),
+            model: None, named: vec!(), assertions: vec!(), scopes: vec!(),
+            last_res: None, asserted: false,
+        })
+    }
+
+    /// Start again with a fresh solver for `c`, keeping the interrupt handle.
+    fn reset(&mut self, c: &mut Ctx, args: &cli::Args) -> io::Result<()> {
+        let h = self.solver.interrupt_handle();
+        *self = State::new(c, args, &h)?;
+        Ok(())
     }
 
-    // Tseitin transformation, to handle formulas
-    let mut tseitin = Tseitin::new();
+    fn last_unsat(&self) -> bool {
+        self.last_res == Some(Res::UNSAT)
+    }
 
-    for s in &stmts {
-        debug!("parsed statement {}", pp::pp1(&c, s));
+    /// Check satisfiability of the assertions, under the given assumptions.
+    fn check_sat(
+        &mut self, c: &mut Ctx, assumptions: &[solver::BLit], args: &cli::Args
+    ) -> Result<(), Box<Error>> {
+        self.tseitin.reclaim_unused_memory();
+        self.model = None;
+        let mut lits: Vec<_> = assumptions.iter().map(|l| l.0).collect();
+        lits.extend(self.named.iter().map(|(_,sel)| sel.0));
+        let mut r = self.solver.solve_with(c, &lits[..]);
+        // bit-vector atoms created during search must be bit-blasted,
+        // and the model checked again
+        while r == Res::SAT && self.bv.add_new_atoms(c, &mut self.solver) {
+            r = self.solver.solve_with(c, &lits[..]);
+        }
+        self.solver.flush_proof(c)?;
+        println!("{}", r);
+        let sat = r == Res::SAT;
+        self.last_res = Some(r);
+        if sat && args.check_model {
+            self.check_model(c)?;
+        }
+        if args.model {
+            if let Some(m) = self.model(c) {
+                println!("{}", pp::pp1(m, &*c))
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that each assertion evaluates to `true` in the model of
+    /// the last `check-sat`, which returned `sat`.
+    fn check_model(&mut self, c: &mut Ctx) -> Result<(), Box<Error>> {
+        self.model(c);
+        let m = self.model.as_ref().ok_or("--check-model: no model after `sat`")?;
+        for &t in self.assertions.iter() {
+            let v = m.eval_strict(c, t);
+            if v != c.b.true_ {
+                let msg = format!(
+                    "--check-model: assertion {} evaluates to {} in the model",
+                    theory::pp_ast(&*c, &t), theory::pp_ast(&*c, &v));
+                return Err(msg.into())
+            }
+        }
+        info!("model checked against {} assertions", self.assertions.len());
+        Ok(())
+    }
+
+    /// Model of the last `check-sat`, if it returned `sat`.
+    fn model(&mut self, c: &mut Ctx) -> Option<&model::Model> {
+        if self.model.is_none() && self.solver.get_model().is_some() {
+            let (solver, bv) = (&self.solver, &self.bv);
+            let bvs: Vec<_> =
+                bv.iter_terms()
+                .filter_map(|t| bv.value(solver, t).map(|v| (*t, v)))
+                .collect();
+            let bvs: Vec<_> = bvs.iter().map(|(t,v)| (*t, c.mk_bv_const(v))).collect();
+            self.model = solver.get_model().map(|m| model::Model::new(c, m, &bvs));
+        }
+        self.model.as_ref()
+    }
+}
+
+/// Options set by `set-option`.
+struct Options {
+    print_success: bool,
+    // models and unsat cores are always available, these are only
+    // recorded so that `get-option` reports them
+    produce_models: bool,
+    produce_unsat_cores: bool,
+}
+
+impl Options {
+    fn new(args: &cli::Args) -> Self {
+        Options {
+            print_success: args.incremental,
+            produce_models: false, produce_unsat_cores: false,
+        }
+    }
+}
+
+/// Print the default registry of statistics, as an attribute list or
+/// as JSON, once the totals of `c`, `solver` and `chrono` are recorded into it.
+///
+/// Each line starts with `prefix`.
+fn print_stats<T>(
+    c: &Ctx, solver: &solver::Solver<Ctx, T>, chrono: &Chrono, json: bool, prefix: &str
+) where T: theory::Theory<Ctx>
+{
+    let st = stats::global();
+    st.timer("time").set(chrono.as_f64());
+    st.counter("terms").set(c.m.n_terms() as u64);
+    solver.export_stats(&st);
+    let s = if json { st.pp_json().to_string() } else { st.pp_smt2().to_string() };
+    for line in s.lines() {
+        println!("{}{}", prefix, line);
+    }
+}
+
+/// Answer `(get-info <key>)`.
+fn get_info(key: &str, st: &State, c: &Ctx, chrono: &Chrono) {
+    match key {
+        ":name" => println!("(:name \"batsmt\")"),
+        ":version" => println!("(:version \"{}\")", env!("CARGO_PKG_VERSION")),
+        ":authors" => println!("(:authors \"Simon Cruanes\")"),
+        ":error-behavior" => println!("(:error-behavior continued-execution)"),
+        ":assertion-stack-levels" => println!("(:assertion-stack-levels {})", st.scopes.len()),
+        ":reason-unknown" => match &st.last_res {
+            Some(Res::Unknown(r)) => println!("(:reason-unknown {})", r),
+            _ => print_error("the last check-sat did not return unknown"),
+        },
+        ":all-statistics" => print_stats(c, &st.solver, chrono, false, ""),
+        _ => println!("unsupported"),
+    }
+}
+
+/// Answer `(get-option <key>)`.
+fn get_option(key: &str, st: &State, opts: &Options, conf: &SolverConfig) {
+    match key {
+        ":print-success" => println!("{}", opts.print_success),
+        ":produce-models" => println!("{}", opts.produce_models),
+        ":produce-unsat-cores" => println!("{}", opts.produce_unsat_cores),
+        ":produce-proofs" => println!("{}", st.solver.proofs_enabled()),
+        ":timeout" => {
+            let ms = st.solver.limits().time.map_or(0, |t| (t * 1e3) as u64);
+            println!("{}", ms)
+        },
+        _ => match conf.get(conf_key(key)) {
+            Some(v) => println!("{}", v),
+            None => println!("unsupported"),
+        },
+    }
+}
+
+/// Name of the `SolverConfig` option set by `(set-option <key> …)`.
+fn conf_key(key: &str) -> &str {
+    match key {
+        ":random-seed" => "seed",
+        _ if key.starts_with(':') => &key[1..],
+        _ => key,
+    }
+}
+
+/// Open `file`, or stdin.
+fn open_input(file: Option<&str>) -> io::Result<Box<dyn io::Read>> {
+    match file {
+        None => {
+            info!("parse stdin");
+            Ok(Box::new(io::stdin()))
+        },
+        Some(file) => {
+            info!("parse file {:?}", file);
+            Ok(Box::new(fs::File::open(file)?))
+        },
+    }
+}
+
+/// Process the statements of `file` (or stdin), and return the result
+/// of the last `check-sat`, if any.
+///
+/// Each file starts from a fresh context and solver.
+fn run_file(
+    file: Option<&str>, args: &cli::Args, interrupt: &InterruptHandle, chrono: &Chrono
+) -> Result<Option<Res>, Box<Error>> {
+    // statistics are reported per file
+    stats::global().reset();
+    if args.format_of(file) == cli::Format::Dimacs {
+        return dimacs::run_file(file, args, interrupt, chrono)
+    }
+
+    // `set-option` can change the configuration, for this file only,
+    // and `reset` goes back to the command line's
+    let orig_args = args;
+    let mut args = orig_args.clone();
+    let mut c = Ctx::new();
+    let mut st = State::new(&mut c, &args, interrupt)?;
+    let mut opts = Options::new(&args);
+    let mut last_res = None;
+
+    // statements are parsed and processed one at a time, so that
+    // we can answer on stdin interactively
+    let input = open_input(file)?;
+    let mut builder = ast_builder::AstBuilder::new(&mut c);
+    let mut parser = parser::StatementStream::new(&mut builder, input);
+    if let Some(file) = file {
+        parser.set_file(file);
+    }
+
+    let parse_time = stats::timer("parse.time");
+    loop {
+        let s = match parse_time.time(|| parser.next_statement()) {
+            Ok(Some(s)) => s,
+            Ok(None) => break,
+            Err(e) => {
+                // the parser skips the faulty statement, if it can
+                print_error(&e.to_string());
+                continue
+            },
+        };
+        let c = parser.builder().ctx();
+        debug!("parsed statement {}", pp::pp1(&*c, &s));
 
         // process statement
-        match s {
+        match &s {
+            Statement::SetInfo(..) | Statement::SetLogic(..) |
+            Statement::DeclareSort(..) | Statement::DeclareFun(..) |
+            Statement::DefineFun(..) | Statement::DefineSort(..) |
+            Statement::DeclareDatatypes(..) => {
+                // handled by the parser and the builder
+                success(opts.print_success)
+            },
+            Statement::Assert(t) | Statement::AssertNamed(_, t)
+                if args.cc == cli::CCImpl::Naive && c.find_micro_theory_term(t).is_some() =>
+            {
+                // the naive congruence closure would ignore the meaning of `u`
+                let u = c.find_micro_theory_term(t).unwrap();
+                print_error(&format!("the naive congruence closure cannot handle {}",
+                    theory::pp_ast(&*c, &u)));
+            },
             Statement::Assert(t) => {
-                let (cs, lits) = tseitin.clauses(&mut c, solver.lit_map_mut(), *t);
-                for lit in lits {
-                    trace!("add side lit {}", pp::pp1(lit, &c));
-                    solver.add_lit(&mut c, lit.clone());
+                // inside a scope, the assertion is guarded by its activation literal
+                let guards: Vec<_> =
+                    st.solver.scope_lit().map(|a| TheoryLit::from_blit(solver::BLit(a)))
+                    .into_iter().collect();
+                assert_term(c, &mut st.solver, &mut st.tseitin, &mut st.bv, *t, &guards);
+                st.asserted = true;
+                st.assertions.push(*t);
+                success(opts.print_success);
+            },
+            Statement::AssertNamed(name, t) => {
+                // assert `t` under a fresh selector literal, so it can
+                // be part of the unsat core
+                let sel = st.solver.new_bool_lit();
+                let mut guards = vec!(TheoryLit::from_blit(solver::BLit(sel)));
+                guards.extend(st.solver.scope_lit().map(|a| TheoryLit::from_blit(solver::BLit(a))));
+                assert_term(c, &mut st.solver, &mut st.tseitin, &mut st.bv, *t, &guards);
+                st.asserted = true;
+                st.assertions.push(*t);
+                st.named.push((name.clone(), solver::BLit(sel)));
+                success(opts.print_success);
+            },
+            Statement::Push(n) => {
+                for _ in 0 .. *n {
+                    st.solver.push_scope();
+                    st.scopes.push(Scope{
+                        n_named: st.named.len(), n_assertions: st.assertions.len(),
+                        n_sorts: c.sorts().len(), n_decls: c.decls().len(),
+                    });
                 }
-                for clause in cs {
-                    solver.add_clause(&mut c, clause);
+                st.asserted = true;
+                success(opts.print_success);
+            },
+            Statement::Pop(n) => {
+                let n = *n as usize;
+                if n > st.scopes.len() {
+                    print_error(&format!("cannot pop {} scopes, only {} are active", n, st.scopes.len()));
+                } else if n == 0 {
+                    success(opts.print_success);
+                } else {
+                    // the parser already forgot the declarations of these scopes
+                    st.solver.pop_scopes(n);
+                    let sc = st.scopes[st.scopes.len() - n];
+                    st.scopes.truncate(st.scopes.len() - n);
+                    st.named.truncate(sc.n_named);
+                    st.assertions.truncate(sc.n_assertions);
+                    c.truncate_decls(sc.n_sorts, sc.n_decls);
+                    st.model = None;
+                    st.last_res = None;
+                    success(opts.print_success);
                 }
             },
             Statement::CheckSat => {
-                tseitin.reclaim_unused_memory();
-                let r = solver.solve(&mut c);
-                println!("{:?}", r)
+                st.check_sat(c, &[], &args)?;
+                last_res = st.last_res.clone();
             },
             Statement::CheckSatAssumptions(v) => {
-                tseitin.reclaim_unused_memory();
                 // map assumptions to literals
-                let lits: Vec<_> =
-                    v.iter().map(|t| solver.new_term_lit(&mut c, *t).0).collect();
-                let r = solver.solve_with(&mut c, &lits[..]);
-                println!("{:?}", r)
+                let lits: Vec<_> = v.iter().map(|t| st.solver.new_term_lit(c, *t)).collect();
+                st.check_sat(c, &lits, &args)?;
+                last_res = st.last_res.clone();
+            },
+            Statement::GetUnsatCore => {
+                if st.last_unsat() {
+                    let State{named, solver, ..} = &mut st;
+                    let core: Vec<_> =
+                        named.iter()
+                        .filter(|(_,sel)| solver.unsat_core_contains_lit(sel.0))
+                        .map(|(name,_)| name.clone())
+                        .collect();
+                    println!("({})", core.join(" "));
+                } else {
+                    print_error("no unsat core available");
+                }
+            },
+            Statement::GetProof => {
+                if ! st.last_unsat() {
+                    print_error("no proof available");
+                } else {
+                    let stdout = io::stdout();
+                    let mut out = stdout.lock();
+                    if let Err(e) = st.solver.write_proof(c, &mut out) {
+                        print_error(&e.to_string());
+                    }
+                }
+            },
+            Statement::GetModel | Statement::GetValue(..) => {
+                match (st.model(c), &s) {
+                    (None, _) => print_error("no model available"),
+                    (Some(m), Statement::GetModel) => println!("{}", pp::pp1(m, &*c)),
+                    (Some(m), Statement::GetValue(v)) => {
+                        let vals = v.iter().map(|t| (*t, m.value(c, *t))).collect();
+                        println!("{}", pp::pp1(&model::Values(vals), &*c))
+                    },
+                    _ => unreachable!(),
+                }
+            },
+            Statement::GetInfo(key) => get_info(key, &st, c, chrono),
+            Statement::GetOption(key) => get_option(key, &st, &opts, &args.conf),
+            Statement::SetOption(opt, v) if &**opt == ":timeout" => {
+                // in milliseconds, 0 means no limit
+                match v.parse::<u64>() {
+                    Ok(ms) => {
+                        let mut limits = st.solver.limits().clone();
+                        limits.time = if ms == 0 { None } else { Some(ms as f64 * 1e-3) };
+                        st.solver.set_limits(limits);
+                        success(opts.print_success);
+                    },
+                    Err(_) => print_error(&format!("invalid timeout {}", v)),
+                }
+            },
+            Statement::SetOption(opt, v) if &**opt == ":produce-proofs" => {
+                // proofs must be enabled before any clause is added
+                match parse_bool(v) {
+                    None => print_error(&format!("expected a boolean, got {}", v)),
+                    Some(b) if b == args.produce_proofs => success(opts.print_success),
+                    Some(_) if st.asserted => {
+                        print_error(&format!("option {} must be set before any assertion", opt));
+                    },
+                    Some(b) => {
+                        args.produce_proofs = b;
+                        let limits = st.solver.limits().clone();
+                        st.reset(c, &args)?;
+                        st.solver.set_limits(limits);
+                        success(opts.print_success)
+                    },
+                }
+            },
+            Statement::SetOption(opt, v) => {
+                let flag = match &**opt {
+                    ":print-success" => &mut opts.print_success,
+                    ":produce-models" => &mut opts.produce_models,
+                    ":produce-unsat-cores" => &mut opts.produce_unsat_cores,
+                    _ if args.conf.get(conf_key(opt)).is_some() => {
+                        // the solver is built with its configuration, so we
+                        // start again with a new one
+                        if st.asserted {
+                            print_error(&format!("option {} must be set before any assertion", opt));
+                        } else {
+                            match args.conf.set(conf_key(opt), v) {
+                                Ok(()) => {
+                                    let limits = st.solver.limits().clone();
+                                    st.reset(c, &args)?;
+                                    st.solver.set_limits(limits);
+                                    success(opts.print_success)
+                                },
+                                Err(e) => print_error(&e),
+                            }
+                        }
+                        continue
+                    },
+                    _ => {
+                        println!("unsupported");
+                        continue
+                    },
+                };
+                match parse_bool(v) {
+                    Some(b) => {
+                        *flag = b;
+                        success(opts.print_success)
+                    },
+                    None => print_error(&format!("expected a boolean, got {}", v)),
+                }
+            },
+            Statement::Echo(msg) => println!("{}", msg),
+            Statement::ResetAssertions => {
+                // declarations made inside scopes are removed too.
+                // Options are kept, including the time limit.
+                if let Some(sc) = st.scopes.first() {
+                    c.truncate_decls(sc.n_sorts, sc.n_decls);
+                }
+                let limits = st.solver.limits().clone();
+                st.reset(c, &args)?;
+                st.solver.set_limits(limits);
+                success(opts.print_success);
+            },
+            Statement::Reset => {
+                success(opts.print_success);
+                parser.reset();
+                parser.builder().reset();
+                args = orig_args.clone();
+                st.reset(parser.builder().ctx(), &args)?;
+                opts = Options::new(&args);
             },
             Statement::Exit => {
+                success(opts.print_success);
                 break;
             }
-            _ => (),
+        }
+    }
+
+    let c = parser.builder().ctx();
+    if args.stats {
+        print_stats(c, &st.solver, chrono, false, "");
+    }
+    if args.stats_json {
+        print_stats(c, &st.solver, chrono, true, "");
+    }
+    Ok(last_res)
+}
+
+fn main() -> Result<(), Box<Error>> {
+    let args = match cli::Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1)
+        },
+    };
+    if args.help {
+        print!("{}", cli::Args::usage());
+        return Ok(())
+    }
+    match &args.log {
+        Some(lvl) => batsmt_logger::init_from_str(lvl),
+        None => batsmt_logger::init(),
+    }
+    let chrono = Chrono::new();
+
+    // on SIGINT, interrupt the current `check-sat`, which answers `unknown`.
+    // Exit if there is none, or on a second SIGINT.
+    let interrupt = InterruptHandle::new();
+    {
+        let h = interrupt.clone();
+        ctrlc::set_handler(move || {
+            if h.is_running() && ! h.is_interrupted() {
+                h.interrupt()
+            } else {
+                process::exit(130)
+            }
+        })?;
+    }
+
+    let mut res = None;
+    if args.files.is_empty() {
+        res = run_file(None, &args, &interrupt, &chrono)?;
+    }
+    for file in args.files.iter() {
+        let file = if file == "-" { None } else { Some(&**file) };
+        if let Some(r) = run_file(file, &args, &interrupt, &chrono)? {
+            res = Some(r);
         }
     }
     info!("exit (after {}s)", chrono.as_f64());
 
-    Ok(())
+    // exit codes of SAT solvers
+    let code = match res {
+        Some(Res::SAT) => 10,
+        Some(Res::UNSAT) => 20,
+        _ => 0,
+    };
+    process::exit(code)
 }
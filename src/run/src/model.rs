@@ -0,0 +1,556 @@
+//! Term-level models, built from the theory's model.
+//!
+//! Each uninterpreted sort gets a finite universe made of one fresh
+//! domain element per congruence class, and each declared function gets
+//! a table mapping the values of its arguments to a value.
+//! Real terms are mapped to numerals by the arithmetic theory.
+//! Arrays are built from the `select` terms of their class, as `store`s
+//! on top of a constant array.
+//! Bit-vector terms are mapped to constants by the bit-blaster.
+
+use {
+    batsmt_core::{ast_u32::AST, AstView, Manager, },
+    batsmt_theory as theory,
+    batsmt_tseitin::{Ctx as TCtx, View as FView, },
+    batsmt_pretty::{self as pp, Pretty1, },
+    batsmt_lra::{Ctx as LRACtx, LRAView, Pred, Q, },
+    batsmt_bv::{self as bv, Ctx as BVCtx, BVView, },
+    fxhash::FxHashMap,
+    num_traits::Zero,
+    crate::ctx::{Ctx, Decl, },
+};
+
+/// Interpretation of a function symbol, as a finite table plus a default value.
+#[derive(Clone,Debug)]
+struct Table {
+    entries: Vec<(Vec<AST>, AST)>,
+    default: AST,
+}
+
+/// A model, with a finite universe for every uninterpreted sort,
+/// and a table for every declared function.
+pub struct Model {
+    repr: FxHashMap<AST, AST>, // term -> class representative
+    bools: FxHashMap<AST, bool>, // values from the SAT solver
+    classes: FxHashMap<AST, AST>, // class representative -> domain element
+    universe: FxHashMap<AST, Vec<AST>>, // sort -> domain elements
+    tables: FxHashMap<AST, Table>, // function -> its interpretation
+    sorts: Vec<AST>,
+    decls: Vec<Decl>,
+    zero: AST, // default value for reals
+    array_defaults: FxHashMap<AST, AST>, // array sort -> constant array
+    bv_values: FxHashMap<AST, AST>, // bit-vector term -> constant
+    bv_zeros: FxHashMap<AST, AST>, // bit-vector sort -> zero
+}
+
+/// Pairs `(term, value)`, as returned by `get-value`.
+pub struct Values(pub Vec<(AST, AST)>);
+
+impl Model {
+    /// Build a model from the theory model `tm`, and the values
+    /// `bvs` of bit-vector terms.
+    ///
+    /// This allocates the domain elements in `c`.
+    pub fn new(c: &mut Ctx, tm: &theory::Model<Ctx>, bvs: &[(AST, AST)]) -> Self {
+        let mut model = Model {
+            repr: FxHashMap::default(),
+            bools: FxHashMap::default(),
+            classes: FxHashMap::default(),
+            universe: FxHashMap::default(),
+            tables: FxHashMap::default(),
+            sorts: c.sorts().to_vec(),
+            decls: c.decls().iter().filter(|d| !c.is_cstor(&d.f) && !c.is_selector(&d.f)).cloned().collect(),
+            zero: c.mk_numeral(&Q::zero()),
+            array_defaults: FxHashMap::default(),
+            bv_values: bvs.iter().cloned().collect(),
+            bv_zeros: FxHashMap::default(),
+        };
+
+        for s in model.sorts.iter() {
+            model.universe.insert(*s, vec!());
+        }
+        for (t,b) in tm.iter_bool() {
+            model.bools.insert(*t, *b);
+        }
+
+        // sort terms, for determinism
+        let mut terms: Vec<(AST,AST)> = tm.iter_repr().map(|(t,r)| (*t,*r)).collect();
+        terms.sort();
+
+        // one domain element per class of uninterpreted sort,
+        // and the value of one of its terms for bit-vectors
+        for &(t,r) in terms.iter() {
+            model.repr.insert(t, r);
+            if let Some(v) = model.bv_values.get(&t).cloned() {
+                model.classes.entry(r).or_insert(v);
+            }
+            if model.classes.contains_key(&r) { continue }
+            match c.m.ty(&r) {
+                Some(ty) if model.universe.contains_key(&ty) => {
+                    let v = model.new_elt(c, ty);
+                    model.classes.insert(r, v);
+                },
+                _ => (), // booleans are `true` or `false`
+            }
+        }
+
+        // universes must not be empty
+        for i in 0 .. model.sorts.len() {
+            let s = model.sorts[i];
+            if model.universe[&s].len() == 0 {
+                model.new_elt(c, s);
+            }
+        }
+
+        // constant arrays, for the sorts that need one
+        for &(_,r) in terms.iter() {
+            if let Some(ty) = c.m.ty(&r) {
+                model.add_bv_zero(c, ty);
+                model.add_array_default(c, ty);
+            }
+        }
+        for i in 0 .. model.decls.len() {
+            let d = model.decls[i].clone();
+            for s in d.args.iter().chain(Some(&d.ret)) {
+                model.add_bv_zero(c, *s);
+                model.add_array_default(c, *s);
+            }
+        }
+
+        // one value per class of arrays, from the `select` terms on it
+        {
+            let mut entries: FxHashMap<AST, Vec<(AST,AST)>> = FxHashMap::default();
+            for &(t,_) in terms.iter() {
+                if let AstView::App{f, args} = c.m.view(&t) {
+                    if *f != c.b.select { continue }
+                    let r = model.repr.get(&args[0]).cloned().unwrap_or(args[0]);
+                    let i = model.eval(c, args[1]);
+                    let es = entries.entry(r).or_insert(vec!());
+                    if ! es.iter().any(|(j,_)| *j == i) {
+                        es.push((i, model.eval(c, t)));
+                    }
+                }
+            }
+            for &(_,r) in terms.iter() {
+                if model.classes.contains_key(&r) { continue }
+                let ty = match c.m.ty(&r) {
+                    Some(ty) if model.array_defaults.contains_key(&ty) => ty,
+                    _ => continue,
+                };
+                let mut v = model.array_defaults[&ty];
+                for (i, x) in entries.get(&r).iter().flat_map(|es| es.iter()) {
+                    v = c.m.mk_app(c.b.store, &[v, *i, *x], Some(ty));
+                }
+                model.classes.insert(r, v);
+            }
+        }
+
+        // function tables, with an arbitrary default value
+        for i in 0 .. model.decls.len() {
+            let d = model.decls[i].clone();
+            let default = match c.m.view(&d.f) {
+                AstView::Const(_) if d.args.len() == 0 => {
+                    match model.value_of_known(c, d.f) {
+                        Some(v) => v,
+                        None => model.default_value(c, d.ret),
+                    }
+                },
+                _ => model.default_value(c, d.ret),
+            };
+            model.tables.insert(d.f, Table{entries: vec!(), default});
+        }
+        for &(t,_) in terms.iter() {
+            if let AstView::App{f, args} = c.m.view(&t) {
+                if ! model.tables.contains_key(f) { continue }
+                let args: Vec<AST> = args.iter().map(|u| model.eval(c, *u)).collect();
+                let v = model.eval(c, t);
+                let tbl = model.tables.get_mut(f).unwrap();
+                if ! tbl.entries.iter().any(|(args2,_)| *args2 == args) {
+                    tbl.entries.push((args, v));
+                }
+            }
+        }
+
+        model
+    }
+
+    /// Allocate a new domain element for sort `ty`.
+    fn new_elt(&mut self, c: &mut Ctx, ty: AST) -> AST {
+        let elts = self.universe.get_mut(&ty).unwrap();
+        let name = format!("{}!val!{}", pp::pp1(c, &ty), elts.len());
+        let v = c.m.mk_string(name, Some(ty));
+        elts.push(v);
+        v
+    }
+
+    /// If `ty` is an array sort, build its constant array `((as const ty) v)`.
+    fn add_array_default(&mut self, c: &mut Ctx, ty: AST) {
+        if self.array_defaults.contains_key(&ty) { return }
+        if let Some((_, val)) = c.view_array_sort(&ty) {
+            self.add_bv_zero(c, val);
+            self.add_array_default(c, val);
+            let v = self.default_value(c, val);
+            let f = c.m.mk_string(format!("(as const {})", pp::pp1(c, &ty)), None);
+            let arr = c.m.mk_app(f, &[v], Some(ty));
+            self.array_defaults.insert(ty, arr);
+        }
+    }
+
+    /// If `ty` is a bit-vector sort, build its zero.
+    fn add_bv_zero(&mut self, c: &mut Ctx, ty: AST) {
+        if self.bv_zeros.contains_key(&ty) { return }
+        if let Some(w) = c.bv_sort_width(&ty) {
+            let zero = c.mk_bv_const(&vec![false; w as usize]);
+            self.bv_zeros.insert(ty, zero);
+        }
+    }
+
+    /// Some value of sort `ty`.
+    fn default_value(&self, c: &Ctx, ty: AST) -> AST {
+        if ty == c.b.bool_ {
+            c.b.false_
+        } else if ty == c.b.real {
+            self.zero
+        } else if let Some(v) = self.array_defaults.get(&ty) {
+            *v
+        } else if let Some(v) = self.bv_zeros.get(&ty) {
+            *v
+        } else {
+            match self.universe.get(&ty) {
+                Some(v) if v.len() > 0 => v[0],
+                _ => panic!("no value for sort {}", pp::pp1(c, &ty)),
+            }
+        }
+    }
+
+    #[inline]
+    fn bool_value(c: &Ctx, b: bool) -> AST {
+        if b { c.b.true_ } else { c.b.false_ }
+    }
+
+    /// Value of `t`, if `t` is directly known to the theory or SAT solver.
+    fn value_of_known(&self, c: &Ctx, t: AST) -> Option<AST> {
+        if let Some(b) = self.bools.get(&t) {
+            return Some(Model::bool_value(c, *b))
+        }
+        if let Some(v) = self.bv_values.get(&t) {
+            return Some(*v)
+        }
+        match self.repr.get(&t) {
+            Some(r) if *r == c.b.true_ || *r == c.b.false_ => Some(*r),
+            Some(r) if c.numeral_value(r).is_some() => Some(*r),
+            Some(r) => self.classes.get(r).cloned(),
+            None => None,
+        }
+    }
+
+    /// Evaluate `t` as a boolean.
+    pub fn eval_bool(&self, c: &Ctx, t: AST) -> bool {
+        self.eval(c, t) == c.b.true_
+    }
+
+    /// Evaluate `t` in the model.
+    ///
+    /// The result is either `true`, `false`, or a domain element.
+    pub fn eval(&self, c: &Ctx, t: AST) -> AST {
+        self.eval_(c, t, false)
+    }
+
+    /// Evaluate `t` in the model, from its subterms, to check the model.
+    ///
+    /// Unlike `eval`, the values given by the SAT solver and the theory
+    /// are only used for terms that cannot be evaluated otherwise,
+    /// so that connectives, `ite`, `=`, `distinct` and uninterpreted
+    /// functions (through their tables) are actually checked.
+    pub fn eval_strict(&self, c: &Ctx, t: AST) -> AST {
+        self.eval_(c, t, true)
+    }
+
+    fn eval_(&self, c: &Ctx, t: AST, strict: bool) -> AST {
+        if ! strict {
+            if let Some(v) = self.value_of_known(c, t) {
+                return v
+            }
+        }
+        let eval_bool = |u: AST| self.eval_(c, u, strict) == c.b.true_;
+        match c.view_as_formula(t) {
+            FView::Bool(b) => Model::bool_value(c, b),
+            FView::TyBool => t,
+            FView::Not(u) => Model::bool_value(c, ! eval_bool(u)),
+            FView::And(args) => {
+                Model::bool_value(c, args.iter().all(|u| eval_bool(*u)))
+            },
+            FView::Or(args) => {
+                Model::bool_value(c, args.iter().any(|u| eval_bool(*u)))
+            },
+            FView::Imply(args) => {
+                let n = args.len();
+                let b =
+                    args[..n-1].iter().any(|u| ! eval_bool(*u))
+                    || eval_bool(args[n-1]);
+                Model::bool_value(c, b)
+            },
+            FView::Eq(a,b) if c.m.ty(&a) == Some(c.b.real) => {
+                Model::bool_value(c, self.eval_q(c, a) == self.eval_q(c, b))
+            },
+            FView::Eq(a,b) if c.bv_width(&a).is_some() => {
+                Model::bool_value(c, self.eval_bv(c, a) == self.eval_bv(c, b))
+            },
+            FView::Eq(a,b) => {
+                Model::bool_value(c, self.eval_(c, a, strict) == self.eval_(c, b, strict))
+            },
+            FView::Distinct(args) => {
+                let mut vs: Vec<AST> = args.iter().map(|u| self.eval_(c, *u, strict)).collect();
+                vs.sort();
+                vs.dedup();
+                Model::bool_value(c, vs.len() == args.len())
+            },
+            FView::Ite(a,b,u) => {
+                if eval_bool(a) { self.eval_(c, b, strict) } else { self.eval_(c, u, strict) }
+            },
+            FView::Atom(t) if strict => match c.m.view(&t) {
+                AstView::App{f, args} if self.tables.contains_key(f) => {
+                    let args: Vec<AST> = args.iter().map(|u| self.eval_(c, *u, true)).collect();
+                    self.apply_table(f, &args)
+                },
+                _ => self.value_of_known(c, t).unwrap_or_else(|| self.eval_atom(c, t)),
+            },
+            FView::Atom(t) => self.eval_atom(c, t),
+        }
+    }
+
+    // value of `f(args)` in the table of `f`
+    fn apply_table(&self, f: &AST, args: &[AST]) -> AST {
+        let tbl = &self.tables[f];
+        tbl.entries.iter()
+            .find(|(args2,_)| *args2 == args)
+            .map(|(_,v)| *v)
+            .unwrap_or(tbl.default)
+    }
+
+    /// Evaluate `t` in the model, building numerals for arithmetic terms if needed.
+    pub fn value(&self, c: &mut Ctx, t: AST) -> AST {
+        if c.m.ty(&t) == Some(c.b.real) {
+            if let Some(q) = self.eval_q(c, t) {
+                return c.mk_numeral(&q)
+            }
+        }
+        if c.bv_width(&t).is_some() {
+            if let Some(v) = self.eval_bv(c, t) {
+                return c.mk_bv_const(&v)
+            }
+        }
+        self.eval(c, t)
+    }
+
+    /// Evaluate the bit-vector term `t` as a constant.
+    fn eval_bv(&self, c: &Ctx, t: AST) -> Option<Vec<bool>> {
+        match c.view_as_bv(&t) {
+            BVView::Const(v) => Some(v.to_vec()),
+            BVView::Op(op, args) => {
+                let mut v = self.eval_bv(c, args[0])?;
+                for u in args[1..].iter() {
+                    v = bv::eval::op(op, &v, &self.eval_bv(c, *u)?);
+                }
+                Some(v)
+            },
+            BVView::Neg(a) => Some(bv::eval::neg(&self.eval_bv(c, *a)?)),
+            BVView::Not(a) => Some(self.eval_bv(c, *a)?.iter().map(|b| !b).collect()),
+            BVView::Concat(args) => {
+                let mut v = vec!();
+                for u in args.iter().rev() { v.extend(self.eval_bv(c, *u)?) }
+                Some(v)
+            },
+            BVView::Extract(hi, lo, a) => {
+                let v = self.eval_bv(c, *a)?;
+                Some(v[lo as usize ..= hi as usize].to_vec())
+            },
+            BVView::Ite(a, b, u) => {
+                if self.eval_bool(c, *a) { self.eval_bv(c, *b) } else { self.eval_bv(c, *u) }
+            },
+            BVView::Pred(..) => None,
+            BVView::Other(..) => {
+                match c.view_as_bv(&self.eval(c, t)) {
+                    BVView::Const(v) => Some(v.to_vec()),
+                    _ => None,
+                }
+            },
+        }
+    }
+
+    /// Evaluate the real term `t` as a rational.
+    fn eval_q(&self, c: &Ctx, t: AST) -> Option<Q> {
+        let num = |u: AST| c.numeral_value(&u).cloned();
+        match c.view_as_lra(&t) {
+            LRAView::Const(q) => Some(q.clone()),
+            LRAView::Add(args) => {
+                let mut q = Q::zero();
+                for u in args.iter() { q += self.eval_q(c, *u)? }
+                Some(q)
+            },
+            LRAView::Sub(args) if args.len() == 1 => self.eval_q(c, args[0]).map(|q| -q),
+            LRAView::Sub(args) => {
+                let mut q = self.eval_q(c, args[0])?;
+                for u in args[1..].iter() { q -= self.eval_q(c, *u)? }
+                Some(q)
+            },
+            LRAView::Mul(args) => {
+                let mut q = self.eval_q(c, args[0])?;
+                for u in args[1..].iter() { q *= self.eval_q(c, *u)? }
+                Some(q)
+            },
+            LRAView::Div(args) => {
+                let mut q = self.eval_q(c, args[0])?;
+                for u in args[1..].iter() {
+                    let d = self.eval_q(c, *u)?;
+                    if d.is_zero() { return None }
+                    q /= d;
+                }
+                Some(q)
+            },
+            LRAView::Pred(..) | LRAView::Other(..) => num(self.eval(c, t)),
+        }
+    }
+
+    // evaluate an uninterpreted term
+    fn eval_atom(&self, c: &Ctx, t: AST) -> AST {
+        match c.view_as_bv(&t) {
+            BVView::Pred(p, a, b) => {
+                let r = match (self.eval_bv(c, *a), self.eval_bv(c, *b)) {
+                    (Some(a), Some(b)) => bv::eval::pred(p, &a, &b),
+                    _ => false,
+                };
+                return Model::bool_value(c, r)
+            },
+            BVView::Other(..) | BVView::Const(..) | BVView::Ite(..) => (),
+            _ => {
+                // bit-vector operation, use the constant if it exists
+                return self.eval_bv(c, t).and_then(|v| c.bv_const(&v)).unwrap_or(t)
+            },
+        }
+        match c.view_as_lra(&t) {
+            LRAView::Pred(p, a, b) => {
+                let (a, b) = match (self.eval_q(c, *a), self.eval_q(c, *b)) {
+                    (Some(a), Some(b)) => (a, b),
+                    _ => return c.b.false_,
+                };
+                let r = match p {
+                    Pred::Leq => a <= b,
+                    Pred::Lt => a < b,
+                    Pred::Geq => a >= b,
+                    Pred::Gt => a > b,
+                    Pred::Eq => a == b,
+                };
+                return Model::bool_value(c, r)
+            },
+            LRAView::Other(..) => (),
+            _ => {
+                // arithmetic term, use the numeral if it exists
+                return self.eval_q(c, t).and_then(|q| c.numeral(&q)).unwrap_or(t)
+            },
+        }
+        match c.m.view(&t) {
+            AstView::App{f, args} if *f == c.b.select => {
+                // look for the index in the `store`s of the array's value
+                let i = self.eval(c, args[1]);
+                let mut arr = self.eval(c, args[0]);
+                loop {
+                    match c.m.view(&arr) {
+                        AstView::App{f, args} if *f == c.b.store => {
+                            if args[1] == i { return args[2] }
+                            arr = args[0];
+                        },
+                        AstView::App{args, ..} if args.len() == 1 => return args[0], // constant
+                        _ => return t,
+                    }
+                }
+            },
+            AstView::App{f, args} if self.tables.contains_key(f) => {
+                let args: Vec<AST> = args.iter().map(|u| self.eval(c, *u)).collect();
+                self.apply_table(f, &args)
+            },
+            AstView::Const(_) if self.tables.contains_key(&t) => self.tables[&t].default,
+            _ => match c.m.ty(&t) {
+                Some(ty) if ty == c.b.bool_ || self.universe.contains_key(&ty) => {
+                    if self.universe.get(&ty).map_or(false, |v| v.contains(&t)) {
+                        t // domain element
+                    } else {
+                        self.default_value(c, ty)
+                    }
+                },
+                _ => t,
+            },
+        }
+    }
+}
+
+mod model {
+    use super::*;
+
+    // print `(define-fun f ((x!0 s0) … (x!n sn)) ret body)`
+    fn pp_define_fun(c: &Ctx, d: &Decl, tbl: &Table, ctx: &mut pp::Ctx) {
+        ctx.sexp(|ctx| {
+            ctx.str("define-fun").space().pp1(c, &d.f).space();
+            ctx.sexp(|ctx| {
+                for (i,s) in d.args.iter().enumerate() {
+                    if i > 0 { ctx.space(); }
+                    ctx.sexp(|ctx| {
+                        ctx.string(format!("x!{}", i)).space().pp1(c, s);
+                    });
+                }
+            });
+            ctx.space().pp1(c, &d.ret).space();
+
+            // body: nested `ite`, ending with the default value
+            for (args,v) in tbl.entries.iter() {
+                ctx.str("(ite").space();
+                if args.len() == 1 {
+                    ctx.sexp(|ctx| {
+                        ctx.str("=").space().str("x!0").space().pp1(c, &args[0]);
+                    });
+                } else {
+                    ctx.sexp(|ctx| {
+                        ctx.str("and");
+                        for (i,a) in args.iter().enumerate() {
+                            ctx.space().sexp(|ctx| {
+                                ctx.str("=").space().string(format!("x!{}", i))
+                                    .space().pp1(c, a);
+                            });
+                        }
+                    });
+                }
+                ctx.space().pp1(c, v).space();
+            }
+            ctx.pp1(c, &tbl.default);
+            for _ in tbl.entries.iter() { ctx.str(")"); }
+        });
+    }
+
+    impl Pretty1<Ctx> for Model {
+        fn pp1_into(&self, c: &Ctx, ctx: &mut pp::Ctx) {
+            ctx.str("(");
+            for s in self.sorts.iter() {
+                ctx.newline().str("  ; universe for ").pp1(c, s).str(":");
+                for v in self.universe[s].iter() {
+                    ctx.str(" ").pp1(c, v);
+                }
+            }
+            for d in self.decls.iter() {
+                ctx.newline().str("  ");
+                pp_define_fun(c, d, &self.tables[&d.f], ctx);
+            }
+            ctx.newline().str(")");
+        }
+    }
+
+    impl Pretty1<Ctx> for Values {
+        fn pp1_into(&self, c: &Ctx, ctx: &mut pp::Ctx) {
+            ctx.sexp(|ctx| {
+                for (i,(t,v)) in self.0.iter().enumerate() {
+                    if i > 0 { ctx.space(); }
+                    ctx.sexp(|ctx| { ctx.pp1(c, t).space().pp1(c, v); });
+                }
+            });
+        }
+    }
+}
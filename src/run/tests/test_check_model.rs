@@ -0,0 +1,33 @@
+use std::{fs, path::Path, process::Command, };
+
+// the sat benchmarks whose models must be checked
+fn benchs() -> Vec<String> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../benchs/sat");
+    let mut files: Vec<_> =
+        fs::read_dir(dir).unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| {
+            let name = p.file_name().unwrap().to_str().unwrap();
+            name.starts_with("t_") && name.ends_with(".smt2")
+        })
+        .map(|p| p.to_str().unwrap().to_string())
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_check_model() {
+    let files = benchs();
+    assert!(! files.is_empty());
+    for file in files.iter() {
+        // a model that falsifies some assertion makes `batsmt-run` fail
+        let out = Command::new(env!("CARGO_BIN_EXE_batsmt-run"))
+            .args(["--check-model", file])
+            .output().unwrap();
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        assert_eq!(out.status.code(), Some(10), "{}:\n{}\n{}", file, stdout, stderr);
+        assert!(stdout.lines().all(|l| ! l.starts_with("(error")), "{}:\n{}", file, stdout);
+    }
+}
@@ -0,0 +1,24 @@
+use std::{io::Write, process::{Command, Stdio}, };
+
+// run `batsmt-run` on `input`, from stdin
+fn run(input: &str) -> String {
+    let mut p = Command::new(env!("CARGO_BIN_EXE_batsmt-run"))
+        .stdin(Stdio::piped()).stdout(Stdio::piped())
+        .spawn().unwrap();
+    p.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let out = p.wait_with_output().unwrap();
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn test_error_escaping() {
+    let out = run("(set-option :produce-models \"yes\") (pop 1)");
+    let errors: Vec<_> = out.lines().filter(|l| l.starts_with("(error")).collect();
+    assert_eq!(errors.len(), 2, "{}", out);
+    for e in errors {
+        // a single string literal, where `"` is written `""`
+        assert!(e.starts_with("(error \"") && e.ends_with("\")"), "{}", e);
+        let msg = &e["(error \"".len() .. e.len() - "\")".len()];
+        assert!(msg.replace("\"\"", "").find('"').is_none(), "{}", e);
+    }
+}
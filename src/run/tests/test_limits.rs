@@ -0,0 +1,32 @@
+use std::{io::Write, process::{Command, Stdio}, };
+
+// pigeon-hole problem: `n+1` pigeons in `n` holes, hard for resolution
+fn pigeon_hole(n: i32) -> String {
+    let var = |p: i32, h: i32| p * n + h + 1;
+    let mut s = format!("p cnf {} {}\n", (n+1) * n, (n+1) + n * n * (n+1) / 2);
+    for p in 0 .. n+1 {
+        for h in 0 .. n { s.push_str(&format!("{} ", var(p, h))) }
+        s.push_str("0\n");
+    }
+    for h in 0 .. n {
+        for p1 in 0 .. n+1 {
+            for p2 in p1+1 .. n+1 {
+                s.push_str(&format!("-{} -{} 0\n", var(p1, h), var(p2, h)));
+            }
+        }
+    }
+    s
+}
+
+#[test]
+fn test_timeout() {
+    // the time limit is reached long before the problem is solved
+    let mut p = Command::new(env!("CARGO_BIN_EXE_batsmt-run"))
+        .args(["--format", "dimacs", "--timeout", "0.001"])
+        .stdin(Stdio::piped()).stdout(Stdio::piped())
+        .spawn().unwrap();
+    p.stdin.take().unwrap().write_all(pigeon_hole(10).as_bytes()).unwrap();
+    let out = p.wait_with_output().unwrap();
+    let out = String::from_utf8(out.stdout).unwrap();
+    assert!(out.lines().any(|l| l == "s UNKNOWN"), "{}", out);
+}
@@ -0,0 +1,23 @@
+use std::{io::Write, process::{Command, Stdio}, };
+
+// run `batsmt-run` on `input`, from stdin
+fn run(input: &str) -> String {
+    let mut p = Command::new(env!("CARGO_BIN_EXE_batsmt-run"))
+        .stdin(Stdio::piped()).stdout(Stdio::piped())
+        .spawn().unwrap();
+    p.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let out = p.wait_with_output().unwrap();
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn test_reset_options() {
+    // `reset` restores the options of the command line
+    let before = run("(get-option :produce-proofs) (get-option :random-seed) (get-option :timeout)");
+    let out = run("(set-option :produce-proofs true) (set-option :random-seed 42) \
+        (set-option :timeout 1000) (get-option :produce-proofs) (get-option :random-seed) \
+        (reset) (get-option :produce-proofs) (get-option :random-seed) (get-option :timeout)");
+    let lines: Vec<_> = out.lines().collect();
+    assert_eq!(&lines[..2], &["true", "42"], "{}", out);
+    assert_eq!(lines[2..].join("\n"), before.trim_end(), "{}", out);
+}
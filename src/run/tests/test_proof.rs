@@ -0,0 +1,52 @@
+extern crate batsmt_check;
+
+use {
+    std::{env, fs, process::Command, },
+    batsmt_check::{sexp, Checker, Outcome, },
+};
+
+// solve `problem` with `--proof`, and check the proof
+fn check_proof(name: &str, problem: &str) -> Outcome {
+    let dir = env::temp_dir();
+    let input = dir.join(format!("batsmt-{}-{}.smt2", name, std::process::id()));
+    let proof = dir.join(format!("batsmt-{}-{}.proof", name, std::process::id()));
+    fs::write(&input, problem).unwrap();
+    let out = Command::new(env!("CARGO_BIN_EXE_batsmt-run"))
+        .arg("--proof").arg(&proof).arg(&input)
+        .output().unwrap();
+    assert!(String::from_utf8_lossy(&out.stdout).contains("unsat"), "{:?}", out);
+
+    let s = fs::read_to_string(&proof).unwrap();
+    let _ = fs::remove_file(&input);
+    let _ = fs::remove_file(&proof);
+    let steps = sexp::parse_all(&s).unwrap();
+    match Checker::new().check_all(&steps) {
+        Ok(o) => o,
+        Err(e) => panic!("proof rejected: {}\n{}", e, s),
+    }
+}
+
+#[test]
+fn test_euf() {
+    let pb = "(declare-sort U 0) (declare-fun f (U) U) (declare-const a U) (declare-const b U)
+        (assert (= a b)) (assert (or (not (= (f a) (f b))) (= (f (f a)) a)))
+        (assert (not (= (f (f b)) b))) (check-sat)";
+    assert_eq!(check_proof("euf", pb), Outcome::Refutation);
+}
+
+#[test]
+fn test_lra() {
+    // arithmetic lemmas are trusted
+    let pb = "(declare-const x Real) (declare-const y Real)
+        (assert (< x y)) (assert (or (< y x) (= x y))) (check-sat)";
+    assert_eq!(check_proof("lra", pb), Outcome::Refutation);
+}
+
+#[test]
+fn test_incremental() {
+    // the proof is written as the solver goes, across `check-sat`
+    let pb = "(declare-const p Bool) (declare-const q Bool)
+        (assert (or p q)) (push 1) (assert (not p)) (assert (not q)) (check-sat) (pop 1)
+        (check-sat)";
+    assert_eq!(check_proof("incr", pb), Outcome::RefutationUnderAssumptions);
+}
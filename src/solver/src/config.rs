@@ -0,0 +1,197 @@
+
+//! Solver configuration.
+//!
+//! Parameters of the SAT solver and of its interaction with the theory,
+//! given to `Solver::new_with`. Options can also be set by name
+//! (see `SolverConfig::set`), so that frontends can expose them directly.
+
+use {
+    std::{fmt, str::FromStr},
+};
+
+/// Restart strategy of the SAT solver.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum Restarts {
+    /// Restart intervals follow the Luby sequence.
+    Luby,
+    /// Restart intervals grow geometrically.
+    Geometric,
+}
+
+/// How the SAT solver picks the polarity of decisions.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum PhaseSaving {
+    /// Always use the default polarity of the variable.
+    None,
+    /// Save the polarity of variables assigned at the last decision level.
+    Limited,
+    /// Save the polarity of all variables.
+    Full,
+}
+
+/// Configuration of a solver.
+#[derive(Clone,Debug)]
+pub struct SolverConfig {
+    /// Restart strategy.
+    pub restarts: Restarts,
+    /// Number of conflicts before the first restart.
+    pub restart_first: u32,
+    /// Growth factor of the interval between restarts.
+    pub restart_inc: f64,
+    /// Phase saving.
+    pub phase_saving: PhaseSaving,
+    /// Seed of the SAT solver's random choices. Must not be 0.
+    pub seed: u64,
+    /// Frequency of random decisions, in `[0,1]`.
+    pub random_freq: f64,
+    /// Enable theory propagation.
+    pub th_propagation: bool,
+    /// Give partial models to the theory, not only full ones.
+    pub partial_check: bool,
+    /// Minimum limit on the number of learnt clauses.
+    pub min_learnts: u32,
+    /// Initial limit on the number of learnt clauses, relative to the
+    /// number of clauses.
+    pub learnts_factor: f64,
+    /// Growth factor of the limit on learnt clauses.
+    pub learnts_inc: f64,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            restarts: Restarts::Geometric,
+            restart_first: 1000,
+            restart_inc: 15.,
+            phase_saving: PhaseSaving::Full,
+            seed: 91648253,
+            random_freq: 0.,
+            th_propagation: true,
+            partial_check: true,
+            min_learnts: 1_200,
+            learnts_factor: 1. / 3.,
+            learnts_inc: 1.1,
+        }
+    }
+}
+
+fn parse<T: FromStr>(key: &str, v: &str) -> Result<T, String> {
+    v.parse::<T>().map_err(|_| format!("invalid value {:?} for option {}", v, key))
+}
+
+fn check(key: &str, ok: bool, what: &str) -> Result<(), String> {
+    if ok { Ok(()) } else { Err(format!("option {} should be {}", key, what)) }
+}
+
+impl SolverConfig {
+    /// Default configuration.
+    pub fn new() -> Self { SolverConfig::default() }
+
+    /// Names of the options, as understood by `set` and `get`.
+    pub const KEYS: &'static [&'static str] = &[
+        "restarts", "restart-first", "restart-inc", "phase-saving", "seed", "random-freq",
+        "th-propagation", "partial-check", "min-learnts", "learnts-factor", "learnts-inc",
+    ];
+
+    /// Set option `key` to the value `v`, parsed from a string.
+    ///
+    /// Fails if `key` is unknown or `v` is not a valid value for it,
+    /// in which case the configuration is unchanged.
+    pub fn set(&mut self, key: &str, v: &str) -> Result<(), String> {
+        match key {
+            "restarts" => {
+                self.restarts = match v {
+                    "luby" => Restarts::Luby,
+                    "geometric" => Restarts::Geometric,
+                    _ => return check(key, false, "luby or geometric"),
+                }
+            },
+            "restart-first" => {
+                // the SAT solver takes an `i32`
+                let n = parse(key, v)?;
+                check(key, n > 0 && n <= i32::MAX as u32, "positive, at most 2147483647")?;
+                self.restart_first = n;
+            },
+            "restart-inc" => {
+                let x = parse(key, v)?;
+                check(key, x > 1., "greater than 1")?;
+                self.restart_inc = x;
+            },
+            "phase-saving" => {
+                self.phase_saving = match v {
+                    "none" => PhaseSaving::None,
+                    "limited" => PhaseSaving::Limited,
+                    "full" => PhaseSaving::Full,
+                    _ => return check(key, false, "none, limited or full"),
+                }
+            },
+            "seed" => {
+                let n = parse(key, v)?;
+                check(key, n > 0, "positive")?;
+                self.seed = n;
+            },
+            "random-freq" => {
+                let x = parse(key, v)?;
+                check(key, x >= 0. && x <= 1., "in [0,1]")?;
+                self.random_freq = x;
+            },
+            "th-propagation" => self.th_propagation = parse(key, v)?,
+            "partial-check" => self.partial_check = parse(key, v)?,
+            "min-learnts" => {
+                let n = parse(key, v)?;
+                check(key, n <= i32::MAX as u32, "at most 2147483647")?;
+                self.min_learnts = n;
+            },
+            "learnts-factor" => {
+                let x = parse(key, v)?;
+                check(key, x > 0., "positive")?;
+                self.learnts_factor = x;
+            },
+            "learnts-inc" => {
+                let x = parse(key, v)?;
+                check(key, x >= 1., "at least 1")?;
+                self.learnts_inc = x;
+            },
+            _ => return Err(format!("unknown option {}", key)),
+        }
+        Ok(())
+    }
+
+    /// Value of option `key`, in the syntax accepted by `set`.
+    pub fn get(&self, key: &str) -> Option<String> {
+        let s = match key {
+            "restarts" => self.restarts.to_string(),
+            "restart-first" => self.restart_first.to_string(),
+            "restart-inc" => self.restart_inc.to_string(),
+            "phase-saving" => self.phase_saving.to_string(),
+            "seed" => self.seed.to_string(),
+            "random-freq" => self.random_freq.to_string(),
+            "th-propagation" => self.th_propagation.to_string(),
+            "partial-check" => self.partial_check.to_string(),
+            "min-learnts" => self.min_learnts.to_string(),
+            "learnts-factor" => self.learnts_factor.to_string(),
+            "learnts-inc" => self.learnts_inc.to_string(),
+            _ => return None,
+        };
+        Some(s)
+    }
+}
+
+impl fmt::Display for Restarts {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        out.write_str(match self {
+            Restarts::Luby => "luby",
+            Restarts::Geometric => "geometric",
+        })
+    }
+}
+
+impl fmt::Display for PhaseSaving {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        out.write_str(match self {
+            PhaseSaving::None => "none",
+            PhaseSaving::Limited => "limited",
+            PhaseSaving::Full => "full",
+        })
+    }
+}
@@ -4,9 +4,14 @@
 pub mod lit_map;
 pub mod solver;
 pub mod blit;
+pub mod proof;
+pub mod limits;
+pub mod config;
 
 pub use crate::{
   lit_map::SatLitMap,
   solver::Solver,
   blit::BLit,
+  limits::{Limits, Reason, InterruptHandle},
+  config::SolverConfig,
 };
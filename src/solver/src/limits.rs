@@ -0,0 +1,117 @@
+
+//! Resource limits.
+//!
+//! A call to `solve` can be given limits on the time it takes,
+//! the number of conflicts and decisions of the SAT solver, and the memory
+//! used by the process. If one of them is reached, the answer is `Unknown`.
+
+use {
+    std::{fmt, fs, sync::{Arc, atomic::{AtomicBool, Ordering}}, },
+};
+
+/// Limits for a single call to `solve`.
+///
+/// `None` means there is no limit.
+#[derive(Clone,Debug,Default)]
+pub struct Limits {
+    /// Wall-clock time, in seconds.
+    pub time: Option<f64>,
+    /// Number of conflicts.
+    pub conflicts: Option<u64>,
+    /// Number of decisions.
+    pub decisions: Option<u64>,
+    /// Resident memory of the process, in megabytes.
+    pub memory: Option<usize>,
+}
+
+/// Why the solver could not decide satisfiability.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum Reason {
+    /// The theory could not validate the candidate model.
+    Incomplete,
+    Timeout,
+    Conflicts,
+    Decisions,
+    Memory,
+    /// `InterruptHandle::interrupt` was called.
+    Interrupted,
+}
+
+/// A handle to interrupt a running call to `solve`, possibly from
+/// another thread.
+///
+/// Handles are cheap to clone, and all the clones of a handle obtained
+/// from a solver interrupt the same solver.
+#[derive(Clone,Debug,Default)]
+pub struct InterruptHandle(Arc<Flags>);
+
+#[derive(Debug,Default)]
+struct Flags {
+    interrupted: AtomicBool,
+    running: AtomicBool, // is `solve` running?
+}
+
+impl Limits {
+    /// No limit at all.
+    pub fn new() -> Self { Limits::default() }
+
+    /// Is there any limit?
+    pub fn is_empty(&self) -> bool {
+        self.time.is_none() && self.conflicts.is_none() &&
+            self.decisions.is_none() && self.memory.is_none()
+    }
+}
+
+impl fmt::Display for Reason {
+    // as in `(get-info :reason-unknown)`
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Reason::Incomplete => "incomplete",
+            Reason::Timeout => "timeout",
+            Reason::Conflicts => "conflicts",
+            Reason::Decisions => "decisions",
+            Reason::Memory => "memout",
+            Reason::Interrupted => "interrupted",
+        };
+        write!(out, "{}", s)
+    }
+}
+
+impl InterruptHandle {
+    /// New handle, not attached to any solver yet (see
+    /// `Solver::set_interrupt_handle`).
+    pub fn new() -> Self { InterruptHandle::default() }
+
+    /// Ask the solver to stop.
+    ///
+    /// The current call to `solve` returns `Unknown(Interrupted)` soon
+    /// afterwards. If none is running, this has no effect. The solver stays
+    /// usable for further calls.
+    pub fn interrupt(&self) {
+        self.0.interrupted.store(true, Ordering::Relaxed)
+    }
+
+    /// Was `interrupt` called during the current call to `solve`?
+    #[inline]
+    pub fn is_interrupted(&self) -> bool { self.0.interrupted.load(Ordering::Relaxed) }
+
+    /// Is a call to `solve` running?
+    pub fn is_running(&self) -> bool { self.0.running.load(Ordering::Relaxed) }
+
+    // at the beginning and at the end of `solve`
+    pub(crate) fn set_running(&self, b: bool) {
+        self.0.interrupted.store(false, Ordering::Relaxed);
+        self.0.running.store(b, Ordering::Relaxed);
+    }
+}
+
+/// Resident memory of the current process, in megabytes, if available.
+///
+/// This relies on `VmRSS` in `/proc/self/status`, so it only works on Linux.
+pub fn resident_memory() -> Option<usize> {
+    let s = fs::read_to_string("/proc/self/status").ok()?;
+    // the line is `VmRSS:   1234 kB`
+    let line = s.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: usize = line["VmRSS:".len() ..].split_whitespace().next()?.parse().ok()?;
+    Some(kb / 1024)
+}
@@ -7,7 +7,7 @@ use {
         ast_u32::{AST, ManagerU32, },
     },
     batsmt_theory::{self as theory, BoolLit, },
-    batsat::{LMap, },
+    batsat::{self as sat, LMap, intmap::AsIndex, },
     crate::BLit,
 };
 
@@ -22,6 +22,7 @@ pub struct SatLitMap {
     term_to_lit: ast::HashMap<AST,BLit>,
     new_theory_lits: Vec<(AST,BLit)>, // only bidir terms
     lit_to_term: LMap<(AST,bool)>,
+    var_to_term: Vec<Option<(AST,bool)>>, // all terms, for printing
 }
 
 impl theory::LitMap<BLit> for SatLitMap {
@@ -31,6 +32,7 @@ impl theory::LitMap<BLit> for SatLitMap {
             b,
             term_to_lit: ast::HashMap::new(),
             lit_to_term: LMap::new(),
+            var_to_term: vec!(),
             new_theory_lits: vec!(),
         }
     }
@@ -91,6 +93,21 @@ impl SatLitMap {
             self.new_theory_lits.push((t, lit));
         }
         self.term_to_lit.insert(t, lit);
+        let v = lit.0.var().as_index();
+        if v >= self.var_to_term.len() { self.var_to_term.resize(v+1, None) }
+        self.var_to_term[v] = Some((t, lit.0.sign()));
+    }
+
+    /// Term `t` and sign such that the positive literal of `v` is `t=sign`.
+    ///
+    /// Unlike `map_lit`, this works for every term, not only theory terms.
+    pub fn var_term(&self, v: sat::Var) -> Option<(AST,bool)> {
+        self.var_to_term.get(v.as_index()).cloned().unwrap_or(None)
+    }
+
+    /// Iterate over all the terms that are mapped to a literal.
+    pub fn iter_terms<'a>(&'a self) -> impl Iterator<Item=(AST,BLit)> + 'a {
+        self.term_to_lit.iter().map(|(t,lit)| (*t, *lit))
     }
 
     /// Iterate over new theory literals, and remove them.
@@ -0,0 +1,297 @@
+
+//! Proof production.
+//!
+//! When proofs are enabled (see `Solver::enable_proofs`), the solver records
+//! every clause it knows about, along with a justification, so that
+//! `UNSAT` answers can be checked independently.
+//!
+//! ## Format
+//!
+//! A proof is a sequence of S-expressions, loosely following
+//! [Alethe](https://verit.loria.fr/documentation/alethe-spec.pdf).
+//!
+//! A clause is written `(cl l1 … ln)`. Each literal is a boolean term `t`
+//! or its negation `(not t)`. Pure boolean literals that have no term
+//! (e.g. selectors of named assertions or activation literals of scopes)
+//! are written `@bN` or `(not @bN)`, where `N` is the variable's index.
+//!
+//! Steps are named `c0`, `c1`, … in the order they appear:
+//!
+//! - `(assume cN C)` is an input clause, or the definition of a fresh
+//!     literal `@bN` (used for case splits, e.g. `(cl @bN t)` and
+//!     `(cl (not @bN) (not t))`), which preserves satisfiability.
+//! - `(assume cN C :assertion t)` is the clause obtained from the assertion `t`,
+//!     after simplification and Tseitin transformation. Literals of `C`
+//!     other than the last one are guards (`(not @bN)`).
+//! - `(step cN C :rule tseitin)` is a definitional clause of the
+//!     Tseitin transformation. It is a propositional tautology once the
+//!     boolean connectives of `C` are interpreted.
+//! - `(step cN C :rule th-lemma :explanation (e1 … en))` is a theory lemma,
+//!     i.e. a tautology modulo the theory (a conflict, or the explanation of
+//!     a propagation). The explanation is optional; each `ei` is an
+//!     equation that holds under the negation of `C`:
+//!     * `(lit a b)`: `a = b` is asserted by the negation of a literal of `C`
+//!         (where `b` may be `true` or `false` for boolean atoms);
+//!     * `(cong a b)`: `a` and `b` are congruent applications;
+//!     * `(eq a b (x1 y1) … (xk yk))`: `a = b` follows from `x1 = y1 ∧ … ∧ xk = yk`
+//!         by a theory rule (if-then-else, injectivity of constructors, …);
+//!     * `(axiom a b)`: `a = b` is an axiom of the theory.
+//!     Together, the equations entail `true = false`.
+//! - `(step cN C :rule trust)` is a theory lemma that comes without any
+//!     explanation (e.g. an instance of a quantified formula, or a lemma of
+//!     arithmetic). Checkers take it for granted.
+//! - `(step cN C :rule drup)` is a clause learnt by the SAT solver. It
+//!     follows from the active clauses by reverse unit propagation.
+//! - `(delete C)` removes a clause from the set of active clauses.
+//!     Deleting an unknown clause does nothing.
+//!
+//! After an `UNSAT` answer, the last step is a `drup` step for the empty
+//! clause, or for the clause made of the negation of some assumptions
+//! (including selectors and activation literals) if there were any.
+//!
+//! Theory propagations are disabled when proofs are enabled, so that every
+//! theory lemma is a conflict or a lemma. It is a `th-lemma` step if the
+//! theory explained it with `Actions::justify`, and a `trust` step otherwise.
+//!
+//! ## Output
+//!
+//! A proof is either kept in memory until it is printed (`Proof::new`),
+//! or written into some output as the solver goes (`Proof::with_output`).
+//! In the latter case, steps are only kept until the next `flush`, so
+//! memory does not grow with the length of the proof.
+
+use {
+    std::io::{self, Write},
+    batsat::{self as sat, intmap::AsIndex, },
+    batsmt_core::ast_u32::AST,
+    batsmt_theory::{Ctx, ExplStep, EqReason, pp_ast, },
+    crate::{BLit, lit_map::SatLitMap, },
+};
+
+/// Justification of a clause.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum Rule {
+    /// Input clause, possibly obtained from the given assertion.
+    Assume(Option<AST>),
+    /// Definitional clause of the Tseitin transformation.
+    Tseitin,
+    /// Theory lemma.
+    ThLemma,
+    /// Theory lemma without explanation.
+    Trust,
+    /// Learnt clause.
+    Drup,
+    /// Deletion of a clause.
+    Delete,
+}
+
+#[derive(Clone,Debug)]
+struct Step {
+    rule: Rule,
+    lits: (usize, usize), // slice of `Proof.lits`
+    expl: (usize, usize), // slice of `Proof.expl`
+}
+
+/// A proof, as a list of steps.
+pub struct Proof {
+    steps: Vec<Step>,
+    lits: Vec<sat::Lit>,
+    expl: Vec<ExplStep<AST, BLit>>,
+    expl_start: usize, // beginning of the explanation for the next theory lemma
+    n_clauses: usize, // clauses already written into `out`
+    out: Option<Box<dyn Write>>, // where to write steps, on `flush`
+    err: Option<io::Error>, // first error while writing into `out`
+}
+
+impl Proof {
+    /// New empty proof, kept in memory.
+    pub fn new() -> Self {
+        Proof {
+            steps: vec!(), lits: vec!(), expl: vec!(), expl_start: 0,
+            n_clauses: 0, out: None, err: None,
+        }
+    }
+
+    /// New empty proof, written into `out` on each `flush`.
+    pub fn with_output(out: Box<dyn Write>) -> Self {
+        Proof { out: Some(out), ..Proof::new() }
+    }
+
+    /// Number of steps in memory.
+    pub fn len(&self) -> usize { self.steps.len() }
+
+    /// Set the explanation for the next theory lemma.
+    pub fn set_explanation(&mut self, e: &[ExplStep<AST, BLit>]) {
+        self.expl.truncate(self.expl_start);
+        self.expl.extend_from_slice(e);
+    }
+
+    /// Is there an explanation for the next theory lemma?
+    pub fn has_explanation(&self) -> bool { self.expl.len() > self.expl_start }
+
+    /// Add a step for clause `c`.
+    pub fn add_step(&mut self, rule: Rule, c: &[sat::Lit]) {
+        let lits = (self.lits.len(), self.lits.len() + c.len());
+        self.lits.extend_from_slice(c);
+        let expl = if rule == Rule::ThLemma {
+            (self.expl_start, self.expl.len())
+        } else {
+            // explanations are only for theory lemmas
+            self.expl.truncate(self.expl_start);
+            (self.expl_start, self.expl_start)
+        };
+        self.expl_start = self.expl.len();
+        self.steps.push(Step{rule, lits, expl});
+    }
+
+    /// Does the proof have its own output?
+    pub fn has_output(&self) -> bool { self.out.is_some() }
+
+    /// Write the steps added since the last call into the output, and
+    /// forget them.
+    ///
+    /// Does nothing if the proof has no output. The first error is
+    /// kept until `finish`, and further steps are dropped.
+    pub fn flush<C>(&mut self, m: &C, lit_map: &SatLitMap) where C: Ctx<B=BLit> {
+        let out = match &mut self.out {
+            None => return,
+            Some(out) => out,
+        };
+        if self.steps.is_empty() { return }
+        if self.err.is_none() {
+            let res = write_steps(
+                m, lit_map, &self.steps, &self.lits, &self.expl, &mut self.n_clauses, out);
+            if let Err(e) = res { self.err = Some(e) }
+        }
+        self.steps.clear();
+        self.lits.clear();
+        // keep the explanation for the next theory lemma
+        self.expl.drain(.. self.expl_start);
+        self.expl_start = 0;
+    }
+
+    /// `flush`, then flush the output itself, and return the first
+    /// error since the last call.
+    pub fn finish<C>(&mut self, m: &C, lit_map: &SatLitMap) -> io::Result<()>
+        where C: Ctx<B=BLit>
+    {
+        self.flush(m, lit_map);
+        if let Some(e) = self.err.take() { return Err(e) }
+        match &mut self.out {
+            Some(out) => out.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Print the proof into `out`, using `lit_map` to map literals back into terms.
+    ///
+    /// If the proof has its own output, only the steps that are not
+    /// flushed yet are printed.
+    pub fn write<C, W>(&self, m: &C, lit_map: &SatLitMap, out: &mut W) -> io::Result<()>
+        where C: Ctx<B=BLit>, W: Write
+    {
+        let mut n_clauses = self.n_clauses;
+        write_steps(m, lit_map, &self.steps, &self.lits, &self.expl, &mut n_clauses, out)
+    }
+}
+
+/// Print `steps`, naming clauses from `n_clauses` on.
+fn write_steps<C, W>(
+    m: &C, lit_map: &SatLitMap, steps: &[Step], lits: &[sat::Lit],
+    expl: &[ExplStep<AST, BLit>], n_clauses: &mut usize, out: &mut W
+) -> io::Result<()>
+    where C: Ctx<B=BLit>, W: Write + ?Sized
+{
+    let mut pr = Printer{m, lit_map, out};
+    for step in steps.iter() {
+        let c = &lits[step.lits.0 .. step.lits.1];
+        match step.rule {
+            Rule::Delete => {
+                write!(pr.out, "(delete ")?;
+                pr.clause(c)?;
+            },
+            Rule::Assume(a) => {
+                write!(pr.out, "(assume c{} ", n_clauses)?;
+                pr.clause(c)?;
+                if let Some(t) = a {
+                    write!(pr.out, " :assertion {}", pp_ast(m, &t))?;
+                }
+                *n_clauses += 1;
+            },
+            Rule::Tseitin | Rule::Trust | Rule::Drup => {
+                write!(pr.out, "(step c{} ", n_clauses)?;
+                pr.clause(c)?;
+                let r = match step.rule {
+                    Rule::Tseitin => "tseitin",
+                    Rule::Trust => "trust",
+                    _ => "drup",
+                };
+                write!(pr.out, " :rule {}", r)?;
+                *n_clauses += 1;
+            },
+            Rule::ThLemma => {
+                write!(pr.out, "(step c{} ", n_clauses)?;
+                pr.clause(c)?;
+                write!(pr.out, " :rule th-lemma")?;
+                let expl = &expl[step.expl.0 .. step.expl.1];
+                if expl.len() > 0 {
+                    write!(pr.out, " :explanation (")?;
+                    for (i,e) in expl.iter().enumerate() {
+                        if i > 0 { write!(pr.out, " ")?; }
+                        pr.expl_step(e)?;
+                    }
+                    write!(pr.out, ")")?;
+                }
+                *n_clauses += 1;
+            },
+        }
+        writeln!(pr.out, ")")?;
+    }
+    Ok(())
+}
+
+/// Temporary structure for printing.
+struct Printer<'a, C, W: ?Sized> {
+    m: &'a C,
+    lit_map: &'a SatLitMap,
+    out: &'a mut W,
+}
+
+impl<'a, C, W> Printer<'a, C, W> where C: Ctx<B=BLit>, W: Write + ?Sized {
+    fn lit(&mut self, lit: sat::Lit) -> io::Result<()> {
+        match self.lit_map.var_term(lit.var()) {
+            Some((t, sign)) if lit.sign() == sign => {
+                write!(self.out, "{}", pp_ast(self.m, &t))
+            },
+            Some((t, _)) => write!(self.out, "(not {})", pp_ast(self.m, &t)),
+            None if lit.sign() => write!(self.out, "@b{}", lit.var().as_index()),
+            None => write!(self.out, "(not @b{})", lit.var().as_index()),
+        }
+    }
+
+    fn clause(&mut self, c: &[sat::Lit]) -> io::Result<()> {
+        write!(self.out, "(cl")?;
+        for &lit in c.iter() {
+            write!(self.out, " ")?;
+            self.lit(lit)?;
+        }
+        write!(self.out, ")")
+    }
+
+    fn expl_step(&mut self, e: &ExplStep<AST, BLit>) -> io::Result<()> {
+        let m = self.m;
+        let (lhs, rhs) = (pp_ast(m, &e.lhs), pp_ast(m, &e.rhs));
+        match &e.reason {
+            EqReason::Lit(_) => write!(self.out, "(lit {} {})", lhs, rhs),
+            EqReason::Congruence => write!(self.out, "(cong {} {})", lhs, rhs),
+            EqReason::Axiom => write!(self.out, "(axiom {} {})", lhs, rhs),
+            EqReason::Eqns(v) => {
+                write!(self.out, "(eq {} {}", lhs, rhs)?;
+                for (a,b) in v.iter() {
+                    write!(self.out, " ({} {})", pp_ast(m, a), pp_ast(m, b))?;
+                }
+                write!(self.out, ")")
+            },
+        }
+    }
+}
@@ -2,12 +2,13 @@
 //! Main SMT solver
 
 use {
-    std::{fmt, marker::PhantomData, },
+    std::{fmt, io, marker::PhantomData, cell::{Cell, RefCell}, rc::Rc, },
     batsat as sat,
     batsmt_theory::{ self as theory,
         Ctx, Theory, TheoryLit, TheoryClauseRef, Trail, LitMap},
-    batsmt_core::{ backtrack, ast_u32::{AST, }, },
-    crate::{ lit_map::{SatLitMap}, },
+    batsmt_core::{ backtrack, ast_u32::{AST, }, stats::{self, Stats}, Chrono, },
+    crate::{ lit_map::{SatLitMap}, proof::{Proof, Rule}, limits::{Limits, Reason, InterruptHandle},
+        config::{SolverConfig, Restarts, PhaseSaving}, },
 };
 
 pub use {
@@ -24,6 +25,10 @@ struct CoreTheory<C: Ctx<B=BLit>, Th: Theory<C>> {
     trail_offset: backtrack::Ref<usize>, // current offset in the trail for the theory
     th_trail: Vec<(AST,bool,BLit)>, // temporary for trail slices
     th_stats: theory::Stats,
+    model: theory::Model<C>, // last model found by the theory
+    proof: Option<Rc<RefCell<Proof>>>, // shared with `Cb`
+    n_decisions: Rc<Cell<u64>>, // decisions in the current call to `solve`, shared with `Cb`
+    partial_check: bool, // give partial models to the theory?
     _m: PhantomData<C>,
 }
 
@@ -37,6 +42,7 @@ struct TmpAct<'a, 'b: 'a> {
     acts: &'a mut sat::theory::TheoryArg<'b>,
     lits: &'a mut Vec<sat::Lit>,
     lit_map: &'a mut SatLitMap,
+    proof: Option<&'a RefCell<Proof>>,
 }
 
 /// A SMT solver.
@@ -46,6 +52,9 @@ struct TmpAct<'a, 'b: 'a> {
 pub struct Solver<C: Ctx<B=BLit>, Th: Theory<C>> {
     s0: Solver0<C,Th>,
     lits: Vec<sat::Lit>, // temporary for clause
+    assumptions: Vec<sat::Lit>, // temporary for `solve_with`
+    scopes: Vec<sat::Lit>, // activation literals of user-level scopes
+    has_model: bool, // did the last call to `solve` return `SAT`?
 }
 
 struct Solver0<C: Ctx<B=BLit>, Th: Theory<C>> {
@@ -58,6 +67,17 @@ struct Solver0<C: Ctx<B=BLit>, Th: Theory<C>> {
 pub enum Res {
     SAT,
     UNSAT,
+    Unknown(Reason),
+}
+
+impl fmt::Display for Res {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Res::SAT => write!(out, "sat"),
+            Res::UNSAT => write!(out, "unsat"),
+            Res::Unknown(_) => write!(out, "unknown"),
+        }
+    }
 }
 
 /// Map theory literals into boolean literals.
@@ -84,7 +104,7 @@ fn get_or_create_lit_<C, F>(
 
 mod solver {
     use {
-        super::*, batsat::SolverInterface,
+        super::*, batsat::{SolverInterface, intmap::AsIndex, },
         batsmt_pretty::{Pretty1},
         batsmt_theory::LitMap,
     };
@@ -95,28 +115,51 @@ mod solver {
     {
         /// New Solver, using the given theory `th` and AST manager.
         pub fn new(b: Builtins, th: Th) -> Self {
+            Self::new_with(b, th, &SolverConfig::default())
+        }
+
+        /// New Solver, using the given theory `th` and configuration.
+        pub fn new_with(b: Builtins, th: Th, conf: &SolverConfig) -> Self {
+            debug!("solver.new {:?}", conf);
             let lit_map = SatLitMap::new(b.clone());
             let c = CoreTheory {
                 lits: Vec::new(),
                 th,
                 _m: PhantomData,
                 th_stats: theory::Stats::new(),
+                model: theory::Model::new(),
+                proof: None,
+                n_decisions: Rc::new(Cell::new(0)),
+                partial_check: conf.partial_check,
                 lit_map,
                 trail_offset: backtrack::Ref::new(0),
                 th_trail: Vec::new(),
             };
-            let cb = Cb::new();
+            let cb = Cb::new(c.n_decisions.clone());
             let mut opts = batsat::SolverOpts::default();
-            opts.luby_restart = false;
-            opts.restart_first = 1000;
-            opts.restart_inc = 15.;
-            opts.min_learnts_lim = 1_200; // min number of learnt clauses
+            opts.luby_restart = conf.restarts == Restarts::Luby;
+            opts.restart_first = conf.restart_first as i32;
+            opts.restart_inc = conf.restart_inc;
+            opts.phase_saving = match conf.phase_saving {
+                PhaseSaving::None => 0,
+                PhaseSaving::Limited => 1,
+                PhaseSaving::Full => 2,
+            };
+            opts.random_seed = conf.seed as f64;
+            opts.random_var_freq = conf.random_freq;
+            opts.min_learnts_lim = conf.min_learnts as i32; // min number of learnt clauses
+            opts.learntsize_factor = conf.learnts_factor;
+            opts.learntsize_inc = conf.learnts_inc;
             // create SAT solver
             let sat = batsat::Solver::new_with(opts, cb);
             let mut s = Solver {
                 s0: Solver0 { sat, c, },
                 lits: Vec::new(),
+                assumptions: Vec::new(),
+                scopes: Vec::new(),
+                has_model: false,
             };
+            s.enable_th_propagation(conf.th_propagation);
             s.init_logic();
             s
         }
@@ -142,10 +185,96 @@ mod solver {
             self.s0.c.th.enable_propagation(b)
         }
 
+        /// Enable proof production (see the `proof` module).
+        ///
+        /// The proof is kept in memory, see `write_proof`.
+        /// This must be called before any clause is added. It disables
+        /// theory propagation.
+        pub fn enable_proofs(&mut self) {
+            self.set_proof(Proof::new())
+        }
+
+        /// Enable proof production, writing the proof into `out`
+        /// as the solver goes (see `flush_proof`).
+        ///
+        /// This must be called before any clause is added. It disables
+        /// theory propagation.
+        pub fn enable_proofs_into(&mut self, out: Box<dyn io::Write>) {
+            self.set_proof(Proof::with_output(out))
+        }
+
+        fn set_proof(&mut self, p: Proof) {
+            if self.s0.c.proof.is_some() { return }
+            debug!("solver.enable-proofs");
+            let p = Rc::new(RefCell::new(p));
+            self.s0.sat.cb_mut().proof = Some(p.clone());
+            self.s0.c.proof = Some(p);
+            self.s0.c.th.enable_proofs(true);
+            // propagations cannot be justified
+            self.s0.c.th.enable_propagation(false);
+        }
+
+        /// Set the resource limits of each call to `solve`.
+        pub fn set_limits(&mut self, limits: Limits) {
+            debug!("solver.set-limits {:?}", limits);
+            self.s0.sat.cb_mut().limits = limits;
+        }
+
+        /// Current resource limits.
+        pub fn limits(&self) -> &Limits { &self.s0.sat.cb().limits }
+
+        /// A handle to interrupt `solve` from another thread.
+        pub fn interrupt_handle(&self) -> InterruptHandle {
+            self.s0.sat.cb().interrupt.clone()
+        }
+
+        /// Use `h` to interrupt `solve`, instead of this solver's own handle.
+        ///
+        /// Useful to share a handle between successive solvers.
+        pub fn set_interrupt_handle(&mut self, h: InterruptHandle) {
+            self.s0.sat.cb_mut().interrupt = h;
+        }
+
+        /// Are proofs enabled?
+        #[inline]
+        pub fn proofs_enabled(&self) -> bool { self.s0.c.proof.is_some() }
+
+        /// Print the proof into `out`.
+        ///
+        /// After the last call to `solve` returned `UNSAT`, this is a
+        /// refutation of the clauses (and assumptions) of this solver.
+        /// Fails if proofs were not enabled, or were enabled
+        /// with `enable_proofs_into`.
+        pub fn write_proof<W: io::Write>(&self, m: &C, out: &mut W) -> io::Result<()> {
+            match &self.s0.c.proof {
+                Some(p) if ! p.borrow().has_output() => p.borrow().write(m, &self.s0.c.lit_map, out),
+                _ => Err(io::Error::new(io::ErrorKind::Other, "proofs are not kept in memory")),
+            }
+        }
+
+        /// Write pending proof steps into the output given to `enable_proofs_into`.
+        ///
+        /// `solve` does it before returning, but only this reports errors.
+        pub fn flush_proof(&mut self, m: &C) -> io::Result<()> {
+            match &self.s0.c.proof {
+                Some(p) => p.borrow_mut().finish(m, &self.s0.c.lit_map),
+                None => Ok(()),
+            }
+        }
+
+        // add a step to the proof, if enabled
+        #[inline]
+        fn log_clause(&self, rule: Rule, c: &[sat::Lit]) {
+            if let Some(p) = &self.s0.c.proof {
+                p.borrow_mut().add_step(rule, c)
+            }
+        }
+
         /// Add a boolean clause.
         #[inline]
         pub fn add_bool_clause_reuse(&mut self, c: &mut Vec<sat::Lit>) {
             trace!("solver.add-bool-clause {:?}", c);
+            self.log_clause(Rule::Assume(None), c);
             self.s0.sat.add_clause_reuse(c);
         }
 
@@ -175,6 +304,46 @@ mod solver {
             lm.get_term_or_else(ctx, &t, true, bidir, f)
         }
 
+        /// Enter a new user-level scope.
+        ///
+        /// The scope comes with a fresh activation literal (see `scope_lit`),
+        /// which is assumed to be true by `solve_with` as long as the
+        /// scope is active. Clauses guarded by this literal are retracted
+        /// when the scope is popped.
+        pub fn push_scope(&mut self) {
+            let act = self.new_bool_lit();
+            trace!("solver.push-scope (activation lit {:?})", act);
+            self.scopes.push(act);
+        }
+
+        /// Exit the `n` innermost user-level scopes.
+        ///
+        /// Their activation literals are permanently set to false, so that
+        /// every clause that was guarded by them becomes trivially satisfied.
+        pub fn pop_scopes(&mut self, n: usize) {
+            assert!(n <= self.scopes.len(),
+                "cannot pop {} scopes (only {} active)", n, self.scopes.len());
+            for _ in 0 .. n {
+                let act = self.scopes.pop().unwrap();
+                trace!("solver.pop-scope (activation lit {:?})", act);
+                self.lits.clear();
+                self.lits.push(! act);
+                self.log_clause(Rule::Assume(None), &self.lits);
+                self.s0.sat.add_clause_reuse(&mut self.lits);
+            }
+            self.has_model = false;
+        }
+
+        /// Number of active user-level scopes.
+        #[inline]
+        pub fn n_scopes(&self) -> usize { self.scopes.len() }
+
+        /// Activation literal of the innermost user-level scope, if any.
+        ///
+        /// Clauses that belong to this scope should contain its negation.
+        #[inline]
+        pub fn scope_lit(&self) -> Option<sat::Lit> { self.scopes.last().cloned() }
+
         /// Declare the given literal, so it's decided by the SAT solver.
         pub fn add_lit(&mut self, m: &C, lit: TheoryLit<C>) {
             let blit = self.s0.get_or_create_lit(m, lit);
@@ -183,6 +352,14 @@ mod solver {
 
         /// Add a clause made from signed terms.
         pub fn add_clause(&mut self, m: &C, c: TheoryClauseRef<C>) {
+            self.add_clause_with(m, c, Rule::Assume(None))
+        }
+
+        /// Add a clause made from signed terms, justified by `rule` in proofs.
+        ///
+        /// `rule` must be either `Rule::Assume` or `Rule::Tseitin`.
+        pub fn add_clause_with(&mut self, m: &C, c: TheoryClauseRef<C>, rule: Rule) {
+            debug_assert!(match rule { Rule::Assume(_) | Rule::Tseitin => true, _ => false });
             trace!("solver.add-clause\n{}", c.pp(m));
             // use `self.lits` as temporary storage
             self.lits.clear();
@@ -193,6 +370,7 @@ mod solver {
                     let lit = s0.get_or_create_lit(m, lit);
                     lit.0
                 }));
+            self.log_clause(rule, &self.lits);
             self.s0.sat.add_clause_reuse(&mut self.lits);
         }
 
@@ -210,37 +388,61 @@ mod solver {
             info!("solver.sat.solve ({} assumptions)", assumptions.len());
 
             self.add_initial_literals(m);
+            self.s0.c.model.clear();
+            self.has_model = false;
+
+            // activation literals for active scopes are assumed, too
+            self.assumptions.clear();
+            self.assumptions.extend_from_slice(&self.scopes);
+            self.assumptions.extend_from_slice(assumptions);
 
-            trace!("assumptions: {:?}", assumptions);
+            trace!("assumptions: {:?}", &self.assumptions);
             let sat = &mut self.s0.sat;
+            sat.cb_mut().start();
             let r = {
                 // temporary theory, pass it to SAT
                 let mut th = TheoryTmp(&mut self.s0.c, m);
-                sat.solve_limited_th(&mut th, assumptions)
+                sat.solve_limited_th(&mut th, &self.assumptions)
             };
+            // an interruption only concerns the current call
+            sat.cb().interrupt.set_running(false);
             info!("{}, sat.conflicts {}, sat.decisions {}, sat.propagations {}, {}",
                   self.s0.c.th_stats,
                   sat.num_conflicts(), sat.num_decisions(),
                   sat.num_propagations(), sat.cb().stats());
             // convert result
-            if r == lbool::TRUE {
+            let res = if r == lbool::UNDEF {
+                let reason = self.s0.sat.cb().stopped.get().unwrap_or(Reason::Incomplete);
+                info!("solver: stopped ({})", reason);
+                Res::Unknown(reason)
+            } else if r == lbool::TRUE && self.s0.c.th.model_is_partial() {
+                info!("solver: the theory gave up on the candidate model");
+                Res::Unknown(Reason::Incomplete)
+            } else if r == lbool::TRUE {
+                self.s0.complete_model();
+                self.has_model = true;
                 Res::SAT
             } else {
                 assert_eq!(r, lbool::FALSE);
+                if self.proofs_enabled() {
+                    // conclude with the negation of the assumptions used
+                    self.lits.clear();
+                    self.lits.extend_from_slice(self.s0.sat.unsat_core());
+                    self.log_clause(Rule::Drup, &self.lits);
+                }
                 Res::UNSAT
-            }
+            };
+            self.s0.c.flush_proof(m);
+            res
         }
 
-        /* TODO
-        pub fn get_model(&self) -> &[(BLit, lbool)] {
-            let mut v = vec!();
-            for (i, v) in self.s0.sat.get_model().iter().enumerate() {
-                let lit = BLit::from_var(sat::Var::from_index(i), true);
-                v.push((lit, v));
-            }
-            v
+        /// Model of the last call to `solve`, if it returned `SAT`.
+        ///
+        /// The model maps terms known to the theory to their class'
+        /// representative, and boolean terms to their truth value.
+        pub fn get_model(&self) -> Option<&theory::Model<C>> {
+            if self.has_model { Some(&self.s0.c.model) } else { None }
         }
-        */
 
         /// Unsat core
         pub fn get_unsat_core(&mut self) -> &[sat::Lit] {
@@ -265,6 +467,16 @@ mod solver {
             self.s0.sat.value_lit(lit.0)
         }
 
+        /// Value of the given literal in the model of the last call to `solve`,
+        /// if it returned `SAT`.
+        pub fn model_value(&self, lit: BLit) -> Option<bool> {
+            if ! self.has_model { return None }
+            match self.s0.sat.get_model().get(lit.0.var().as_index()) {
+                Some(&v) if v != lbool::UNDEF => Some((v == lbool::TRUE) == lit.0.sign()),
+                _ => None,
+            }
+        }
+
         #[inline]
         pub fn unsat_core_contains_lit(&mut self, lit: sat::Lit) -> bool {
             self.s0.sat.unsat_core_contains_lit(lit)
@@ -286,6 +498,22 @@ mod solver {
         pub fn n_conflicts(&self) -> usize { self.s0.sat.num_conflicts() as usize }
         pub fn n_props(&self) -> usize { self.s0.sat.num_propagations() as usize }
         pub fn n_decisions(&self) -> usize { self.s0.sat.num_decisions() as usize }
+
+        /// Record the current values of the counters above, and of
+        /// `th_stats`, into `st`.
+        pub fn export_stats(&self, st: &Stats) {
+            st.counter("sat.vars").set(self.n_lits() as u64);
+            st.counter("sat.clauses").set(self.n_clauses() as u64);
+            st.counter("sat.conflicts").set(self.n_conflicts() as u64);
+            st.counter("sat.propagations").set(self.n_props() as u64);
+            st.counter("sat.decisions").set(self.n_decisions() as u64);
+            let th = self.th_stats();
+            st.counter("theory.conflicts").set(th.conflicts);
+            st.counter("theory.propagations").set(th.propagations);
+            st.counter("theory.lemmas").set(th.lemmas);
+            st.counter("theory.explanations").set(th.explanations);
+            st.counter("theory.explanation-lits").set(th.explanation_lits);
+        }
     }
 
     impl<C,Th> CoreTheory<C, Th>
@@ -296,23 +524,35 @@ mod solver {
             self.lit_map.map_lit(lit)
         }
 
+        // write pending proof steps, if the proof has an output
+        fn flush_proof(&self, m: &C) {
+            if let Some(p) = &self.proof {
+                p.borrow_mut().flush(m, &self.lit_map)
+            }
+        }
+
         // internal checking
         fn check<'a>(&mut self, m: &mut C, partial: bool, a: &mut sat::theory::TheoryArg<'a>)
         {
+            self.flush_proof(m);
+
             // no need to parse the trail or do anything, if the theory doesn't support partial
-            // checks
-            if partial && ! Th::has_partial_check() {
+            // checks, or they are disabled
+            let has_partial = self.partial_check && Th::has_partial_check();
+            if partial && ! has_partial {
                 return;
             }
 
             // obtain theory literals from `a`.
             // do we use the full model, or just what's not been examined last?
+            // If partial checks are disabled but the theory supports them, it still
+            // expects to see each literal only once.
             let model = {
-                if partial {
+                if partial || (Th::has_partial_check() && ! has_partial) {
                     let offset = *self.trail_offset;
                     let tr = a.model();
                     &tr[offset..]
-                } else if Th::has_partial_check() {
+                } else if has_partial {
                     &[] // already got the whole trail
                 } else {
                     a.model()
@@ -343,12 +583,20 @@ mod solver {
                 return; // trivial
             }
 
-            let CoreTheory{lits, th, lit_map, th_trail, th_stats: stats, ..} = self;
-            let mut acts = TmpAct{ok: true, acts: a, lits, lit_map, stats};
+            let CoreTheory{lits, th, lit_map, th_trail, th_stats: stats, model, proof, ..} = self;
+            let n_lemmas = stats.lemmas;
+            let proof = proof.as_ref().map(|p| &**p);
+            let mut acts = TmpAct{ok: true, acts: a, lits, lit_map, stats, proof};
             if partial {
                 th.partial_check(m, &mut acts, &Trail::from_slice(&th_trail));
             } else {
                 th.final_check(m, &mut acts, &Trail::from_slice(&th_trail));
+                if acts.ok && acts.stats.lemmas == n_lemmas {
+                    // the trail is a full model, ask the theory to save it
+                    // before the SAT solver backtracks
+                    model.clear();
+                    th.build_model(m, model);
+                }
             }
         }
     }
@@ -358,6 +606,10 @@ mod solver {
         where C: Ctx<B=BLit>, Th: Theory<C>
     {
         fn create_level(&mut self) {
+            // each new level starts with a decision
+            let n = &self.0.n_decisions;
+            n.set(n.get() + 1);
+            self.0.flush_proof(self.1);
             self.0.trail_offset.push_level();
             self.0.th.push_level(self.1);
         }
@@ -390,6 +642,14 @@ mod solver {
             };
             let e = self.0.th.explain_propagation(self.1, t, sign, blit).iter().map(|l| l.0);
             self.0.lits.extend(e);
+            self.0.th_stats.explanations += 1;
+            self.0.th_stats.explanation_lits += self.0.lits.len() as u64;
+            if let Some(pr) = &self.0.proof {
+                // the theory lemma is `e1 ∧ … ∧ en => p`
+                let c: Vec<_> =
+                    Some(p).into_iter().chain(self.0.lits.iter().map(|l| !*l)).collect();
+                pr.borrow_mut().add_step(Rule::ThLemma, &c);
+            }
             &self.0.lits
         }
     }
@@ -406,17 +666,87 @@ mod solver {
             };
             get_or_create_lit_(ctx, &mut self.c.lit_map, l, f)
         }
+
+        // add truth values of boolean terms into the model, using the SAT model
+        fn complete_model(&mut self) {
+            let Solver0{sat, c} = self;
+            let values = sat.get_model();
+            for (t, lit) in c.lit_map.iter_terms() {
+                match values.get(lit.0.var().as_index()) {
+                    Some(&v) if v != lbool::UNDEF => {
+                        c.model.add_bool(t, (v == lbool::TRUE) == lit.0.sign());
+                    },
+                    _ => (),
+                }
+            }
+        }
     }
 
     /// Used for callbacks in the SAT solver.
+    ///
+    /// It also enforces the resource limits, through `stop`.
     pub(super) struct Cb {
-        n_restarts: u32,
-        n_gc_calls: u32,
+        n_restarts: stats::Counter,
+        n_gc_calls: stats::Counter,
+        pub(super) proof: Option<Rc<RefCell<Proof>>>, // shared with `CoreTheory`
+        pub(super) limits: Limits,
+        pub(super) stopped: Cell<Option<Reason>>, // limit reached in the current call
+        pub(super) interrupt: InterruptHandle,
+        chrono: Chrono, // started with the current call
+        n_conflicts: u64, // conflicts in the current call
+        n_decisions: Rc<Cell<u64>>, // shared with `CoreTheory`
+        memout: bool,
     }
 
     impl Cb {
-        fn new() -> Self {
-            Cb { n_restarts: 0, n_gc_calls: 0, }
+        fn new(n_decisions: Rc<Cell<u64>>) -> Self {
+            Cb {
+                n_restarts: stats::counter("sat.restarts"),
+                n_gc_calls: stats::counter("sat.gc"),
+                proof: None,
+                limits: Limits::new(),
+                stopped: Cell::new(None),
+                interrupt: InterruptHandle::new(),
+                chrono: Chrono::new(),
+                n_conflicts: 0,
+                n_decisions,
+                memout: false,
+            }
+        }
+
+        /// Reset counters, at the beginning of a call to `solve`.
+        pub(super) fn start(&mut self) {
+            self.stopped.set(None);
+            self.interrupt.set_running(true);
+            self.chrono = Chrono::new();
+            self.n_conflicts = 0;
+            self.n_decisions.set(0);
+            self.memout = false;
+        }
+
+        // memory is only checked once in a while, it's expensive
+        fn check_memory(&mut self) {
+            if let Some(lim) = self.limits.memory {
+                if crate::limits::resident_memory().map_or(false, |m| m > lim) {
+                    self.memout = true;
+                }
+            }
+        }
+
+        // which limit is reached, if any?
+        fn limit_reached(&self) -> Option<Reason> {
+            let l = &self.limits;
+            if self.memout {
+                Some(Reason::Memory)
+            } else if l.conflicts.map_or(false, |n| self.n_conflicts >= n) {
+                Some(Reason::Conflicts)
+            } else if l.decisions.map_or(false, |n| self.n_decisions.get() >= n) {
+                Some(Reason::Decisions)
+            } else if l.time.map_or(false, |t| self.chrono.as_f64() >= t) {
+                Some(Reason::Timeout)
+            } else {
+                None
+            }
         }
 
         fn stats<'a>(&'a self) -> impl fmt::Display+'a { self }
@@ -425,15 +755,51 @@ mod solver {
     impl fmt::Display for Cb {
         fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
             write!(out, "sat.restarts: {}, sat.gc: {}",
-                   self.n_restarts, self.n_gc_calls)
+                   self.n_restarts.get(), self.n_gc_calls.get())
         }
     }
 
     impl batsat::Callbacks for Cb {
         #[inline(always)]
-        fn on_restart(&mut self) { self.n_restarts += 1 }
+        fn on_restart(&mut self) {
+            self.n_restarts.incr();
+            self.check_memory();
+        }
         #[inline(always)]
-        fn on_gc(&mut self, _: usize, _: usize) { self.n_gc_calls += 1; }
+        fn on_gc(&mut self, _: usize, _: usize) {
+            self.n_gc_calls.incr();
+            self.check_memory();
+        }
+
+        fn stop(&self) -> bool {
+            if self.stopped.get().is_some() { return true }
+            if self.interrupt.is_interrupted() {
+                self.stopped.set(Some(Reason::Interrupted));
+                return true
+            }
+            if self.limits.is_empty() { return false }
+            let r = self.limit_reached();
+            if r.is_some() { self.stopped.set(r) }
+            r.is_some()
+        }
+
+        fn on_new_clause(&mut self, c: &[sat::Lit], kind: sat::ClauseKind) {
+            // each conflict produces a learnt clause
+            if let sat::ClauseKind::Learnt = kind {
+                self.n_conflicts += 1;
+            }
+            // input clauses and theory lemmas are recorded by the solver itself,
+            // with more precise justifications
+            if let (Some(p), sat::ClauseKind::Learnt) = (&self.proof, kind) {
+                p.borrow_mut().add_step(Rule::Drup, c)
+            }
+        }
+
+        fn on_delete_clause(&mut self, c: &[sat::Lit]) {
+            if let Some(p) = &self.proof {
+                p.borrow_mut().add_step(Rule::Delete, c)
+            }
+        }
     }
 }
 
@@ -458,6 +824,11 @@ impl<'a,'b,C> theory::Actions<C> for TmpAct<'a,'b>
             self.lits.reserve(c.len());
             // convert `BLit -> sat::Lit`
             for BLit(a) in c.iter() { self.lits.push(*a) }
+            if let Some(p) = self.proof {
+                let mut p = p.borrow_mut();
+                let rule = if p.has_explanation() { Rule::ThLemma } else { Rule::Trust };
+                p.add_step(rule, &self.lits);
+            }
             self.acts.add_theory_lemma(&self.lits)
         }
     }
@@ -473,11 +844,22 @@ impl<'a,'b,C> theory::Actions<C> for TmpAct<'a,'b>
             self.lits.reserve(c.len());
             // convert `BLit -> sat::Lit`
             for BLit(a) in c.iter() { self.lits.push(*a) }
+            if let Some(p) = self.proof {
+                let mut p = p.borrow_mut();
+                let rule = if p.has_explanation() { Rule::ThLemma } else { Rule::Trust };
+                p.add_step(rule, &self.lits);
+            }
 
             self.acts.raise_conflict(&self.lits, costly);
         }
     }
 
+    fn justify(&mut self, expl: &[theory::ExplStep<C::AST, C::B>]) {
+        if let (true, Some(p)) = (self.ok, self.proof) {
+            p.borrow_mut().set_explanation(expl)
+        }
+    }
+
     #[inline]
     fn map_lit(&mut self, m: &C, lit: TheoryLit<C>) -> BLit {
         let TmpAct{acts,lit_map,..} = self;
@@ -485,5 +867,20 @@ impl<'a,'b,C> theory::Actions<C> for TmpAct<'a,'b>
         get_or_create_lit_(m, lit_map, lit, f)
     }
 
+    fn add_split(&mut self, p: BLit) {
+        if self.ok {
+            self.stats.lemmas += 1;
+            // define a fresh literal `s` as `¬p`; the clauses are not
+            // tautologies, so the SAT solver keeps them and decides `p`
+            let s = self.acts.mk_new_lit();
+            for c in [[s, p.0], [!s, !p.0]].iter() {
+                if let Some(pr) = self.proof {
+                    pr.borrow_mut().add_step(Rule::Assume(None), c);
+                }
+                self.acts.add_theory_lemma(c);
+            }
+        }
+    }
+
     fn has_conflict(&self) -> bool { !self.ok }
 }
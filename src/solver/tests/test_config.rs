@@ -0,0 +1,40 @@
+extern crate batsmt_solver;
+
+use batsmt_solver::{SolverConfig, config::Restarts};
+
+#[test]
+fn test_set_get() {
+    let mut conf = SolverConfig::new();
+    conf.set("restarts", "luby").unwrap();
+    conf.set("seed", "42").unwrap();
+    conf.set("th-propagation", "false").unwrap();
+    assert_eq!(conf.restarts, Restarts::Luby);
+    assert_eq!(conf.seed, 42);
+    assert!(! conf.th_propagation);
+    // `get` returns what `set` accepts
+    for k in SolverConfig::KEYS {
+        let v = conf.get(k).unwrap();
+        let mut conf2 = SolverConfig::new();
+        conf2.set(k, &v).unwrap();
+        assert_eq!(conf2.get(k).unwrap(), v);
+    }
+}
+
+#[test]
+fn test_set_invalid() {
+    let mut conf = SolverConfig::new();
+    assert!(conf.set("restarts", "sometimes").is_err());
+    assert!(conf.set("seed", "0").is_err());
+    assert!(conf.set("random-freq", "2").is_err());
+    assert!(conf.set("no-such-option", "1").is_err());
+    assert!(conf.get("no-such-option").is_none());
+
+    // invalid values leave the configuration unchanged
+    let def = SolverConfig::new();
+    for (k, v) in [("seed", "0"), ("random-freq", "2"), ("restart-inc", "0.5"),
+                   ("restart-first", "0"), ("restart-first", "2147483648"),
+                   ("min-learnts", "4000000000"), ("learnts-factor", "-1"), ("learnts-inc", "0")].iter() {
+        assert!(conf.set(k, v).is_err(), "{} {}", k, v);
+        assert_eq!(conf.get(k), def.get(k), "{}", k);
+    }
+}
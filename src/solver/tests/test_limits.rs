@@ -0,0 +1,20 @@
+extern crate batsmt_solver;
+
+use batsmt_solver::limits::{self, Limits, };
+
+#[test]
+fn test_resident_memory() {
+    // a running process uses at least some memory, but not terabytes
+    if cfg!(target_os = "linux") {
+        let m = limits::resident_memory().expect("no VmRSS");
+        assert!(m < 1_000_000, "{} MB", m);
+    }
+}
+
+#[test]
+fn test_limits_empty() {
+    let mut l = Limits::new();
+    assert!(l.is_empty());
+    l.conflicts = Some(10);
+    assert!(! l.is_empty());
+}
@@ -0,0 +1,230 @@
+
+//! Combination of two theories.
+//!
+//! Both theories see every literal of the trail, and are expected to ignore
+//! the ones they do not know about.
+//!
+//! Theories communicate through _interface equalities_ between shared terms
+//! (see `Theory::iter_interface_terms`). Since equalities are atoms,
+//! an equality implied by one theory is propagated to the SAT solver and
+//! the other theory sees it on the trail.
+//! Other equalities are found by model-based theory combination:
+//! in `final_check`, if the models of the two theories disagree on whether
+//! two shared terms are equal, the equality between them is added as a new
+//! atom, and the SAT solver has to decide it.
+//!
+//! Several theories can be combined by nesting `Combine`.
+
+use {
+    std::marker::PhantomData,
+    batsmt_core::backtrack::Backtrackable,
+    fxhash::{FxHashMap, FxHashSet, },
+    crate::{Ctx, Theory, Actions, Trail, TheoryLit, ExplStep, Model, },
+};
+
+/// A context that can build equalities between terms.
+pub trait HasEq : Ctx {
+    /// Build the boolean term `a = b`.
+    fn mk_eq(&mut self, a: &Self::AST, b: &Self::AST) -> Self::AST;
+}
+
+/// The combination of theories `T1` and `T2`.
+pub struct Combine<C:Ctx, T1, T2> {
+    th1: T1,
+    th2: T2,
+    origin: FxHashMap<C::B, bool>, // propagated literal -> propagated by `th1`?
+    new_lemma: bool, // did a theory add a lemma during this check?
+    eqns: FxHashSet<(C::AST, C::AST)>, // interface equalities already added
+    m1: Model<C>,
+    m2: Model<C>,
+    terms1: FxHashSet<C::AST>, // interface terms of `th1`
+    shared: Vec<C::AST>,
+    classes: FxHashMap<C::AST, C::AST>, // repr in one model -> shared term
+    tmp_eqns: Vec<(C::AST, C::AST)>,
+}
+
+/// Actions that record which theory propagated a literal.
+struct Tagged<'a, C:Ctx, A> {
+    acts: &'a mut A,
+    origin: &'a mut FxHashMap<C::B, bool>,
+    new_lemma: &'a mut bool,
+    first: bool,
+    _m: PhantomData<C>,
+}
+
+impl<C:Ctx, T1:Theory<C>, T2:Theory<C>> Combine<C, T1, T2> {
+    /// Combine the two theories.
+    pub fn new(th1: T1, th2: T2) -> Self {
+        Combine {
+            th1, th2, origin: FxHashMap::default(), new_lemma: false,
+            eqns: FxHashSet::default(), m1: Model::new(), m2: Model::new(),
+            terms1: FxHashSet::default(), shared: vec!(),
+            classes: FxHashMap::default(), tmp_eqns: vec!(),
+        }
+    }
+
+    /// Access the first theory.
+    pub fn th1(&self) -> &T1 { &self.th1 }
+
+    /// Access the second theory.
+    pub fn th2(&self) -> &T2 { &self.th2 }
+
+    /// Number of interface equalities added so far.
+    pub fn n_interface_eqns(&self) -> usize { self.eqns.len() }
+
+    /// Find shared terms that are equal in one model but not in the other.
+    ///
+    /// The corresponding equations are put into `self.tmp_eqns`.
+    fn find_disagreements(&mut self, c: &mut C) {
+        self.m1.clear();
+        self.m2.clear();
+        self.th1.build_model(c, &mut self.m1);
+        self.th2.build_model(c, &mut self.m2);
+
+        self.terms1.clear();
+        self.shared.clear();
+        {
+            let terms1 = &mut self.terms1;
+            self.th1.iter_interface_terms(c, |t| { terms1.insert(*t); });
+            let shared = &mut self.shared;
+            self.th2.iter_interface_terms(c, |t| {
+                if terms1.remove(t) { shared.push(*t) }
+            });
+        }
+
+        self.tmp_eqns.clear();
+        for &(m_a, m_b) in [(&self.m1, &self.m2), (&self.m2, &self.m1)].iter() {
+            // group shared terms by their class in `m_a`, and check that
+            // they also are in the same class in `m_b`
+            self.classes.clear();
+            for t in self.shared.iter() {
+                let r = *m_a.repr(t).unwrap_or(t);
+                let u = *self.classes.entry(r).or_insert(*t);
+                if u != *t && m_b.repr(&u).unwrap_or(&u) != m_b.repr(t).unwrap_or(t) {
+                    self.tmp_eqns.push(if u < *t { (u, *t) } else { (*t, u) });
+                }
+            }
+        }
+    }
+}
+
+impl<C:Ctx, T1:Theory<C>, T2:Theory<C>> Backtrackable<C> for Combine<C, T1, T2> {
+    fn push_level(&mut self, c: &mut C) {
+        self.th1.push_level(c);
+        self.th2.push_level(c);
+    }
+
+    fn pop_levels(&mut self, c: &mut C, n: usize) {
+        self.th1.pop_levels(c, n);
+        self.th2.pop_levels(c, n);
+    }
+}
+
+impl<C:HasEq, T1:Theory<C>, T2:Theory<C>> Theory<C> for Combine<C, T1, T2> {
+    fn final_check<A>(&mut self, c: &mut C, acts: &mut A, trail: &Trail<C>)
+        where A: Actions<C>
+    {
+        self.new_lemma = false;
+        {
+            let Combine{th1, th2, origin, new_lemma, ..} = self;
+            th1.final_check(c, &mut Tagged{acts, origin, new_lemma, first: true, _m: PhantomData}, trail);
+            if acts.has_conflict() { return }
+            th2.final_check(c, &mut Tagged{acts, origin, new_lemma, first: false, _m: PhantomData}, trail);
+            if acts.has_conflict() || *new_lemma { return }
+        }
+
+        // both theories are happy, now check that they agree
+        self.find_disagreements(c);
+        for &(a, b) in self.tmp_eqns.iter() {
+            if ! self.eqns.insert((a,b)) {
+                continue; // already added
+            }
+            let eq = c.mk_eq(&a, &b);
+            // let the SAT solver decide `a=b`
+            let lit = acts.map_lit(c, TheoryLit::new_t(eq, true));
+            acts.add_split(lit);
+        }
+    }
+
+    fn partial_check<A>(&mut self, c: &mut C, acts: &mut A, trail: &Trail<C>)
+        where A: Actions<C>
+    {
+        let Combine{th1, th2, origin, new_lemma, ..} = self;
+        th1.partial_check(c, &mut Tagged{acts, origin, new_lemma, first: true, _m: PhantomData}, trail);
+        if ! acts.has_conflict() {
+            th2.partial_check(c, &mut Tagged{acts, origin, new_lemma, first: false, _m: PhantomData}, trail);
+        }
+    }
+
+    // NOTE: if only one of the theories handles partial checks, the other one
+    // will not see the trail in `final_check`.
+    fn has_partial_check() -> bool {
+        debug_assert_eq!(T1::has_partial_check(), T2::has_partial_check());
+        T1::has_partial_check()
+    }
+
+    fn add_literal(&mut self, c: &mut C, t: C::AST, lit: C::B) {
+        self.th1.add_literal(c, t, lit);
+        self.th2.add_literal(c, t, lit);
+    }
+
+    fn explain_propagation(&mut self, c: &mut C, t: C::AST, sign: bool, p: C::B) -> &[C::B] {
+        match self.origin.get(&p) {
+            Some(true) => self.th1.explain_propagation(c, t, sign, p),
+            Some(false) => self.th2.explain_propagation(c, t, sign, p),
+            None => panic!("combine: no theory propagated {:?}", p),
+        }
+    }
+
+    fn enable_propagation(&mut self, on: bool) {
+        self.th1.enable_propagation(on);
+        self.th2.enable_propagation(on);
+    }
+
+    fn enable_proofs(&mut self, on: bool) {
+        self.th1.enable_proofs(on);
+        self.th2.enable_proofs(on);
+    }
+
+    // the second theory can override values given by the first one
+    fn build_model(&mut self, c: &mut C, model: &mut Model<C>) {
+        self.th1.build_model(c, model);
+        self.th2.build_model(c, model);
+    }
+
+    fn model_is_partial(&self) -> bool {
+        self.th1.model_is_partial() || self.th2.model_is_partial()
+    }
+
+    fn iter_interface_terms<F>(&mut self, c: &C, mut f: F) where F: FnMut(&C::AST) {
+        self.th1.iter_interface_terms(c, &mut f);
+        self.th2.iter_interface_terms(c, &mut f);
+    }
+}
+
+impl<'a, C:Ctx, A:Actions<C>> Actions<C> for Tagged<'a, C, A> {
+    fn add_lemma(&mut self, c: &[C::B]) {
+        *self.new_lemma = true;
+        self.acts.add_lemma(c)
+    }
+
+    fn propagate(&mut self, p: C::B) -> bool {
+        self.origin.insert(p, self.first);
+        self.acts.propagate(p)
+    }
+
+    fn raise_conflict(&mut self, c: &[C::B], costly: bool) {
+        self.acts.raise_conflict(c, costly)
+    }
+
+    fn map_lit(&mut self, m: &C, lit: TheoryLit<C>) -> C::B { self.acts.map_lit(m, lit) }
+
+    fn add_split(&mut self, p: C::B) {
+        *self.new_lemma = true;
+        self.acts.add_split(p)
+    }
+
+    fn justify(&mut self, expl: &[ExplStep<C::AST, C::B>]) { self.acts.justify(expl) }
+
+    fn has_conflict(&self) -> bool { self.acts.has_conflict() }
+}
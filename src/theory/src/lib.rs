@@ -16,10 +16,14 @@ use {
 };
 
 pub mod lit_map;
+pub mod model;
+pub mod combine;
 
 // re-exports for litmap
 pub use {
     crate::lit_map::{LitMap, Builtins as LitMapBuiltins, },
+    crate::model::Model,
+    crate::combine::{Combine, HasEq, },
 };
 
 /// Abstract notion of boolean literals.
@@ -133,12 +137,48 @@ pub trait Actions<C:Ctx> {
     /// Map a theory literal into a proper boolean literal.
     fn map_lit(&mut self, m: &C, lit: TheoryLit<C>) -> C::B;
 
+    /// Ask the boolean solver to decide `p`.
+    ///
+    /// Unlike a lemma `p ∨ ¬p`, which the solver may simplify away, this
+    /// ensures that the search goes on until `p` is assigned.
+    fn add_split(&mut self, p: C::B);
+
+    /// Justify the next conflict or lemma with a list of equations.
+    ///
+    /// This is only used for proof production, and must be called right
+    /// before `raise_conflict` or `add_lemma`. By default it does nothing.
+    fn justify(&mut self, _expl: &[ExplStep<C::AST, C::B>]) {}
+
     /// Check if a conflict was found yet.
     ///
     /// This is useful to interrupt work early.
     fn has_conflict(&self) -> bool;
 }
 
+/// Reason why two terms are equal, in the explanation of a theory lemma.
+#[derive(Clone,Debug)]
+pub enum EqReason<AST, B> {
+    /// The equation comes from this asserted literal.
+    Lit(B),
+    /// The terms are congruent applications.
+    Congruence,
+    /// The equation is implied by these equations (e.g. by injectivity).
+    Eqns(Vec<(AST,AST)>),
+    /// The equation holds in the theory.
+    Axiom,
+}
+
+/// An equation `lhs = rhs`, used in the explanation of a theory lemma.
+///
+/// A list of such equations is a certificate that can be replayed
+/// by a proof checker.
+#[derive(Clone,Debug)]
+pub struct ExplStep<AST, B> {
+    pub lhs: AST,
+    pub rhs: AST,
+    pub reason: EqReason<AST, B>,
+}
+
 /// The theory subset of the (partial) model picked by the SAT solver.
 ///
 /// This is given to the theory in order to check its validity. It doesn't show
@@ -198,6 +238,61 @@ pub trait Theory<C:Ctx> : Backtrackable<C>{
 
     /// Enable/disable boolean propagation.
     fn enable_propagation(&mut self, _on: bool) {}
+
+    /// Enable/disable explanations of conflicts, for proofs.
+    ///
+    /// If enabled, the theory should call `Actions::justify` before
+    /// raising conflicts or adding lemmas.
+    fn enable_proofs(&mut self, _on: bool) {}
+
+    /// Store the current model into `model`.
+    ///
+    /// This is called right after a `final_check` that did not raise
+    /// any conflict nor add any lemma, so the theory's state reflects
+    /// a full, consistent model.
+    /// It should map the terms the theory knows about to
+    /// the representative of their class.
+    fn build_model(&mut self, _ctx: &mut C, _model: &mut Model<C>) {}
+
+    /// Is the model of the last `final_check` only a candidate?
+    ///
+    /// This is asked after a `final_check` that did not raise any conflict
+    /// nor add any lemma. A theory that is not complete (e.g. for
+    /// quantifiers) returns `true` if it could not validate the model,
+    /// in which case the solver cannot answer `SAT`.
+    fn model_is_partial(&self) -> bool { false }
+
+    /// Call `f` on the terms this theory might share with other theories.
+    ///
+    /// When theories are combined (see `Combine`), terms given by
+    /// two theories are _shared_, and both theories must agree on which
+    /// shared terms are equal in their respective models.
+    /// Calling `f` several times on the same term is fine.
+    /// By default, a theory shares nothing.
+    fn iter_interface_terms<F>(&mut self, _ctx: &C, _f: F) where F: FnMut(&C::AST) {}
+}
+
+/// A theory that does not interpret any literal.
+///
+/// With it, a solver is a plain SAT solver.
+#[derive(Clone,Copy,Debug,Default)]
+pub struct EmptyTheory;
+
+mod empty_theory {
+    use super::*;
+
+    impl<C:Ctx> Backtrackable<C> for EmptyTheory {
+        fn push_level(&mut self, _c: &mut C) {}
+        fn pop_levels(&mut self, _c: &mut C, _n: usize) {}
+    }
+
+    impl<C:Ctx> Theory<C> for EmptyTheory {
+        fn final_check<A:Actions<C>>(&mut self, _ctx: &mut C, _acts: &mut A, _trail: &Trail<C>) {}
+
+        fn explain_propagation(&mut self, _ctx: &mut C, _t: C::AST, _sign: bool, _p: C::B) -> &[C::B] {
+            unreachable!("the empty theory does not propagate")
+        }
+    }
 }
 
 /// Statistics.
@@ -206,6 +301,8 @@ pub struct Stats {
     pub conflicts: u64,
     pub propagations: u64,
     pub lemmas: u64,
+    pub explanations: u64, // propagations explained to the SAT solver
+    pub explanation_lits: u64, // sum of the size of these explanations
 }
 
 mod stats {
@@ -213,13 +310,15 @@ mod stats {
     impl Stats {
         /// New statistics accumulator.
         pub fn new() -> Self {
-            Stats{ conflicts: 0, propagations: 0, lemmas: 0, }
+            Stats{ conflicts: 0, propagations: 0, lemmas: 0, explanations: 0, explanation_lits: 0, }
         }
     }
     impl fmt::Display for Stats {
         fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
-            write!(out, "theory.conflicts {}, theory.propagations {}, theory.lemmas {}",
-                   self.conflicts, self.propagations, self.lemmas)
+            write!(out, "theory.conflicts {}, theory.propagations {}, theory.lemmas {}, \
+                         theory.explanations {}, theory.explanation-lits {}",
+                   self.conflicts, self.propagations, self.lemmas,
+                   self.explanations, self.explanation_lits)
         }
     }
     impl Default for Stats {
@@ -397,6 +496,9 @@ impl<C:Ctx> TheoryClauseSet<C> {
         self.offsets.push((idx, len));
     }
 
+    /// Number of clauses in the set.
+    pub fn len(&self) -> usize { self.offsets.len() }
+
     /// Iterate over the contained clauses.
     ///
     /// Use `c.into()` over the slices to turn them into proper `TheoryClause`,
@@ -524,7 +626,6 @@ pub struct SimpleActions<C:Ctx> {
     costly: bool,
     props: Vec<C::B>,
     lemmas: Vec<Vec<C::B>>,
-    #[allow(unused)]
     mk_lit: Box<Fn() -> C::B>, // FIXME: actual litmap or something?
 }
 
@@ -555,6 +656,14 @@ impl<C:Ctx> Actions<C> for SimpleActions<C> {
     fn map_lit(&mut self, _m: &C, _lit: TheoryLit<C>) -> C::B {
         unimplemented!("map-lit")
     }
+    // `s ∨ p` and `¬s ∨ ¬p`, for a fresh `s`
+    fn add_split(&mut self, p: C::B) {
+        if !self.has_conflict() {
+            let s = (self.mk_lit)();
+            self.lemmas.push(vec!(s, p));
+            self.lemmas.push(vec!(!s, !p));
+        }
+    }
 }
 
 impl<C:Ctx> SimpleActions<C> {
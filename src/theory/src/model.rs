@@ -0,0 +1,63 @@
+//! Term-level models.
+
+use {
+    batsmt_core::ast::{self, AstMap, },
+    crate::Ctx,
+};
+
+/// A term-level model, obtained from a satisfiable trail.
+///
+/// It maps every term known to the theory to the representative of its
+/// equivalence class, and boolean terms to their truth value.
+/// Turning classes into actual domain elements is left to the user,
+/// who knows about sorts.
+pub struct Model<C:Ctx> {
+    repr: ast::HashMap<C::AST, C::AST>,
+    bools: ast::HashMap<C::AST, bool>,
+}
+
+impl<C:Ctx> Model<C> {
+    /// New empty model.
+    pub fn new() -> Self {
+        Model { repr: ast::HashMap::new(), bools: ast::HashMap::new(), }
+    }
+
+    /// Remove all bindings.
+    pub fn clear(&mut self) {
+        self.repr.clear();
+        self.bools.clear();
+    }
+
+    /// Is the model empty?
+    pub fn is_empty(&self) -> bool { self.repr.is_empty() && self.bools.is_empty() }
+
+    /// Declare that `t` belongs to the class of `repr`.
+    #[inline]
+    pub fn add_repr(&mut self, t: C::AST, repr: C::AST) {
+        self.repr.insert(t, repr)
+    }
+
+    /// Declare the truth value of the boolean term `t`.
+    #[inline]
+    pub fn add_bool(&mut self, t: C::AST, b: bool) {
+        self.bools.insert(t, b)
+    }
+
+    /// Representative of the class of `t`, if `t` is known.
+    #[inline]
+    pub fn repr(&self, t: &C::AST) -> Option<&C::AST> { self.repr.get(t) }
+
+    /// Truth value of `t`, if `t` is a known boolean term.
+    #[inline]
+    pub fn value_bool(&self, t: &C::AST) -> Option<bool> { self.bools.get(t).cloned() }
+
+    /// Iterate over pairs `(term, representative)`.
+    pub fn iter_repr(&self) -> impl Iterator<Item=(&C::AST, &C::AST)> { self.repr.iter() }
+
+    /// Iterate over pairs `(boolean term, truth value)`.
+    pub fn iter_bool(&self) -> impl Iterator<Item=(&C::AST, &bool)> { self.bools.iter() }
+}
+
+impl<C:Ctx> Default for Model<C> {
+    fn default() -> Self { Model::new() }
+}
@@ -8,7 +8,7 @@
 
 use {
     batsmt_core::{
-        ast_u32::{self, AST, }, gc, AstView,
+        ast_u32::{self, AST, }, gc, stats, AstView,
         ast::{self, AstMap, iter_dag::State as AstIter},
     },
     fxhash::FxHashSet,
@@ -79,6 +79,8 @@ pub struct Tseitin<C:Ctx> {
     tmp_ast: Vec<AST>, // for arguments
     cs: TheoryClauseSet<C>, // clauses
     lits: FxHashSet<TheoryLit<C>>, // lits
+    n_formulas: stats::Counter,
+    n_clauses: stats::Counter,
 }
 
 /// Temporary structure
@@ -295,6 +297,8 @@ impl<C> Tseitin<C> where C: Ctx {
             iter: ast::iter_dag::new(),
             simp_map: ast::HashMap::new(),
             cs: TheoryClauseSet::new(),
+            n_formulas: stats::counter("tseitin.formulas"),
+            n_clauses: stats::counter("tseitin.clauses"),
         }
     }
 
@@ -325,6 +329,28 @@ impl<C> Tseitin<C> where C: Ctx {
         &mut self, m: &mut C, lit_map: &mut LM, t: AST
     ) -> (impl Iterator<Item=TheoryClauseRef<C>>, impl Iterator<Item=&TheoryLit<C>>)
         where LM: LitMap<C::B>
+    {
+        self.clauses_(m, lit_map, t, &[])
+    }
+
+    /// Same as `clauses`, but `t` is only asserted under the literals `guards`.
+    ///
+    /// The definitions of the connectives inside `t` are unconditional
+    /// (so they can be cached safely), but the toplevel clause
+    /// becomes `¬guard1 ∨ … ∨ ¬guardn ∨ t`.
+    /// This is useful for selector or activation literals.
+    pub fn clauses_guarded<LM>(
+        &mut self, m: &mut C, lit_map: &mut LM, t: AST, guards: &[TheoryLit<C>]
+    ) -> (impl Iterator<Item=TheoryClauseRef<C>>, impl Iterator<Item=&TheoryLit<C>>)
+        where LM: LitMap<C::B>
+    {
+        self.clauses_(m, lit_map, t, guards)
+    }
+
+    fn clauses_<LM>(
+        &mut self, m: &mut C, lit_map: &mut LM, t: AST, guards: &[TheoryLit<C>]
+    ) -> (impl Iterator<Item=TheoryClauseRef<C>>, impl Iterator<Item=&TheoryLit<C>>)
+        where LM: LitMap<C::B>
     {
         // first, simplify to flatten connectives and remove `distinct`
         let t = self.simplify(m, t);
@@ -445,11 +471,13 @@ impl<C> Tseitin<C> where C: Ctx {
         });
 
         {
-            // unit clause asserting that `t` is true
+            // unit clause asserting that `t` is true (under `guard`)
             let mut lmb = LitMapB{lit_map, m};
             let top_lit = lmb.term_to_lit(&t);
-            self.cs.push(&[top_lit]);
+            self.cs.push_iter(guards.iter().map(|g| !g.clone()).chain(Some(top_lit)));
         }
+        self.n_formulas.incr();
+        self.n_clauses.add(self.cs.len() as u64);
 
         (self.cs.iter(), self.lits.iter())
     }